@@ -1,17 +1,40 @@
-#![allow(dead_code)]
-use accel_data::AccelData;
-use adxl355::{Accelerometer, Adxl355, Config as ADXLConfig, ODR_LPF, Range};
+use crate::interrupt::{InterruptSource, RppalInterrupt};
+use accel_data::{
+    AccelData, Backoff, Command, ErrorKind, Histogram, RateMeter, SensorInfo, Status, Units,
+};
+use adxl355::{
+    Accelerometer, Adxl355, AxisTransform, Config as ADXLConfig, Diagnostics, Error as AccelError,
+    F32x3, HPF_CORNER, ODR_LPF, Range, RawAccelerometer, STANDARD_GRAVITY, SharedAdxl355,
+};
 use atomic_time::AtomicOptionInstant;
-use rppal::gpio::{Gpio, InputPin};
+use embedded_hal::spi::SpiBus;
+use rppal::gpio::Gpio;
 use rppal::spi::{Bus, Mode, SlaveSelect, Spi};
+use serde::Serialize;
 use std::error::Error;
+use std::fmt::Debug;
 use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicU32, AtomicUsize, Ordering};
 use std::thread;
 use std::time::{Duration, Instant};
-use tokio::sync::broadcast::Sender;
+use tokio::sync::{broadcast::Sender, mpsc, watch};
 
-const ACCEL_ODR: ODR_LPF = ODR_LPF::ODR_1000_Hz;
+/// `rppal` equivalent of [`adxl355::SPI_MODE`]
+fn spi_mode() -> Mode {
+    use embedded_hal::spi::{Phase, Polarity};
+    match (adxl355::SPI_MODE.polarity, adxl355::SPI_MODE.phase) {
+        (Polarity::IdleLow, Phase::CaptureOnFirstTransition) => Mode::Mode0,
+        (Polarity::IdleLow, Phase::CaptureOnSecondTransition) => Mode::Mode1,
+        (Polarity::IdleHigh, Phase::CaptureOnFirstTransition) => Mode::Mode2,
+        (Polarity::IdleHigh, Phase::CaptureOnSecondTransition) => Mode::Mode3,
+    }
+}
+
+/// Output data period of `odr`
+pub fn sample_period(odr: ODR_LPF) -> Duration {
+    Duration::from_micros(get_odr(odr) as u64)
+}
 
 /// Convert ODR_LPF to microseconds
 const fn get_odr(odr: ODR_LPF) -> u32 {
@@ -30,244 +53,1734 @@ const fn get_odr(odr: ODR_LPF) -> u32 {
     }
 }
 
-#[derive(Debug, Clone)]
+/// Get the supported output data rate closest to `hz`
+fn odr_from_hz(hz: f32) -> ODR_LPF {
+    ODR_LPF::all()
+        .iter()
+        .copied()
+        .min_by(|a, b| {
+            let da = (f32::from(*a) - hz).abs();
+            let db = (f32::from(*b) - hz).abs();
+            da.total_cmp(&db)
+        })
+        .unwrap_or_default()
+}
+
+/// Cap `odr` to the fastest supported output data rate not above `max_hz`,
+/// or the slowest one if `max_hz` is below all of them
+pub fn cap_odr(odr: ODR_LPF, max_hz: Option<f32>) -> ODR_LPF {
+    let Some(max_hz) = max_hz else {
+        return odr;
+    };
+    let limit = f32::from(odr).min(max_hz);
+    ODR_LPF::all()
+        .iter()
+        .copied()
+        .find(|odr| f32::from(*odr) <= limit)
+        .unwrap_or(ODR_LPF::ODR_3_906_Hz)
+}
+
+/// Get the range for a full scale value in g
+fn range_from_g(g: u8) -> Option<Range> {
+    match g {
+        2 => Some(Range::_2G),
+        4 => Some(Range::_4G),
+        8 => Some(Range::_8G),
+        _ => None,
+    }
+}
+
+/// Reconfigure a running device. The device is put in standby for the
+/// register writes and restarted afterwards, also if they failed. Output data
+/// rates are capped to `max_rate` Hz. Returns the first error, as sent back
+/// to the client
+pub fn apply_command(
+    index: u32,
+    device: &mut Adxl355<Spi>,
+    cmd: Command,
+    max_rate: Option<f32>,
+) -> Result<(), String> {
+    log::info!("[ACCEL] Device {index} applying command {cmd:?}");
+    device.stop().map_err(|e| e.to_string())?;
+    let res = match cmd {
+        Command::SetOdr { hz, .. } => {
+            let closest = odr_from_hz(hz);
+            let odr = cap_odr(closest, max_rate);
+            if odr != closest {
+                log::warn!("[ACCEL] Device {index} output data rate capped to {odr}");
+            }
+            device.set_odr(odr).map_err(|e| e.to_string())
+        }
+        Command::SetRange { g, .. } => match range_from_g(g) {
+            Some(range) => device.set_range(range).map_err(|e| e.to_string()),
+            None => Err(format!("invalid range: {g} g")),
+        },
+        Command::SetHpf { corner, .. } => match HPF_CORNER::try_from(corner) {
+            Ok(hpf) => device.set_hpf(hpf).map_err(|e| e.to_string()),
+            Err(_) => Err(format!("invalid HPF corner: {corner}")),
+        },
+    };
+    let started = device.start().map_err(|e| e.to_string());
+    res.and(started)
+        .inspect_err(|e| log::error!("[ACCEL] Device {index} failed to apply command: {e}"))
+}
+
+/// Source of the sample times [`AccelData::gap`] is derived from
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum TimestampMode {
+    /// Gaps are nominal output data periods; only the first gap, from the
+    /// synchronization point, is measured at the start of the interrupt
+    /// callback. Includes interrupt dispatch jitter, typically tens to
+    /// hundreds of microseconds.
+    #[default]
+    Nominal,
+    /// Gaps are measured between the kernel timestamps of the DRDY edges.
+    /// Free of dispatch jitter, but limited by the GPIO interrupt latency of
+    /// the kernel (a few microseconds). Edges the callback could not keep up
+    /// with are folded into the next gap.
+    Edge,
+}
+
+impl std::str::FromStr for TimestampMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "nominal" => Ok(TimestampMode::Nominal),
+            "edge" => Ok(TimestampMode::Edge),
+            _ => Err(format!(
+                "invalid timestamp mode '{s}', expected nominal or edge"
+            )),
+        }
+    }
+}
+
+/// Sample timing settings
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Timing {
+    /// Source of the sample times
+    pub mode: TimestampMode,
+    /// Estimated fixed delay from the DRDY edge to the interrupt callback,
+    /// subtracted from the callback times
+    pub latency: Duration,
+}
+
+impl Timing {
+    /// Time in microseconds since the edge at `last`, updating it to the
+    /// edge at `timestamp`; `None` unless in [`TimestampMode::Edge`]
+    fn edge_gap(&self, last: &mut Option<Duration>, timestamp: Option<Duration>) -> Option<u32> {
+        match self.mode {
+            TimestampMode::Nominal => None,
+            TimestampMode::Edge => {
+                let timestamp = timestamp?;
+                last.replace(timestamp)
+                    .map(|last| timestamp.saturating_sub(last).as_micros() as u32)
+            }
+        }
+    }
+}
+
+/// Per-sensor sampling settings
+#[derive(Debug, Copy, Clone)]
+pub struct Sampling {
+    /// Sample timing
+    pub timing: Timing,
+    /// Interval of the `[STATS]` log lines
+    pub stats_interval: Duration,
+    /// Read the die temperature once every this many samples and attach
+    /// the latest reading to every sample (0 disables). Attached values are
+    /// at most `temp_every` output data periods old.
+    pub temp_every: u32,
+    /// Units of the forwarded samples
+    pub units: Units,
+    /// Read STATUS with every sample and mark re-reads of a sample already
+    /// forwarded, see [`AccelData::fresh`]
+    pub freshness: bool,
+    /// Samples read and dropped after every start of the sensor, to keep
+    /// filter transients out of the forwarded data
+    pub discard: u32,
+    /// Synchronization point shared by all sensors: the first gap of every
+    /// sensor and all [`AccelData::time`] stamps are measured from it. The
+    /// stamps are taken at the start of each sensor's callback, so sensors
+    /// stay skewed by their independent interrupt dispatch latency (tens to
+    /// hundreds of microseconds, less the fixed `timing.latency`), or by up
+    /// to one sample period when polled.
+    pub epoch: Instant,
+    /// CPU core the sampling threads are pinned to, Linux only
+    pub core: Option<usize>,
+}
+
+impl Sampling {
+    /// [`AccelData::time`] of `instant`
+    fn time(&self, instant: Instant) -> u64 {
+        instant.saturating_duration_since(self.epoch).as_micros() as u64
+    }
+
+    /// Pin the calling sampling thread of sensor `index` to [`Sampling::core`]
+    /// if set
+    fn pin_thread(&self, index: u32) {
+        let Some(core) = self.core else {
+            return;
+        };
+        match crate::affinity::pin_current_thread(core) {
+            Ok(()) => log::info!("[ACCEL] Device {index} sampling thread pinned to core {core}"),
+            Err(e) => log::warn!("[ACCEL] Device {index} sampling thread not pinned: {e}"),
+        }
+    }
+}
+
+/// Read a sample in `units`, and if `freshness` is set whether STATUS
+/// reported it as new. Raw counts skip the scaling to g, leaving only an
+/// exact integer to float conversion per axis
+fn read_sample<SPI, E>(
+    device: &mut Adxl355<SPI>,
+    units: Units,
+    freshness: bool,
+) -> Result<(F32x3, Option<bool>), AccelError<E>>
+where
+    SPI: SpiBus<u8, Error = E>,
+    E: Debug,
+{
+    if freshness {
+        // STATUS is read in the same burst as the sample, costing four more
+        // bytes per transfer rather than another transaction
+        let frame = device.read_frame()?;
+        let v = device.orientation().apply(frame.sample);
+        let scale = match units {
+            Units::G => device.scale(),
+            Units::Mps2 => device.scale() * STANDARD_GRAVITY,
+            Units::Counts => 1.0,
+        };
+        let sample = F32x3::new(v.x as f32 * scale, v.y as f32 * scale, v.z as f32 * scale);
+        return Ok((sample, Some(frame.data_ready())));
+    }
+    let sample = match units {
+        Units::G => device.accel_norm(),
+        Units::Mps2 => device.accel_mps2(),
+        Units::Counts => device
+            .accel_raw()
+            .map(|raw| F32x3::new(raw.x as f32, raw.y as f32, raw.z as f32)),
+    }?;
+    Ok((sample, None))
+}
+
+/// Interleaves temperature reads with the acceleration reads
+#[derive(Debug)]
+struct TempSampler {
+    every: u32,
+    count: u32,
+    latest: Option<f32>,
+}
+
+impl TempSampler {
+    fn new(every: u32) -> Self {
+        Self {
+            every,
+            count: 0,
+            latest: None,
+        }
+    }
+
+    /// Latest temperature, read from `device` every `every` calls
+    fn sample<SPI, E>(&mut self, device: &mut Adxl355<SPI>) -> Option<f32>
+    where
+        SPI: SpiBus<u8, Error = E>,
+        E: Debug,
+    {
+        if self.every == 0 {
+            return None;
+        }
+        if self.count == 0 {
+            self.latest = device.read_temp_celsius().ok();
+        }
+        self.count = (self.count + 1) % self.every;
+        self.latest
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct AccelDesc {
     pub bus: Bus,
     pub ss: SlaveSelect,
     pub drdy: u8,
+    /// Mounting orientation, applied to every reading
+    pub orientation: AxisTransform,
+    /// Output data rate, overriding the one shared by all sensors
+    pub odr: Option<ODR_LPF>,
+    /// Measurement range, overriding the one shared by all sensors
+    pub range: Option<Range>,
+    /// High pass filter corner, overriding the one shared by all sensors
+    pub hpf: Option<HPF_CORNER>,
+    /// Human readable name, sent to clients in the handshake
+    pub name: Option<String>,
+}
+
+impl AccelDesc {
+    /// Configuration of this sensor: `base` with the overrides applied
+    pub fn config(&self, base: &ADXLConfig) -> ADXLConfig {
+        let mut config = *base;
+        if let Some(odr) = self.odr {
+            config.odr(odr);
+        }
+        if let Some(range) = self.range {
+            config.range(range);
+        }
+        if let Some(hpf) = self.hpf {
+            config.hpf(hpf);
+        }
+        config
+    }
+}
+
+/// Check that no two sensors share a chip select or a DRDY pin, listing
+/// every conflict
+pub fn validate_descs(acceldescs: &[AccelDesc]) -> Result<(), String> {
+    let mut conflicts = Vec::new();
+    for (i, a) in acceldescs.iter().enumerate() {
+        for (j, b) in acceldescs.iter().enumerate().skip(i + 1) {
+            if a.bus == b.bus && a.ss == b.ss {
+                conflicts.push(format!(
+                    "devices {i} and {j} share bus {:?} chip select {:?}",
+                    a.bus, a.ss
+                ));
+            }
+            if a.drdy == b.drdy {
+                conflicts.push(format!("devices {i} and {j} share DRDY pin {}", a.drdy));
+            }
+        }
+    }
+    if conflicts.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("Invalid sensor list: {}", conflicts.join(", ")))
+    }
+}
+
+/// Sample statistics, reported once per interval
+struct SampleStats {
+    meter: RateMeter,
+    /// Gaps between samples in the current interval
+    gaps: Histogram,
+    /// Samples since the sensor was brought up, never reset. At 4 kHz a
+    /// `u64` lasts for over a hundred million years
+    total: u64,
+    /// Sample rate measured over the last complete interval
+    rate_hz: Option<f32>,
 }
 
 struct AccelDataRate {
-    last: AtomicOptionInstant,
-    count: AtomicUsize,
+    /// Sample rate and gap statistics
+    stats: Mutex<SampleStats>,
+    /// Time of the most recent sample
+    latest: AtomicOptionInstant,
+    /// Synchronization point the next forwarded sample measures its gap
+    /// from, cleared by that sample
+    sync: AtomicOptionInstant,
+    /// Interleaved temperature reads
+    temp: Mutex<TempSampler>,
+    /// Sequence number of the next sample, counting failed reads too.
+    /// Wraps after about 12 days at 4 kHz, see [`AccelData::seq`]
+    seq: AtomicU32,
+    /// Startup samples still to be discarded
+    discard: AtomicU32,
+    /// Put in standby by [`accelerator_idle`], or still settling after it
+    /// woke: samples are read to deassert DRDY, but not forwarded
+    standby: AtomicBool,
+    /// Failures since the last [`Status::Error`] records
+    faults: Mutex<Vec<Fault>>,
+    /// See [`SensorSetup::clients`]
+    clients: Option<Arc<AtomicUsize>>,
+    /// Output data rate of the device as [`ODR_LPF::val`], refreshed by
+    /// every sample. Read by the async tasks instead of the device, whose
+    /// lock a sampling thread hung in a transfer holds
+    odr: AtomicU8,
+}
+
+/// Occurrences of an [`ErrorKind`] since its last [`Status::Error`] record
+struct Fault {
+    kind: ErrorKind,
+    count: u32,
+    /// [`AccelData::time`] of the latest occurrence
+    time: u64,
+    /// Description of the latest occurrence
+    message: String,
+}
+
+impl AccelDataRate {
+    fn new(sampling: &Sampling, clients: Option<Arc<AtomicUsize>>, odr: ODR_LPF) -> Self {
+        Self {
+            stats: Mutex::new(SampleStats {
+                meter: RateMeter::new(sampling.stats_interval),
+                gaps: Histogram::new(),
+                total: 0,
+                rate_hz: None,
+            }),
+            latest: AtomicOptionInstant::none(),
+            sync: AtomicOptionInstant::none(),
+            temp: Mutex::new(TempSampler::new(sampling.temp_every)),
+            seq: AtomicU32::new(0),
+            discard: AtomicU32::new(sampling.discard),
+            standby: AtomicBool::new(false),
+            faults: Mutex::new(Vec::new()),
+            clients,
+            odr: AtomicU8::new(odr.val()),
+        }
+    }
+
+    /// Current output data rate of the device
+    fn odr(&self) -> ODR_LPF {
+        ODR_LPF::try_from(self.odr.load(Ordering::Relaxed)).unwrap_or_default()
+    }
+
+    /// Whether no clients are connected to send the samples to
+    fn idle(&self) -> bool {
+        self.clients
+            .as_ref()
+            .is_some_and(|clients| clients.load(Ordering::Relaxed) == 0)
+    }
+
+    /// Count an occurrence of `kind` at `time`, for [`accelerator_status`]
+    fn fault(&self, kind: ErrorKind, time: u64, message: String) {
+        let Ok(mut faults) = self.faults.lock() else {
+            return;
+        };
+        match faults.iter_mut().find(|fault| fault.kind == kind) {
+            Some(fault) => {
+                fault.count = fault.count.saturating_add(1);
+                fault.time = time;
+                fault.message = message;
+            }
+            None => faults.push(Fault {
+                kind,
+                count: 1,
+                time,
+                message,
+            }),
+        }
+    }
+
+    /// Take the failures counted since the last call as [`Status::Error`]
+    /// records of sensor `idx`
+    fn take_faults(&self, idx: u32) -> Vec<Status> {
+        let faults = self
+            .faults
+            .lock()
+            .map(|mut faults| std::mem::take(&mut *faults))
+            .unwrap_or_default();
+        faults
+            .into_iter()
+            .map(|fault| Status::Error {
+                idx: Some(idx),
+                kind: fault.kind,
+                count: fault.count,
+                time: fault.time,
+                message: fault.message,
+            })
+            .collect()
+    }
+}
+
+/// State of a sensor for bug reports, logged as JSON on `SIGUSR1`
+#[derive(Debug, Serialize)]
+pub struct SensorDiagnostics {
+    /// Sensor index, as sent in [`AccelData::idx`]
+    pub idx: u32,
+    /// SPI bus and chip select
+    pub bus: String,
+    /// GPIO pin of the DRDY interrupt
+    pub drdy: u8,
+    /// Mounting orientation
+    pub orientation: String,
+    /// Device registers, or the error reading them
+    pub device: Result<Diagnostics, String>,
+    /// Sample rate measured over the last stats interval
+    pub rate_hz: Option<f32>,
+    /// Samples since the sensor was brought up
+    pub total: u64,
+}
+
+/// Handle to a running [`Sensor`], for the tasks that read its registers or
+/// reconfigure it while it samples
+#[derive(Clone)]
+pub struct SensorHandle {
+    /// Sensor index, as sent in [`AccelData::idx`]
+    pub index: u32,
+    /// Handle to the device, shared with the sampling interrupt
+    pub device: SharedAdxl355<Spi>,
+    desc: AccelDesc,
+    datarate: Arc<AccelDataRate>,
+}
+
+impl SensorHandle {
+    /// Read the device registers and the sampling statistics
+    pub fn collect(&self) -> SensorDiagnostics {
+        let (rate_hz, total) = self
+            .datarate
+            .stats
+            .lock()
+            .map(|stats| (stats.rate_hz, stats.total))
+            .unwrap_or_default();
+        SensorDiagnostics {
+            idx: self.index,
+            bus: format!("{:?}/{:?}", self.desc.bus, self.desc.ss),
+            drdy: self.desc.drdy,
+            orientation: self.desc.orientation.to_string(),
+            device: self
+                .device
+                .with(|device| device.diagnostics())
+                .map_err(|e| e.to_string()),
+            rate_hz,
+            total,
+        }
+    }
+}
+
+/// What triggers the sampling of a [`Sensor`]
+enum Trigger {
+    /// [`accelerator_loop`] thread waiting for DRDY edges, stopped by setting
+    /// the flag
+    Interrupt(Arc<AtomicBool>, thread::JoinHandle<RppalInterrupt>),
+    /// [`accelerator_task`] thread, stopped by setting the flag
+    Poll(Arc<AtomicBool>, thread::JoinHandle<()>),
+}
+
+/// Accelerometer brought up by [`accelerator_init`]
+pub struct Sensor {
+    /// Sensor index, as sent in [`AccelData::idx`]
+    pub index: u32,
+    /// Descriptor the sensor was brought up from
+    pub desc: AccelDesc,
+    trigger: Trigger,
+    /// Handle to the device, shared with the sampling interrupt
+    pub device: SharedAdxl355<Spi>,
+    datarate: Arc<AccelDataRate>,
+    sampling: Sampling,
+    /// Delays between failed restarts
+    backoff: Backoff,
+    /// Earliest time of the next restart attempt after a failure
+    retry_at: Option<Instant>,
+    /// Put in standby while no clients are connected
+    standby: bool,
+    /// Since when the measured rate is too low, see [`AdaptiveOdr`]
+    slow_since: Option<Instant>,
+}
+
+impl Sensor {
+    /// Stop sampling: join the sampling thread, clearing the DRDY interrupt,
+    /// then put the device in standby
+    pub fn stop(self) {
+        match self.trigger {
+            Trigger::Interrupt(stop, handle) => {
+                stop.store(true, Ordering::Relaxed);
+                match handle.join() {
+                    Ok(irq) => {
+                        let pin = irq.into_inner();
+                        log::info!("Cleared interrupt for {pin:?}");
+                    }
+                    Err(_) => {
+                        log::error!("[ACCEL] Sampling thread of device {} panicked", self.index)
+                    }
+                }
+            }
+            Trigger::Poll(stop, handle) => {
+                stop.store(true, Ordering::Relaxed);
+                if handle.join().is_err() {
+                    log::error!("[ACCEL] Polling thread of device {} panicked", self.index);
+                }
+            }
+        }
+        if let Err(e) = self.device.with(|device| device.stop()) {
+            log::warn!(
+                "[ACCEL] Failed to put device {} in standby: {e}",
+                self.index
+            );
+        }
+    }
+
+    /// Handle for the status, command and diagnostics tasks
+    pub fn handle(&self) -> SensorHandle {
+        SensorHandle {
+            index: self.index,
+            desc: self.desc.clone(),
+            device: self.device.clone(),
+            datarate: self.datarate.clone(),
+        }
+    }
+
+    /// Current configuration of the sensor, for the client handshake
+    pub fn info(&self) -> SensorInfo {
+        self.device.with(|device| sensor_info(self.index, device))
+    }
+}
+
+/// Log a transfer of the driver, the trace hook of `--trace-spi`
+pub fn trace_spi(transfer: &adxl355::Transfer<'_>) {
+    log::trace!(
+        "[SPI] {:?} reg={:#04x} bytes={:02x?} ok={}",
+        transfer.direction,
+        transfer.register,
+        transfer.bytes,
+        transfer.ok
+    );
 }
 
+/// Open, configure and start the accelerometer, waiting for it to settle.
+/// `config` is the configuration shared by all sensors, overridden by the
+/// descriptor
+fn open_device(acceldesc: &AccelDesc, config: &ADXLConfig) -> Result<Adxl355<Spi>, Box<dyn Error>> {
+    let spi = Spi::new(
+        acceldesc.bus,
+        acceldesc.ss,
+        1_000_000, // 1 MHz
+        spi_mode(),
+    )
+    .inspect_err(|_| log::error!("Failed to initialize SPI on bus {:?}", acceldesc.bus))?;
+    let mut accel = adxl355::Adxl355::new(spi, &acceldesc.config(config)).inspect_err(|_| {
+        log::error!(
+            "Failed to create ADXL355 instance on bus {:?}",
+            acceldesc.bus
+        )
+    })?;
+    accel.set_orientation(acceldesc.orientation);
+    accel
+        .start()
+        .inspect_err(|e| log::error!("Failed to start accel: {e}"))?;
+    let settling = accel.settling_time();
+    log::info!("Waiting {settling:?} for accel to settle");
+    thread::sleep(settling);
+    if let Ok(value) = accel.accel_norm() {
+        log::info!("Accelerometer {acceldesc:?} data: {value:?}");
+    }
+    Ok(accel)
+}
+
+/// Probe each accelerometer and print its identification and current
+/// configuration, without reconfiguring it
+pub fn list_devices(acceldescs: &[AccelDesc]) {
+    println!(
+        "{:<6} {:<6} {:<5} {:<10} {:<9} {:<5} {:<10} {:<12} {:<6} {:<8}",
+        "bus", "ss", "drdy", "devid", "partid", "rev", "odr", "hpf", "range", "temp"
+    );
+    for acceldesc in acceldescs {
+        let probe = || -> Result<_, Box<dyn Error>> {
+            let spi = Spi::new(acceldesc.bus, acceldesc.ss, 1_000_000, spi_mode())?;
+            let mut accel = Adxl355::attach(spi)?;
+            Ok((accel.read_ids()?, accel.read_temp_celsius()?, accel))
+        };
+        let prefix = format!(
+            "{:<6} {:<6} {:<5}",
+            format!("{:?}", acceldesc.bus),
+            format!("{:?}", acceldesc.ss),
+            acceldesc.drdy
+        );
+        match probe() {
+            Ok((ids, temp, accel)) => println!(
+                "{prefix} {:<10} {:<9} {:<5} {:<10} {:<12} {:<6} {:<8}",
+                format!("{:#04x}/{:#04x}", ids.devid_ad, ids.devid_mst),
+                format!("{:#04x}", ids.partid),
+                ids.revid,
+                accel.odr().to_string(),
+                format!("{:?}", accel.hpf()),
+                accel.range().to_string(),
+                format!("{temp:.1} °C"),
+            ),
+            Err(e) => println!("{prefix} not responding: {e}"),
+        }
+    }
+}
+
+/// Start the [`accelerator_loop`] thread of a device on its DRDY pin
+fn spawn_interrupt(
+    index: u32,
+    mut irq: RppalInterrupt,
+    device: SharedAdxl355<Spi>,
+    sink: Sender<AccelData>,
+    datarate: Arc<AccelDataRate>,
+    sampling: Sampling,
+) -> std::io::Result<(Arc<AtomicBool>, thread::JoinHandle<RppalInterrupt>)> {
+    let stop = Arc::new(AtomicBool::new(false));
+    let handle = thread::Builder::new()
+        .name(format!("accel-irq-{index}"))
+        .spawn({
+            let stop = stop.clone();
+            move || {
+                if let Err(e) =
+                    accelerator_loop(index, device, &mut irq, sink, &datarate, sampling, &stop)
+                {
+                    log::error!("[ACCEL] Device {index} DRDY interrupt failed: {e}");
+                }
+                irq
+            }
+        })?;
+    Ok((stop, handle))
+}
+
+/// Settings sensors are brought up with, at startup and on reload
+#[derive(Clone)]
+pub struct SensorSetup {
+    /// Configuration shared by all sensors, overridden by their descriptors
+    pub config: ADXLConfig,
+    /// Sampling settings
+    pub sampling: Sampling,
+    /// Channel the samples are sent on
+    pub sink: Sender<AccelData>,
+    /// Clients the samples are read for, see
+    /// [`accel_data::ServerConfig::clients`]. While none are connected the
+    /// samples are neither scaled nor sent; `None` always sends them, for
+    /// consumers in the daemon that need every sample
+    pub clients: Option<Arc<AtomicUsize>>,
+}
+
+/// Open sensor `index` and start its DRDY interrupt thread, or start a polling
+/// thread if the pin is unavailable. The first gap is measured from `sync`
+fn start_sensor(
+    index: u32,
+    acceldesc: &AccelDesc,
+    setup: &SensorSetup,
+    gpio: &Result<Gpio, rppal::gpio::Error>,
+    sync: Instant,
+) -> Result<Sensor, String> {
+    let (sink, sampling) = (&setup.sink, setup.sampling);
+    let device = match open_device(acceldesc, &setup.config) {
+        Ok(device) => SharedAdxl355::new(device),
+        Err(e) => return Err(format!("device {index} on bus {:?} ({e})", acceldesc.bus)),
+    };
+    let odr = device.with(|device| device.odr());
+    let datarate = Arc::new(AccelDataRate::new(&sampling, setup.clients.clone(), odr));
+    datarate.sync.store(Some(sync), Ordering::Relaxed);
+    let attach = |gpio: &Gpio| -> Result<_, String> {
+        let drdy = gpio
+            .get(acceldesc.drdy)
+            .and_then(|pin| RppalInterrupt::new(pin.into_input()))
+            .map_err(|e| e.to_string())?;
+        spawn_interrupt(
+            index,
+            drdy,
+            device.clone(),
+            sink.clone(),
+            datarate.clone(),
+            sampling,
+        )
+        .map_err(|e| format!("sampling thread: {e}"))
+    };
+    let drdy = match gpio {
+        Ok(gpio) => attach(gpio),
+        Err(e) => Err(e.to_string()),
+    };
+    let trigger = match drdy {
+        Ok((stop, handle)) => {
+            log::info!(
+                "[ACCEL] Device {index} on bus {:?}: sampling on DRDY pin {}",
+                acceldesc.bus,
+                acceldesc.drdy
+            );
+            Trigger::Interrupt(stop, handle)
+        }
+        Err(e) => {
+            log::warn!(
+                "[ACCEL] Device {index} on bus {:?}: DRDY pin {} unavailable ({e}), polling instead",
+                acceldesc.bus,
+                acceldesc.drdy
+            );
+            let stop = Arc::new(AtomicBool::new(false));
+            let handle = thread::Builder::new()
+                .name(format!("accel-poll-{index}"))
+                .spawn({
+                    let (device, sink, datarate, stop) =
+                        (device.clone(), sink.clone(), datarate.clone(), stop.clone());
+                    move || accelerator_task(index, device, sink, datarate, sampling, stop)
+                })
+                .map_err(|e| {
+                    log::error!("[ACCEL] Failed to start polling thread: {e}");
+                    format!(
+                        "device {index} on bus {:?} (polling thread: {e})",
+                        acceldesc.bus
+                    )
+                })?;
+            Trigger::Poll(stop, handle)
+        }
+    };
+    Ok(Sensor {
+        index,
+        desc: acceldesc.clone(),
+        trigger,
+        device,
+        datarate,
+        sampling,
+        backoff: Backoff::new(Duration::from_millis(500), Duration::from_secs(30)),
+        retry_at: None,
+        standby: false,
+        slow_since: None,
+    })
+}
+
+/// Attempts to open the GPIO controller at startup, which may not be ready
+/// yet early in boot
+const GPIO_ATTEMPTS: u32 = 5;
+
+/// Open the GPIO controller, retrying with backoff up to [`GPIO_ATTEMPTS`]
+/// times. Gives up early, with the last error, once `running` is cleared
+fn open_gpio(running: &AtomicBool) -> Result<Gpio, rppal::gpio::Error> {
+    let mut backoff = Backoff::new(Duration::from_millis(250), Duration::from_secs(2));
+    let mut attempt = 1;
+    loop {
+        let e = match Gpio::new() {
+            Ok(gpio) => {
+                if attempt > 1 {
+                    log::info!("[ACCEL] GPIO available after {attempt} attempts");
+                }
+                return Ok(gpio);
+            }
+            Err(e) if attempt >= GPIO_ATTEMPTS => return Err(e),
+            Err(e) => e,
+        };
+        let delay = backoff.next_delay();
+        log::warn!(
+            "[ACCEL] GPIO unavailable ({e}), attempt {attempt} of {GPIO_ATTEMPTS}, retrying in {delay:?}"
+        );
+        // sleep in slices to notice a shutdown
+        let until = Instant::now() + delay;
+        while let Some(left) = until.checked_duration_since(Instant::now()) {
+            if !running.load(Ordering::Relaxed) {
+                return Err(e);
+            }
+            thread::sleep(left.min(Duration::from_millis(50)));
+        }
+        attempt += 1;
+    }
+}
+
+/// Initialize the accelerometers and attach their DRDY interrupts. Sensors
+/// are polled if the GPIO controller stays unavailable; a shutdown while
+/// waiting for it aborts the initialization.
 pub fn accelerator_init(
     acceldescs: &[AccelDesc],
-    sink: Sender<AccelData>,
-) -> Result<Vec<InputPin>, Box<dyn Error>> {
+    setup: &SensorSetup,
+    require_all: bool,
+    running: &AtomicBool,
+) -> Result<Vec<Sensor>, Box<dyn Error>> {
     log::debug!(
         "AtomicOptionInstant is lock free? {}",
         AtomicOptionInstant::is_lock_free()
     );
-    let now = Instant::now(); // synchronization point
-    let gpio = Gpio::new()?;
-    let pins = acceldescs
+    let now = setup.sampling.epoch; // synchronization point
+    let gpio = open_gpio(running);
+    if !running.load(Ordering::Relaxed) {
+        return Err("shutdown requested during initialization".into());
+    }
+    let mut failed = Vec::new();
+    let sensors = acceldescs
         .iter()
         .enumerate()
         .filter_map(|(index, acceldesc)| {
-            if let Ok(drdy) = gpio.get(acceldesc.drdy) {
-                log::info!(
-                    "DRDY pin {} found, initializing accel on bus {:?}",
-                    acceldesc.drdy,
-                    acceldesc.bus
+            start_sensor(index as u32, acceldesc, setup, &gpio, now)
+                .inspect_err(|e| failed.push(e.clone()))
+                .ok()
+        })
+        .collect::<Vec<_>>();
+    if !failed.is_empty() {
+        let failed = format!(
+            "{} of {} sensors failed to initialize: {}",
+            failed.len(),
+            acceldescs.len(),
+            failed.join(", ")
+        );
+        if require_all {
+            sensors.into_iter().for_each(Sensor::stop);
+            return Err(failed.into());
+        }
+        log::warn!("[ACCEL] {failed}");
+    }
+    if sensors.is_empty() {
+        log::warn!("No accelerometers found, exiting thread.");
+    }
+    Ok(sensors)
+}
+
+/// Longest wait of the supervisor for the lock of a device, which a sampling
+/// thread hung in a transfer holds
+const DEVICE_LOCK_WAIT: Duration = Duration::from_millis(500);
+
+/// Run `f` with exclusive access to `device`, or return `None` if its lock
+/// is not released within [`DEVICE_LOCK_WAIT`]
+fn with_device_within<R>(
+    device: &SharedAdxl355<Spi>,
+    f: impl FnOnce(&mut Adxl355<Spi>) -> R,
+) -> Option<R> {
+    let mut f = Some(f);
+    let until = Instant::now() + DEVICE_LOCK_WAIT;
+    loop {
+        if let Some(res) = device.try_with(|device| f.take().map(|f| f(device))) {
+            return res;
+        }
+        if Instant::now() > until {
+            return None;
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+/// Re-open a sensor in place, keeping its device handle valid for other users
+fn accelerator_restart(sensor: &mut Sensor, config: &ADXLConfig) -> Result<(), Box<dyn Error>> {
+    // the sampling thread keeps going and picks up the new device
+    let accel = open_device(&sensor.desc, config)?;
+    let odr = accel.odr();
+    with_device_within(&sensor.device, |device| *device = accel)
+        .ok_or_else(|| format!("device busy for {DEVICE_LOCK_WAIT:?}"))?;
+    sensor
+        .datarate
+        .discard
+        .store(sensor.sampling.discard, Ordering::Relaxed);
+    sensor.datarate.odr.store(odr.val(), Ordering::Relaxed);
+    sensor
+        .datarate
+        .sync
+        .store(Some(Instant::now()), Ordering::Relaxed);
+    Ok(())
+}
+
+/// Default watchdog timeout: 10 output data periods, but at least 100 ms to
+/// tolerate scheduling jitter
+pub fn default_watchdog_timeout(odr: ODR_LPF) -> Duration {
+    Duration::from_micros(10 * get_odr(odr) as u64).max(Duration::from_millis(100))
+}
+
+/// Stall timeout of the watchdog of [`accelerator_supervisor`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Watchdog {
+    /// Sensors are never restarted
+    Disabled,
+    /// Restart sensors that produced no samples for this long
+    Timeout(Duration),
+    /// [`default_watchdog_timeout`] of the current output data rate of each
+    /// sensor
+    Default,
+}
+
+impl Watchdog {
+    /// Stall timeout of `sensor`
+    fn timeout(&self, sensor: &Sensor) -> Option<Duration> {
+        match self {
+            Watchdog::Disabled => None,
+            Watchdog::Timeout(timeout) => Some(*timeout),
+            Watchdog::Default => Some(default_watchdog_timeout(sensor.datarate.odr())),
+        }
+    }
+}
+
+/// Standby of idle sensors, see [`accelerator_idle`]
+#[derive(Debug, Clone)]
+pub struct IdleShutdown {
+    /// Time without clients before the sensors are put in standby
+    pub timeout: Duration,
+    /// Clients connected to the servers, see
+    /// [`accel_data::ServerConfig::clients`]
+    pub clients: Arc<AtomicUsize>,
+}
+
+/// Measured sample rate below which a sensor does not keep up with its
+/// output data rate, as a fraction of it
+const ADAPTIVE_MIN_RATIO: f32 = 0.9;
+
+/// Lowering of the output data rate of sensors the host cannot keep up
+/// with, see [`accelerator_adapt`]
+#[derive(Debug, Clone)]
+pub struct AdaptiveOdr {
+    /// Slowest output data rate the sensors are lowered to
+    pub floor: ODR_LPF,
+    /// Time the measured rate must stay too low before each step
+    pub sustain: Duration,
+    /// Channel the [`Status::RateReduced`] records are sent on
+    pub status: Sender<Status>,
+}
+
+/// What [`accelerator_supervisor`] does besides reloading the sensors
+#[derive(Debug, Clone)]
+pub struct Supervision {
+    /// Restart of stalled sensors
+    pub watchdog: Watchdog,
+    /// Standby of the sensors while no clients are connected
+    pub idle: Option<IdleShutdown>,
+    /// Lowering of output data rates the host cannot keep up with
+    pub adaptive: Option<AdaptiveOdr>,
+}
+
+/// Supervise the sensors while `running`: restart any that produced no
+/// samples for their watchdog timeout, put them in standby while no clients
+/// are connected if `idle` is set, lower the output data rate of those that
+/// do not keep up if `adaptive` is set, and reconcile them with every
+/// sensor list received on `reloads`, see [`accelerator_reload`]. The
+/// current sensors are published on `handles`. Returns the sensors on
+/// shutdown.
+pub async fn accelerator_supervisor(
+    mut sensors: Vec<Sensor>,
+    supervision: Supervision,
+    setup: SensorSetup,
+    mut reloads: mpsc::Receiver<Vec<AccelDesc>>,
+    handles: watch::Sender<Vec<SensorHandle>>,
+    running: Arc<AtomicBool>,
+) -> Vec<Sensor> {
+    let Supervision {
+        watchdog,
+        idle,
+        adaptive,
+    } = supervision;
+    match watchdog {
+        Watchdog::Disabled => {}
+        Watchdog::Timeout(timeout) => {
+            log::info!("[ACCEL] Watchdog started with timeout {timeout:?}")
+        }
+        Watchdog::Default => log::info!("[ACCEL] Watchdog started with per-sensor timeouts"),
+    }
+    if let Some(idle) = &idle {
+        log::info!(
+            "[ACCEL] Sensors go to standby after {:?} without clients",
+            idle.timeout
+        );
+    }
+    if let Some(adaptive) = &adaptive {
+        log::info!(
+            "[ACCEL] Output data rates are lowered down to {} when not kept up for {:?}",
+            adaptive.floor,
+            adaptive.sustain
+        );
+    }
+    let mut next_index = sensors.iter().map(|s| s.index + 1).max().unwrap_or(0);
+    // time the last client disconnected
+    let mut idle_since = None;
+    while running.load(Ordering::Relaxed) {
+        let mut period = sensors
+            .iter()
+            .filter_map(|sensor| watchdog.timeout(sensor))
+            .min()
+            .map_or(Duration::from_secs(1), |timeout| {
+                (timeout / 2).max(Duration::from_millis(10))
+            });
+        if idle.is_some() {
+            // wake promptly once a client connects
+            period = period.min(IDLE_POLL);
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(period) => {
+                if let Some(idle) = &idle {
+                    accelerator_idle(&mut sensors, idle, &mut idle_since).await;
+                }
+                if let Some(adaptive) = &adaptive {
+                    accelerator_adapt(&mut sensors, adaptive).await;
+                }
+                sensors = accelerator_watchdog(sensors, watchdog, &setup, &running).await;
+            }
+            Some(descs) = reloads.recv() => {
+                sensors = accelerator_reload(sensors, descs, &setup, &mut next_index).await;
+                handles.send_replace(sensors.iter().map(Sensor::handle).collect());
+            }
+        }
+    }
+    sensors
+}
+
+/// Put the sensors in standby once no clients were connected for the
+/// `idle` timeout, counting from `idle_since`, and wake them when one
+/// connects. Woken sensors forward samples once they settled, and after
+/// [`Sampling::discard`] more samples
+async fn accelerator_idle(
+    sensors: &mut [Sensor],
+    idle: &IdleShutdown,
+    idle_since: &mut Option<Instant>,
+) {
+    let connected = idle.clients.load(Ordering::Relaxed) > 0;
+    if connected {
+        *idle_since = None;
+    } else if idle_since.get_or_insert_with(Instant::now).elapsed() < idle.timeout {
+        return;
+    }
+    // sensors to put in standby, or to wake
+    let changed: Vec<_> = sensors
+        .iter_mut()
+        .filter(|sensor| sensor.standby == connected)
+        .map(|sensor| {
+            sensor.standby = !connected;
+            (sensor.handle(), sensor.sampling.discard)
+        })
+        .collect();
+    if changed.is_empty() {
+        return;
+    }
+    let timeout = idle.timeout;
+    let res = tokio::task::spawn_blocking(move || {
+        if !connected {
+            for (handle, _) in &changed {
+                handle.datarate.standby.store(true, Ordering::Relaxed);
+                match with_device_within(&handle.device, |device| device.stop()) {
+                    Some(Ok(())) => log::info!(
+                        "[ACCEL] Device {} in standby, no clients for {timeout:?}",
+                        handle.index
+                    ),
+                    Some(Err(e)) => log::warn!(
+                        "[ACCEL] Failed to put device {} in standby: {e}",
+                        handle.index
+                    ),
+                    None => log::warn!(
+                        "[ACCEL] Failed to put device {} in standby: busy for {DEVICE_LOCK_WAIT:?}",
+                        handle.index
+                    ),
+                }
+            }
+            return;
+        }
+        let mut settling = Duration::ZERO;
+        for (handle, _) in &changed {
+            log::info!("[ACCEL] Device {} waking, client connected", handle.index);
+            match with_device_within(&handle.device, |device| {
+                device.start().map(|()| device.settling_time())
+            }) {
+                Some(Ok(time)) => settling = settling.max(time),
+                // left to the watchdog, which restarts it
+                Some(Err(e)) => log::error!("[ACCEL] Failed to wake device {}: {e}", handle.index),
+                None => log::error!(
+                    "[ACCEL] Failed to wake device {}: busy for {DEVICE_LOCK_WAIT:?}",
+                    handle.index
+                ),
+            }
+        }
+        thread::sleep(settling);
+        for (handle, discard) in &changed {
+            let datarate = &handle.datarate;
+            datarate.discard.store(*discard, Ordering::Relaxed);
+            datarate
+                .latest
+                .store(Some(Instant::now()), Ordering::Relaxed);
+            datarate.standby.store(false, Ordering::Relaxed);
+            log::info!(
+                "[ACCEL] Device {} resumed after {settling:?} settling",
+                handle.index
+            );
+        }
+    })
+    .await;
+    if let Err(e) = res {
+        log::error!("[ACCEL] Idle standby task failed: {e}");
+    }
+}
+
+/// Step the output data rate of each sensor whose measured rate stayed below
+/// [`ADAPTIVE_MIN_RATIO`] of it for the `adaptive` sustain time down to the
+/// next slower one, not below the floor, and tell the clients. Sensors in
+/// standby are left alone
+async fn accelerator_adapt(sensors: &mut [Sensor], adaptive: &AdaptiveOdr) {
+    for sensor in sensors.iter_mut().filter(|sensor| !sensor.standby) {
+        let odr = sensor.datarate.odr();
+        let measured = sensor
+            .datarate
+            .stats
+            .lock()
+            .ok()
+            .and_then(|stats| stats.rate_hz)
+            .filter(|hz| *hz < f32::from(odr) * ADAPTIVE_MIN_RATIO);
+        let Some(measured) = measured else {
+            sensor.slow_since = None;
+            continue;
+        };
+        if sensor.slow_since.get_or_insert_with(Instant::now).elapsed() < adaptive.sustain {
+            continue;
+        }
+        sensor.slow_since = None;
+        let Some(&next) = ODR_LPF::all()
+            .get(odr.val() as usize + 1)
+            .filter(|next| f32::from(**next) >= f32::from(adaptive.floor))
+        else {
+            log::warn!(
+                "[ACCEL] Device {} measured {measured:.1} Hz at {odr}, already at the floor",
+                sensor.index
+            );
+            continue;
+        };
+        let (index, device) = (sensor.index, sensor.device.clone());
+        let res = tokio::task::spawn_blocking(move || {
+            with_device_within(&device, |device| {
+                device
+                    .stop()
+                    .and_then(|_| device.set_odr(next))
+                    .and_then(|_| device.start())
+                    .map_err(|e| e.to_string())
+            })
+            .unwrap_or_else(|| Err(format!("device busy for {DEVICE_LOCK_WAIT:?}")))
+        })
+        .await;
+        match res {
+            Ok(Ok(())) => {
+                sensor.datarate.odr.store(next.val(), Ordering::Relaxed);
+                log::warn!(
+                    "[ACCEL] Device {index} measured {measured:.1} Hz at {odr}, lowered to {next}"
                 );
-                let mut drdy = drdy.into_input();
-                if let Ok(spi) = Spi::new(
-                    acceldesc.bus,
-                    acceldesc.ss,
-                    1_000_000, // 1 MHz
-                    Mode::Mode0,
-                ) {
-                    if let Ok(mut accel) = adxl355::Adxl355::new(
-                        spi,
-                        ADXLConfig::default()
-                            .odr(ACCEL_ODR)
-                            .hpf(adxl355::HPF_CORNER::_0_238_ODR)
-                            .range(Range::_2G),
-                    ) {
-                        if let Err(e) = accel.start() {
-                            log::error!("Failed to start accel: {e}");
-                            None
-                        } else if let Err(e) = {
-                            thread::sleep(Duration::from_millis(100));
-                            if let Ok(value) = accel.accel_norm() {
-                                log::info!("Accelerometer {acceldesc:?} data: {value:?}");
-                            }
-                            let sink = sink.clone();
-                            let past = AtomicOptionInstant::new(Some(now));
-                            let datarate = AccelDataRate {
-                                last: AtomicOptionInstant::none(),
-                                count: AtomicUsize::new(0),
-                            };
-                            drdy.set_async_interrupt(
-                                rppal::gpio::Trigger::FallingEdge,
-                                None,
-                                move |_| {
-                                    accelerator_callback(
-                                        index as u32,
-                                        &mut accel,
-                                        &past,
-                                        &sink,
-                                        &datarate,
-                                    )
-                                },
-                            )
-                        } {
+                // the next measurement is at the new rate
+                if let Ok(mut stats) = sensor.datarate.stats.lock() {
+                    stats.rate_hz = None;
+                }
+                let _ = adaptive.status.send(Status::RateReduced {
+                    idx: index,
+                    from: odr.into(),
+                    to: next.into(),
+                    measured,
+                });
+            }
+            // left to the watchdog if the device stopped
+            Ok(Err(e)) => log::error!("[ACCEL] Device {index} failed to lower rate: {e}"),
+            Err(e) => log::error!("[ACCEL] Adaptive rate task failed: {e}"),
+        }
+    }
+}
+
+/// Restart the sensors that produced no samples for their `watchdog`
+/// timeout, backing off after failed restarts. Sensors in standby are left
+/// alone
+async fn accelerator_watchdog(
+    mut sensors: Vec<Sensor>,
+    watchdog: Watchdog,
+    setup: &SensorSetup,
+    running: &AtomicBool,
+) -> Vec<Sensor> {
+    let mut restarted = Vec::with_capacity(sensors.len());
+    for mut sensor in sensors.drain(..) {
+        let timeout = watchdog.timeout(&sensor).filter(|_| !sensor.standby);
+        let Some(timeout) = timeout else {
+            restarted.push(sensor);
+            continue;
+        };
+        let stalled = sensor
+            .datarate
+            .latest
+            .load(Ordering::Relaxed)
+            .is_some_and(|latest| latest.elapsed() > timeout);
+        let backing_off = sensor.retry_at.is_some_and(|at| Instant::now() < at);
+        if stalled && !backing_off && running.load(Ordering::Relaxed) {
+            log::error!(
+                "[ACCEL] Device {} stalled: no samples for {timeout:?}, restarting",
+                sensor.index
+            );
+            let config = setup.config;
+            sensor = match tokio::task::spawn_blocking(move || {
+                let res = accelerator_restart(&mut sensor, &config).map_err(|e| e.to_string());
+                (sensor, res)
+            })
+            .await
+            {
+                Ok((mut sensor, res)) => {
+                    match res {
+                        Ok(()) => {
+                            log::info!("[ACCEL] Device {} restarted", sensor.index);
+                            sensor.backoff.reset();
+                            sensor.retry_at = None;
+                            sensor.datarate.fault(
+                                ErrorKind::Restarted,
+                                sensor.sampling.time(Instant::now()),
+                                format!("no samples for {timeout:?}"),
+                            );
+                        }
+                        Err(e) => {
+                            let delay = sensor.backoff.next_delay();
                             log::error!(
-                                "Failed to set async interrupt for pin {}: {}",
-                                acceldesc.drdy,
-                                e
+                                "[ACCEL] Device {} restart failed: {e}, retrying in {delay:?}",
+                                sensor.index
                             );
-                            None
-                        } else {
-                            log::info!(
-                                "Accelerometer on bus {:?} initialized successfully.",
-                                acceldesc.bus
+                            sensor.retry_at = Some(Instant::now() + delay);
+                            sensor.datarate.fault(
+                                ErrorKind::RestartFailed,
+                                sensor.sampling.time(Instant::now()),
+                                format!("{e}, retrying in {delay:?}"),
                             );
-                            Some(drdy)
                         }
-                    } else {
-                        log::error!(
-                            "Failed to create ADXL355 instance on bus {:?}",
-                            acceldesc.bus
-                        );
-                        None
                     }
-                } else {
-                    log::error!("Failed to initialize SPI on bus {:?}", acceldesc.bus);
-                    None
+                    sensor
                 }
-            } else {
+                Err(e) => {
+                    log::error!("[ACCEL] Watchdog restart task failed: {e}");
+                    continue;
+                }
+            };
+        }
+        restarted.push(sensor);
+    }
+    restarted
+}
+
+/// Reconcile the running sensors with `descs`, matching them by bus and chip
+/// select: sensors no longer listed are stopped, sensors whose descriptor
+/// changed are stopped and started again under the same index, and new
+/// sensors are started under the next unused index, counting up from
+/// `next_index`. Other sensors keep sampling undisturbed. Logs a summary of
+/// the changes and returns the sensors now running, ordered by index.
+pub async fn accelerator_reload(
+    sensors: Vec<Sensor>,
+    descs: Vec<AccelDesc>,
+    setup: &SensorSetup,
+    next_index: &mut u32,
+) -> Vec<Sensor> {
+    let same_slot = |a: &AccelDesc, b: &AccelDesc| a.bus == b.bus && a.ss == b.ss;
+    let (mut removed, mut reconfigured, mut unchanged) = (0, 0, 0);
+    let mut kept = Vec::with_capacity(descs.len());
+    let mut stopped = Vec::new();
+    let mut start = Vec::new();
+    for sensor in sensors {
+        match descs.iter().find(|desc| same_slot(desc, &sensor.desc)) {
+            Some(desc) if *desc == sensor.desc => {
+                unchanged += 1;
+                kept.push(sensor);
+            }
+            Some(desc) => {
                 log::info!(
-                    "DRDY pin {} not found, skipping accel on bus {:?}",
-                    acceldesc.drdy,
-                    acceldesc.bus
+                    "[ACCEL] Device {} reconfigured: {desc:?}, restarting",
+                    sensor.index
                 );
-                None
+                reconfigured += 1;
+                start.push((sensor.index, desc.clone()));
+                stopped.push(sensor);
             }
+            None => {
+                log::info!("[ACCEL] Device {} removed, stopping", sensor.index);
+                removed += 1;
+                stopped.push(sensor);
+            }
+        }
+    }
+    let added = descs
+        .iter()
+        .filter(|desc| {
+            !kept.iter().any(|s: &Sensor| same_slot(desc, &s.desc))
+                && !stopped.iter().any(|s| same_slot(desc, &s.desc))
+        })
+        .map(|desc| {
+            let index = *next_index;
+            *next_index += 1;
+            log::info!("[ACCEL] Device {index} added: {desc:?}");
+            (index, desc.clone())
         })
         .collect::<Vec<_>>();
-    if pins.is_empty() {
-        log::warn!("No accelerometer DRDY pins found, exiting thread.");
+    let added_count = added.len();
+    start.extend(added);
+    // stop first, freeing the pins of removed sensors for the started ones
+    let stop = tokio::task::spawn_blocking(move || stopped.into_iter().for_each(Sensor::stop));
+    if let Err(e) = stop.await {
+        log::error!("[ACCEL] Reload stop task failed: {e}");
+    }
+    let setup = setup.clone();
+    let started = tokio::task::spawn_blocking(move || {
+        let gpio = Gpio::new();
+        start
+            .iter()
+            .filter_map(|(index, desc)| {
+                start_sensor(*index, desc, &setup, &gpio, Instant::now())
+                    .inspect_err(|e| log::error!("[ACCEL] Reload failed to start {e}"))
+                    .ok()
+            })
+            .collect::<Vec<_>>()
+    })
+    .await
+    .unwrap_or_else(|e| {
+        log::error!("[ACCEL] Reload start task failed: {e}");
+        Vec::new()
+    });
+    let failed = reconfigured + added_count - started.len();
+    kept.extend(started);
+    kept.sort_by_key(|sensor| sensor.index);
+    log::info!(
+        "[ACCEL] Reload: added {added_count}, removed {removed}, reconfigured {reconfigured}, unchanged {unchanged}, failed {failed}"
+    );
+    kept
+}
+
+/// Configuration of sensor `index` as sent to clients
+fn sensor_info<SPI: SpiBus>(index: u32, device: &Adxl355<SPI>) -> SensorInfo {
+    SensorInfo {
+        idx: index,
+        odr: device.odr().into(),
+        range: device.range().into(),
+        hpf: device.hpf().val(),
+        scale: device.scale(),
+    }
+}
+
+/// Publish a [`Status::Health`] record per current sensor once per second
+/// until `running` is cleared, reading the die temperature if `temperature`
+/// is set, each followed by a [`Status::Config`] record if `config` is set
+/// and by a [`Status::Error`] record per kind of failure of the sensor
+/// since the last one if `errors` is set. Failures while no client listens
+/// are not reported
+pub async fn accelerator_status(
+    sensors: watch::Receiver<Vec<SensorHandle>>,
+    temperature: bool,
+    config: bool,
+    errors: bool,
+    status: Sender<Status>,
+    running: Arc<AtomicBool>,
+) {
+    let mut interval = tokio::time::interval(Duration::from_secs(1));
+    while running.load(Ordering::Relaxed) {
+        interval.tick().await;
+        let sensors = sensors.borrow().clone();
+        let faults: Vec<_> = sensors
+            .iter()
+            .map(|sensor| sensor.datarate.take_faults(sensor.index))
+            .collect();
+        if status.receiver_count() == 0 {
+            continue;
+        }
+        for (SensorHandle { index, device, .. }, faults) in sensors.iter().zip(faults) {
+            let temperature = if temperature {
+                let device = device.clone();
+                match tokio::task::spawn_blocking(move || device.with(|d| d.read_temp_celsius()))
+                    .await
+                {
+                    Ok(Ok(celsius)) => Some(celsius),
+                    Ok(Err(e)) => {
+                        log::warn!("[ACCEL] Device {index}: failed to read temperature: {e}");
+                        None
+                    }
+                    Err(e) => {
+                        log::error!("[ACCEL] Temperature read task failed: {e}");
+                        None
+                    }
+                }
+            } else {
+                None
+            };
+            let _ = status.send(Status::Health {
+                idx: *index,
+                temperature,
+            });
+            if config {
+                let (index, device) = (*index, device.clone());
+                match tokio::task::spawn_blocking(move || {
+                    device.with(|device| sensor_info(index, device))
+                })
+                .await
+                {
+                    Ok(info) => {
+                        let _ = status.send(Status::Config(info));
+                    }
+                    Err(e) => log::error!("[ACCEL] Configuration read task failed: {e}"),
+                }
+            }
+            if errors {
+                for fault in faults {
+                    let _ = status.send(fault);
+                }
+            }
+        }
+    }
+}
+
+/// Log the [`SensorDiagnostics`] of every current sensor as a JSON line
+/// whenever the daemon receives `SIGUSR1`, until `running` is cleared
+pub async fn accelerator_diagnostics(
+    sensors: watch::Receiver<Vec<SensorHandle>>,
+    running: Arc<AtomicBool>,
+) {
+    use tokio::signal::unix::{SignalKind, signal};
+    let mut usr1 = match signal(SignalKind::user_defined1()) {
+        Ok(usr1) => usr1,
+        Err(e) => {
+            log::error!("[ACCEL] Failed to listen for SIGUSR1: {e}");
+            return;
+        }
+    };
+    while running.load(Ordering::Relaxed) && usr1.recv().await.is_some() {
+        let sensors = sensors.borrow().clone();
+        for sensor in sensors {
+            match tokio::task::spawn_blocking(move || sensor.collect()).await {
+                Ok(diagnostics) => match serde_json::to_string(&diagnostics) {
+                    Ok(json) => log::info!("[DIAG] {json}"),
+                    Err(e) => log::error!("[DIAG] Failed to serialize diagnostics: {e}"),
+                },
+                Err(e) => log::error!("[DIAG] Diagnostics task failed: {e}"),
+            }
+        }
     }
-    Ok(pins)
 }
 
-fn accelerator_callback(
+/// Sample `device` on the DRDY edges of `irq` until `stop` is set.
+///
+/// Generic over the `embedded-hal` SPI bus and the [`InterruptSource`], so
+/// the loop is not tied to the Raspberry Pi; the daemon runs it over
+/// [`RppalInterrupt`]. With [`Sampling::core`] set the calling thread is
+/// pinned, and stays pinned after the loop returns.
+fn accelerator_loop<SPI, E, I>(
     index: u32,
-    device: &mut Adxl355<Spi>,
-    past: &AtomicOptionInstant,
+    device: SharedAdxl355<SPI>,
+    irq: &mut I,
+    sink: Sender<AccelData>,
+    datarate: &AccelDataRate,
+    sampling: Sampling,
+    stop: &AtomicBool,
+) -> Result<(), I::Error>
+where
+    SPI: SpiBus<u8, Error = E>,
+    E: Debug,
+    I: InterruptSource,
+{
+    datarate
+        .latest
+        .store(Some(Instant::now()), Ordering::Relaxed);
+    let mut last_edge = None;
+    sampling.pin_thread(index);
+    // Wake up regularly to check for shutdown
+    let timeout = Duration::from_millis(100);
+    while !stop.load(Ordering::Relaxed) {
+        let Some(edge) = irq.wait_edge(timeout)? else {
+            continue;
+        };
+        let edge_gap = sampling.timing.edge_gap(&mut last_edge, edge.timestamp);
+        device.with(|device| {
+            accelerator_callback(index, device, &sink, datarate, &sampling, edge_gap)
+        });
+    }
+    Ok(())
+}
+
+fn accelerator_callback<SPI, E>(
+    index: u32,
+    device: &mut Adxl355<SPI>,
     sink: &Sender<AccelData>,
     datarate: &AccelDataRate,
-) {
+    sampling: &Sampling,
+    edge_gap: Option<u32>,
+) where
+    SPI: SpiBus<u8, Error = E>,
+    E: Debug,
+{
     let now = Instant::now();
-    datarate.count.fetch_add(1, Ordering::Relaxed); // Increment count
-    // At the first call, we get time from the synchronization point
-    let gap = past
+    let now = now.checked_sub(sampling.timing.latency).unwrap_or(now);
+    datarate.latest.store(Some(now), Ordering::Relaxed);
+    datarate.odr.store(device.odr().val(), Ordering::Relaxed);
+    if datarate.standby.load(Ordering::Relaxed) {
+        // read to deassert DRDY
+        let _ = device.accel_raw();
+        return;
+    }
+    // Callbacks of a device are serialized by its lock
+    let discard = datarate.discard.load(Ordering::Relaxed);
+    if discard > 0 {
+        datarate.discard.store(discard - 1, Ordering::Relaxed);
+        // read to deassert DRDY
+        let _ = device.accel_raw();
+        if discard == 1 {
+            log::info!(
+                "[ACCEL] Device {index} discarded {} startup samples",
+                sampling.discard
+            );
+        }
+        return;
+    }
+    // At the first forwarded sample, we get time from the synchronization
+    // point
+    let gap = datarate
+        .sync
         .swap(None, Ordering::Relaxed)
         .map(|past| {
             log::debug!("Accelerometer callback triggered for device at index {index}");
             now.duration_since(past).as_micros() as u32
         })
-        .unwrap_or(get_odr(ACCEL_ODR)); // otherwise, we use the output data rate
-    // Update the tick count
-    datarate
-        .last
-        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |past| {
-            match past {
-                None => Some(Some(now)), // first call, set to current time
-                Some(past) => {
-                    let dur = now.duration_since(past).as_secs_f32();
-                    if dur < 1.0 {
-                        None // Keep the old timestamp if less than 1 second has passed
-                    } else {
-                        let count = datarate.count.swap(0, Ordering::Relaxed); // Reset count
-                        log::debug!(
-                            "[ACCEL] Device {index} data rate: {:.3} Hz",
-                            count as f32 / dur
-                        );
-                        Some(Some(now)) // Update to the current time
-                    }
-                }
-            }
+        .or(edge_gap) // otherwise, the time between DRDY edges if measured
+        .unwrap_or(get_odr(device.odr())); // or the output data rate
+    let report = datarate
+        .stats
+        .lock()
+        .map(|mut stats| {
+            stats.gaps.record(gap);
+            stats.total += 1;
+            let rates = stats.meter.record(1, AccelData::SIZE as u64)?;
+            stats.rate_hz = Some(rates.count_per_s);
+            let gaps = stats.gaps.percentiles();
+            stats.gaps.reset();
+            Some((rates, gaps, stats.total))
         })
-        .ok();
+        .unwrap_or_default();
+    if let Some((rates, gaps, total)) = report {
+        log::info!(
+            "[STATS] source=accel idx={index} {rates} gap_p50_us={} gap_p99_us={} gap_max_us={} gap_jitter_us={} total={total}",
+            gaps.p50,
+            gaps.p99,
+            gaps.max,
+            // spread of the gaps, what pinning the sampling thread cuts
+            gaps.p99.saturating_sub(gaps.p50)
+        );
+    }
 
-    if let Ok(data) = device.accel_norm() {
-        if sink.receiver_count() > 0
-            && sink
+    let seq = datarate.seq.fetch_add(1, Ordering::Relaxed);
+    if datarate.idle() {
+        // Nobody is listening: skip the temperature, scaling and sending. The
+        // data registers are still read, DRDY is only deasserted by reading
+        // them and would otherwise stop the interrupts
+        let _ = device.accel_raw();
+        return;
+    }
+    let temp = datarate
+        .temp
+        .lock()
+        .map(|mut temp| temp.sample(device))
+        .unwrap_or_default();
+    let time = sampling.time(now);
+    match read_sample(device, sampling.units, sampling.freshness) {
+        Ok((data, fresh)) => {
+            if sink
                 .send(AccelData {
                     idx: index,
+                    seq,
                     gap,
+                    time,
                     x: data.x,
                     y: data.y,
                     z: data.z,
+                    temp,
+                    fresh,
                 })
                 .is_err()
-        {
-            log::error!("Failed to send accelerometer data for device at index {index}");
+            {
+                log::error!("Failed to send accelerometer data for device at index {index}");
+            }
+        }
+        Err(e) => {
+            log::error!("Failed to read accelerometer data from device at index {index}",);
+            datarate.fault(ErrorKind::ReadFailed, time, format!("{e:?}"));
         }
-    } else {
-        log::error!("Failed to read accelerometer data from device at index {index}",);
     }
 }
 
-pub async fn accelerator_task(
+/// Interval at which an idle polling task checks for clients
+const IDLE_POLL: Duration = Duration::from_millis(100);
+
+/// Polling fallback for sensors without a usable DRDY pin: samples the
+/// device once per [`sample_period`] of its current output data rate until
+/// `stop` is set.
+///
+/// The poll is timed by the host clock, which drifts against the sensor's, so
+/// a sample is occasionally read twice or skipped. Gaps are nominal periods.
+///
+/// While no clients are connected, see [`SensorSetup::clients`], the device
+/// is not read at all, and the task only wakes every [`IDLE_POLL`] to check
+/// for new ones: the first sample after a client connects arrives up to
+/// that much later. Such an idle
+/// sensor is not supervised by the watchdog.
+fn accelerator_task(
     index: u32,
-    acceldesc: AccelDesc,
+    device: SharedAdxl355<Spi>,
     sink: Sender<AccelData>,
-    running: Arc<AtomicBool>,
+    datarate: Arc<AccelDataRate>,
+    sampling: Sampling,
+    stop: Arc<AtomicBool>,
 ) {
-    if let Ok(spi) = Spi::new(
-        acceldesc.bus,
-        acceldesc.ss,
-        1_000_000, // 1 MHz
-        Mode::Mode0,
-    ) {
-        if let Ok(mut accel) = adxl355::Adxl355::new(
-            spi,
-            ADXLConfig::default()
-                .odr(ACCEL_ODR)
-                .hpf(adxl355::HPF_CORNER::_0_238_ODR)
-                .range(Range::_2G),
-        ) {
-            if let Err(e) = accel.start() {
-                log::error!("Failed to start accel: {e}");
-            } else {
-                thread::sleep(Duration::from_millis(100));
-                if let Ok(value) = accel.accel_norm() {
-                    log::info!("Accelerometer {acceldesc:?} data: {value:?}");
-                }
-                let mut now = Instant::now();
-                while running.load(Ordering::Relaxed) {
-                    if let Ok(data) = accel.accel_norm() {
-                        let tnow = Instant::now();
-                        let dur = tnow.duration_since(now).as_micros() as u32;
-                        now = tnow;
-                        if sink.receiver_count() > 0
-                            && sink
-                                .send(AccelData {
-                                    idx: index,
-                                    gap: dur,
-                                    x: data.x,
-                                    y: data.y,
-                                    z: data.z,
-                                })
-                                .is_err()
-                        {
-                            log::error!(
-                                "Failed to send accelerometer data for device at index {index}"
-                            );
-                        }
-                    } else {
-                        log::error!(
-                            "Failed to read accelerometer data from device at index {index}"
-                        );
-                    }
-                    thread::sleep(Duration::from_micros(900)); // Adjust as needed
-                }
+    datarate
+        .latest
+        .store(Some(Instant::now()), Ordering::Relaxed);
+    let mut period = sample_period(datarate.odr());
+    log::info!("[ACCEL] Device {index} polling every {period:?}");
+    sampling.pin_thread(index);
+    let mut next = Instant::now();
+    let mut idle = false;
+    while !stop.load(Ordering::Relaxed) {
+        let current = sample_period(datarate.odr());
+        if current != period {
+            log::info!("[ACCEL] Device {index} polling every {current:?}");
+            period = current;
+        }
+        if datarate.standby.load(Ordering::Relaxed) {
+            thread::sleep(IDLE_POLL);
+            next = Instant::now();
+            continue;
+        }
+        if datarate.idle() {
+            if !idle {
+                log::info!("[ACCEL] Device {index} idle, no clients");
+                idle = true;
             }
+            datarate
+                .latest
+                .store(Some(Instant::now()), Ordering::Relaxed);
+            thread::sleep(IDLE_POLL);
+            next = Instant::now();
+            continue;
+        }
+        if idle {
+            log::info!("[ACCEL] Device {index} resuming, clients connected");
+            idle = false;
+        }
+        next += period;
+        let now = Instant::now();
+        if next > now {
+            thread::sleep(next - now);
         } else {
-            log::error!(
-                "Failed to create ADXL355 instance on bus {:?}",
-                acceldesc.bus
-            );
+            // fell behind, e.g. during a restart: resume without a burst
+            next = now;
         }
-    } else {
-        log::error!(
-            "Failed to create ADXL355 instance on bus {:?}",
-            acceldesc.bus
+        device
+            .with(|device| accelerator_callback(index, device, &sink, &datarate, &sampling, None));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn desc(bus: Bus, ss: SlaveSelect, drdy: u8) -> AccelDesc {
+        AccelDesc {
+            bus,
+            ss,
+            drdy,
+            orientation: AxisTransform::IDENTITY,
+            odr: None,
+            range: None,
+            hpf: None,
+            name: None,
+        }
+    }
+
+    #[test]
+    fn distinct_sensors_are_valid() {
+        let descs = [
+            desc(Bus::Spi0, SlaveSelect::Ss0, 25),
+            desc(Bus::Spi0, SlaveSelect::Ss1, 24),
+            desc(Bus::Spi1, SlaveSelect::Ss0, 26),
+        ];
+        assert_eq!(validate_descs(&descs), Ok(()));
+        assert_eq!(validate_descs(&[]), Ok(()));
+    }
+
+    #[test]
+    fn shared_chip_select_is_rejected() {
+        let descs = [
+            desc(Bus::Spi0, SlaveSelect::Ss0, 25),
+            desc(Bus::Spi1, SlaveSelect::Ss2, 26),
+            desc(Bus::Spi0, SlaveSelect::Ss0, 24),
+        ];
+        let e = validate_descs(&descs).unwrap_err();
+        assert!(
+            e.contains("devices 0 and 2 share bus Spi0 chip select Ss0"),
+            "{e}"
+        );
+        assert!(!e.contains("DRDY"), "{e}");
+    }
+
+    #[test]
+    fn shared_drdy_pin_is_rejected() {
+        let descs = [
+            desc(Bus::Spi0, SlaveSelect::Ss0, 25),
+            desc(Bus::Spi1, SlaveSelect::Ss2, 25),
+        ];
+        let e = validate_descs(&descs).unwrap_err();
+        assert!(e.contains("devices 0 and 1 share DRDY pin 25"), "{e}");
+        assert!(!e.contains("chip select"), "{e}");
+    }
+
+    #[test]
+    fn every_conflict_is_listed() {
+        let descs = [
+            desc(Bus::Spi0, SlaveSelect::Ss0, 25),
+            desc(Bus::Spi0, SlaveSelect::Ss0, 25),
+        ];
+        let e = validate_descs(&descs).unwrap_err();
+        assert!(
+            e.contains("chip select") && e.contains("DRDY pin 25"),
+            "{e}"
         );
     }
 }
@@ -0,0 +1,38 @@
+//! CPU affinity of the sampling threads
+use std::io;
+
+/// Pin the calling thread to CPU `core`, counting from 0.
+///
+/// Linux only: elsewhere this returns an [`io::ErrorKind::Unsupported`]
+/// error and the thread keeps running wherever the scheduler puts it.
+#[cfg(target_os = "linux")]
+pub fn pin_current_thread(core: usize) -> io::Result<()> {
+    if core >= libc::CPU_SETSIZE as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("no core {core}"),
+        ));
+    }
+    // SAFETY: `cpu_set_t` is a plain bit array, for which all zeros is the
+    // empty set, and `core` is within its bounds
+    let res = unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_SET(core, &mut set);
+        // pid 0 is the calling thread
+        libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set)
+    };
+    if res == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Pin the calling thread to CPU `core`: unsupported outside Linux
+#[cfg(not(target_os = "linux"))]
+pub fn pin_current_thread(core: usize) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("cannot pin threads to core {core} on this platform"),
+    ))
+}
@@ -0,0 +1,49 @@
+//! DRDY interrupt abstraction, so the sampling loop is not tied to `rppal`
+use std::time::Duration;
+
+/// DRDY edge reported by an [`InterruptSource`]
+#[derive(Debug, Copy, Clone)]
+pub struct Edge {
+    /// Time of the edge on a monotonic clock, if the platform records it
+    pub timestamp: Option<Duration>,
+}
+
+/// Source of DRDY interrupts of one accelerometer
+pub trait InterruptSource {
+    type Error: std::error::Error;
+
+    /// Block until the next falling edge of DRDY, or until `timeout` has
+    /// elapsed, in which case `None` is returned
+    fn wait_edge(&mut self, timeout: Duration) -> Result<Option<Edge>, Self::Error>;
+}
+
+/// [`InterruptSource`] on a Raspberry Pi GPIO pin
+#[derive(Debug)]
+pub struct RppalInterrupt(rppal::gpio::InputPin);
+
+impl RppalInterrupt {
+    /// Configure `pin` to report falling edges
+    pub fn new(mut pin: rppal::gpio::InputPin) -> Result<Self, rppal::gpio::Error> {
+        pin.set_interrupt(rppal::gpio::Trigger::FallingEdge, None)?;
+        Ok(Self(pin))
+    }
+
+    /// Release the pin, clearing its interrupt
+    pub fn into_inner(mut self) -> rppal::gpio::InputPin {
+        if let Err(e) = self.0.clear_interrupt() {
+            log::error!("Failed to clear interrupt for {:?}: {e}", self.0);
+        }
+        self.0
+    }
+}
+
+impl InterruptSource for RppalInterrupt {
+    type Error = rppal::gpio::Error;
+
+    fn wait_edge(&mut self, timeout: Duration) -> Result<Option<Edge>, Self::Error> {
+        let event = self.0.poll_interrupt(false, Some(timeout))?;
+        Ok(event.map(|event| Edge {
+            timestamp: Some(event.timestamp),
+        }))
+    }
+}
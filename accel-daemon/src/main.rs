@@ -1,19 +1,37 @@
 mod accel;
+mod affinity;
+mod interrupt;
 
+use std::collections::BTreeMap;
 use std::sync::{
     Arc,
-    atomic::{AtomicBool, Ordering},
+    atomic::{AtomicBool, AtomicUsize, Ordering},
 };
 
 #[allow(unused_imports)]
-use accel::{AccelDesc, accelerator_init, accelerator_task};
-use accel_data::tcp_server;
+use accel::{
+    AccelDesc, AdaptiveOdr, IdleShutdown, Sampling, Sensor, SensorSetup, Supervision,
+    TimestampMode, Timing, Watchdog, accelerator_diagnostics, accelerator_init, accelerator_status,
+    accelerator_supervisor, apply_command, cap_odr, list_devices, sample_period, trace_spi,
+    validate_descs,
+};
+use accel_data::{
+    AccelData, AxisMask, CSV_HEADER, CaptureHeader, CaptureWriter, Coalescer, CommandRequest,
+    DecimationPlan, Ema, Handshake, History, MAX_FFT_SIZE, Magnitude, SampleSource, SensorInfo,
+    ServerConfig, SpectrumAnalyzer, Status, Summarizer, SyntheticSource, TiltDetector, Units,
+    deadband_sink, relay, tcp_server, udp_server, uds_server, wsock_server,
+};
 
+use adxl355::{AxisTransform, Config as ADXLConfig, HPF_CORNER, ODR_LPF, Range, STANDARD_GRAVITY};
 use clap::Parser;
 use rppal::spi::{Bus, SlaveSelect};
+use serde::Deserialize;
+
+/// High pass filter corner of sensors without `--sensor-hpf`
+const HPF: HPF_CORNER = HPF_CORNER::_0_238_ODR;
 
 /// Program to forward serial port over TCP
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Clone)]
 #[command(version, about, long_about)]
 struct Args {
     #[arg(
@@ -24,6 +42,302 @@ struct Args {
     )]
     /// Network port to listen on to send commands
     port: u16,
+    #[arg(
+        short,
+        long,
+        value_parser = clap::value_parser!(u16).range(1..=65535)
+    )]
+    /// Network port to serve data over WebSocket (disabled if not set)
+    ws_port: Option<u16>,
+    #[arg(
+        short,
+        long,
+        value_parser = clap::value_parser!(u16).range(1..=65535)
+    )]
+    /// Network port to serve data over UDP (disabled if not set). Clients
+    /// subscribe by sending `hello` datagrams
+    udp_port: Option<u16>,
+    #[arg(long)]
+    /// Also serve the TCP stream on a Unix-domain socket at this path, for
+    /// local recorders. Bypasses the network stack; the socket file is
+    /// removed on shutdown
+    uds: Option<std::path::PathBuf>,
+    #[arg(long)]
+    /// Shared secret every client must present: TCP and Unix-domain clients
+    /// as their first line, WebSocket clients as `?token=` or an
+    /// `Authorization: Bearer` header, UDP clients as `hello TOKEN`. Others
+    /// are disconnected and logged. Tokens travel in clear text
+    auth_token: Option<Secret>,
+    #[arg(long)]
+    /// Allow admin WebSocket clients to reconfigure the sensors
+    allow_commands: bool,
+    #[arg(long)]
+    /// Only forward a sample when any axis changed by more than this many g
+    /// since the last forwarded sample of the same sensor. Forwarded samples
+    /// are then not uniformly spaced, clients must use `gap` for timing.
+    deadband: Option<f32>,
+    #[arg(long, conflicts_with = "deadband")]
+    /// Forward the samples of all sensors as time-aligned tuples: one record
+    /// per sensor and sample period, in sensor order, with the nominal time
+    /// of the period as `time`. A sensor that dropped a sample has a record
+    /// with NaN axes in its place. Meant for sensors at the same output data
+    /// rate: a slower one is missing from most tuples and a faster one only
+    /// sends its latest sample of each period. Sensors added by a reload are
+    /// not forwarded
+    coalesce: bool,
+    #[arg(long, default_value = "1000")]
+    /// Maximum interval between forwarded samples in deadband mode, in milliseconds
+    keepalive_ms: u64,
+    #[arg(long, default_value = "xyz")]
+    /// Axes forwarded to clients, e.g. `xz`. Disabled axes are omitted from
+    /// TCP data frames and zeroed in WebSocket messages. `t` adds the
+    /// temperature and `f` the freshness flag to TCP records
+    axes: AxisMask,
+    #[arg(long, default_value = "1000 Hz")]
+    /// Accelerometer output data rate, e.g. `4000 Hz` or `62.5`
+    odr: ODR_LPF,
+    #[arg(long, default_value = "2g")]
+    /// Accelerometer measurement range: `2g`, `4g` or `8g`
+    range: Range,
+    #[arg(long)]
+    /// Restart a sensor that produced no samples for this many milliseconds
+    /// (0 disables). Default is 10 output data periods, at least 100 ms
+    watchdog_ms: Option<u64>,
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    /// Put the sensors in standby after this many milliseconds without
+    /// clients, and wake them when one connects. The first samples after a
+    /// wake wait for the settling time and `--discard-samples`. Default is
+    /// to sample continuously
+    idle_timeout_ms: Option<u64>,
+    #[arg(long)]
+    /// Lower the output data rate of a sensor one step at a time, down to
+    /// this rate, while its measured rate stays below 90% of it for three
+    /// stats intervals, e.g. on an overloaded host. Each step is logged and
+    /// sent to clients as a `rate_reduced` status record. Default is to keep
+    /// the configured rate
+    adaptive_odr_floor: Option<ODR_LPF>,
+    #[arg(long)]
+    /// Include the die temperature of each sensor in the once per second
+    /// status records
+    status_temperature: bool,
+    #[arg(long)]
+    /// Tell clients about sensor read failures and watchdog restarts with
+    /// once per second error status records, and about samples skipped
+    /// for them as they happen
+    status_errors: bool,
+    #[arg(long)]
+    /// Follow every status record with the current ODR, range, HPF corner
+    /// and scale of the sensor, so late clients and sensors reconfigured by
+    /// admin commands decode correctly without a new handshake. Data
+    /// records are unchanged
+    status_config: bool,
+    #[arg(long, default_value = "64")]
+    /// Smallest TCP data frame in bytes, used while writes complete quickly
+    tcp_batch_min: usize,
+    #[arg(long, default_value = "8192")]
+    /// Largest TCP data frame in bytes, used while writes are slow
+    tcp_batch_max: usize,
+    #[arg(long)]
+    /// Longest a sample waits in a partially filled TCP data frame or
+    /// WebSocket message, in milliseconds. Bounds the latency of slow
+    /// streams such as `--magnitude-hz`. Default is to send frames and
+    /// messages only when full
+    tcp_max_latency_ms: Option<u64>,
+    #[arg(long)]
+    /// Leave Nagle's algorithm enabled on TCP and WebSocket connections
+    /// (no `TCP_NODELAY`): fewer, fuller packets for slow remote links, at
+    /// the cost of up to a round trip of extra latency
+    tcp_delay: bool,
+    #[arg(long)]
+    /// Socket send buffer size of TCP and WebSocket connections in bytes.
+    /// Larger buffers help high throughput remote links but let more data
+    /// queue up behind a slow client. Default is the system default
+    tcp_send_buffer: Option<u32>,
+    #[arg(long, default_value = "5000")]
+    /// Deadline of each write to a TCP or WebSocket client in milliseconds.
+    /// Clients that stall for longer are dropped
+    write_timeout_ms: u64,
+    #[arg(long, default_value = "nominal")]
+    /// Sample timing: `nominal` output data periods, or `edge` to measure the
+    /// time between the kernel timestamps of the DRDY interrupts
+    timestamp: TimestampMode,
+    #[arg(long, default_value = "0")]
+    /// Estimated delay from the DRDY edge to the interrupt callback in
+    /// microseconds, subtracted from the times measured in the callback
+    timestamp_latency_us: u64,
+    #[arg(long)]
+    /// Offer an exponentially smoothed copy of the data as channel `ema`,
+    /// with this smoothing factor in (0, 1]. The cutoff frequency is about
+    /// `alpha * odr / 2π`
+    ema_alpha: Option<f32>,
+    #[arg(long)]
+    /// Offer the gravity-subtracted magnitude `sqrt(x²+y²+z²) - 1` as
+    /// channel `magnitude`, at this many samples per second per sensor.
+    /// Assumes the sensors measure a roughly 1 g gravity baseline
+    magnitude_hz: Option<f32>,
+    #[arg(long, value_parser = parse_decimate_hz)]
+    /// Offer an anti-aliased copy of the data at about this many samples per
+    /// second per sensor as channel `decimated`. The rate is rounded to an
+    /// integer fraction of the output data rate of each sensor, must not
+    /// exceed it and must be at least a 4000th of it. Frequencies up to 0.4
+    /// times the rate, and at most the sensor bandwidth of a quarter of its
+    /// output data rate, are kept
+    decimate_hz: Option<f32>,
+    #[arg(long, value_parser = clap::value_parser!(u32).range(0..=MAX_FFT_SIZE as i64))]
+    /// Send the dominant frequencies of each axis as `spectrum` status
+    /// records, from a running FFT over windows of this many samples per
+    /// sensor (rounded up to a power of two, at most 65536). The frequency
+    /// resolution is `odr / size`. Each window has its mean removed and is
+    /// Hann weighted
+    fft_size: Option<u32>,
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    /// Send the min, max and mean of each axis over windows of this many
+    /// milliseconds per sensor as `summary` status records, alongside the
+    /// data. Windows are aligned to multiples of their length since startup,
+    /// the same for all sensors
+    summary_ms: Option<u64>,
+    #[arg(long)]
+    /// Send an `orientation_changed` status record when the gravity direction
+    /// of a sensor turned by more than this many degrees since the last
+    /// record. Gravity is the low pass filtered acceleration, see
+    /// `--tilt-smoothing-ms`; samples must be in g
+    tilt_threshold_deg: Option<f32>,
+    #[arg(long, default_value = "500")]
+    /// Time constant of the gravity estimate of `--tilt-threshold-deg` in
+    /// milliseconds. Longer is steadier under vibration but reports later
+    tilt_smoothing_ms: u64,
+    #[arg(long, default_value = "0.1")]
+    /// Samples whose magnitude differs from 1 g by more than this many g are
+    /// left out of the gravity estimate, as the sensor is accelerating
+    tilt_tolerance: f32,
+    #[arg(long, default_value = "0.5")]
+    /// Fraction by which consecutive FFT windows overlap, in [0, 1). A
+    /// spectrum is sent every `size * (1 - overlap)` samples per sensor
+    fft_overlap: f32,
+    #[arg(long, default_value = "3", value_parser = clap::value_parser!(u32).range(0..=64))]
+    /// Number of peaks per axis in each spectrum record, at most 64
+    fft_peaks: u32,
+    #[arg(long, default_value = "0")]
+    /// Attach the die temperature to the samples, read once every this many
+    /// samples (0 disables). Attached values are at most this many output
+    /// data periods old. Add `t` to `--axes` to forward it over TCP/UDP
+    temp_every: u32,
+    #[arg(long, default_value = "g")]
+    /// Units of the forwarded samples, recorded in the handshake: `g`,
+    /// `mps2` for m/s², or `counts` to forward the raw 20-bit readings and
+    /// skip the per-sample scaling on the Pi. Clients multiply counts by the
+    /// `scale` of each sensor in the handshake. Deadband thresholds are in
+    /// the same units
+    units: Units,
+    #[arg(long)]
+    /// Read STATUS in the same burst as every sample and mark samples that
+    /// were already forwarded, as happens when polling outruns the sensor.
+    /// Add `f` to `--axes` to forward the flag over TCP/UDP
+    freshness: bool,
+    #[arg(long, default_value = "0")]
+    /// Drop this many samples of each sensor after it starts, and after every
+    /// watchdog restart, before forwarding any. Keeps the filter transients
+    /// that may follow the settling time out of captures; the first gap
+    /// forwarded spans the dropped samples
+    discard_samples: u32,
+    #[arg(long)]
+    /// Pin the sampling threads of the sensors, interrupt or polling, to
+    /// this CPU core, away from the servers, to cut the jitter of `gap`.
+    /// Compare `gap_jitter_us` (p99 less median gap) in the `[STATS]` lines
+    /// with and without it. Linux only: elsewhere a warning is logged and
+    /// threads are not pinned
+    sensor_core: Option<usize>,
+    #[arg(long)]
+    /// Log every SPI transfer of the sensors, register, direction and bytes,
+    /// as `[SPI]` lines at trace level, e.g. with `RUST_LOG=trace` to debug
+    /// the wiring during bring-up. Formatting a line per transfer slows
+    /// sampling and misses samples at high output data rates
+    trace_spi: bool,
+    #[arg(long, default_value = "1000")]
+    /// Interval of the `[STATS]` log lines of sensors and clients in
+    /// milliseconds. Stats are logged as `key=value` pairs
+    stats_interval_ms: u64,
+    #[arg(long, default_value = "0")]
+    /// Keep the last this many samples of each sensor (0 disables, at most
+    /// 65536) for WebSocket clients to have replayed on connect, requested
+    /// with e.g. `ws://host:port/?replay=1000`
+    history: usize,
+    #[arg(long, default_value = "100", value_parser = clap::value_parser!(u32).range(1..))]
+    /// Capacity of the sample channels, shared by all clients. A client
+    /// falls this many samples (plus `--client-buffer`) behind before
+    /// samples are skipped for it. Costs about 50 bytes per sample per
+    /// channel: at 4 kHz with two sensors, 8000 samples cover 1 s for 400 kB
+    channel_capacity: u32,
+    #[arg(long, default_value = "0")]
+    /// Samples buffered per TCP and WebSocket client on top of the channel
+    /// capacity, so a client that pauses briefly (e.g. a dashboard's garbage
+    /// collection) catches up without skipping. About 50 bytes per sample
+    /// per client
+    client_buffer: usize,
+    #[arg(long)]
+    /// Highest output data rate in Hz, e.g. to reduce CPU load and heat. The
+    /// configured ODR, and ODRs set by admin commands, are lowered to the
+    /// fastest supported rate not above it
+    max_rate: Option<f32>,
+    #[arg(long, value_parser = parse_sensor_odr)]
+    /// Output data rate of one sensor as `INDEX=RATE`, overriding `--odr`,
+    /// e.g. `0=4000` for vibration and `1=31.25` for tilt. Repeat for each
+    /// sensor. Clients decode each sensor with its own rate and scale from
+    /// the handshake
+    sensor_odr: Vec<(usize, ODR_LPF)>,
+    #[arg(long, value_parser = parse_sensor_range)]
+    /// Measurement range of one sensor as `INDEX=RANGE`, overriding
+    /// `--range`, e.g. `1=8g`. Repeat for each sensor
+    sensor_range: Vec<(usize, Range)>,
+    #[arg(long, value_parser = parse_sensor_hpf)]
+    /// High pass filter corner of one sensor as `INDEX=CORNER`, the
+    /// HPF_CORNER register value (0 disables the filter, 1-6), e.g. `1=0`.
+    /// Default for all sensors is 6
+    sensor_hpf: Vec<(usize, HPF_CORNER)>,
+    #[arg(long, value_parser = parse_orientation)]
+    /// Mounting orientation of a sensor as `INDEX=AXES`, the signed sensor
+    /// axes reported as x, y and z, e.g. `1=-y,x,z` for sensor 1 turned 90°
+    /// about z or `0=x,-y,-z` for sensor 0 mounted upside down. Repeat for
+    /// each rotated sensor
+    orientation: Vec<(usize, AxisTransform)>,
+    #[arg(long)]
+    /// JSON file listing the sensors, replacing the built-in two, e.g.
+    /// `[{"bus":0,"ss":0,"drdy":25},{"bus":1,"ss":2,"drdy":26,"odr":"31.25",
+    /// "range":"8g","hpf":0,"orientation":"-y,x,z","name":"motor mount"}]`.
+    /// Names are sent to clients in the handshake. Sensors are numbered
+    /// in order, the per-sensor flags apply on top. On SIGHUP the file is
+    /// read again: new sensors are started, removed ones stopped and changed
+    /// ones restarted, while clients stay connected. The handshake of later
+    /// clients still lists the sensors at startup, use `--status-config` to
+    /// announce the others
+    sensors: Option<std::path::PathBuf>,
+    #[arg(long)]
+    /// Print the identification and configuration of each sensor and exit
+    list_devices: bool,
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    /// Collect this many samples of each sensor, emit them and exit. Samples
+    /// are printed to stdout as JSON lines in time order, or written to
+    /// `--oneshot-output`; `--oneshot-csv` emits CSV instead. Servers run as
+    /// usual meanwhile; on Ctrl+C the samples collected so far are emitted
+    oneshot: Option<u32>,
+    #[arg(long, requires = "oneshot")]
+    /// Write the `--oneshot` samples to this capture file, or CSV file with
+    /// `--oneshot-csv`, instead of stdout
+    oneshot_output: Option<std::path::PathBuf>,
+    #[arg(long, requires = "oneshot")]
+    /// Emit the `--oneshot` samples as CSV with a header line, to stdout or
+    /// to `--oneshot-output`, instead of JSON lines or a capture file
+    oneshot_csv: bool,
+    #[arg(long)]
+    /// Exit with an error if any configured sensor fails to initialize,
+    /// instead of running with the ones that did
+    require_all: bool,
+    #[arg(long, conflicts_with_all = ["list_devices", "require_all"])]
+    /// Serve synthetic samples instead of reading the sensors: one source
+    /// per configured sensor at its output data rate, gravity along z with a
+    /// 10 Hz vibration along x. No hardware is touched, and SIGHUP, the
+    /// watchdog, idle standby and `--adaptive-odr-floor` have no effect
+    simulate: bool,
 }
 
 #[tokio::main]
@@ -34,19 +348,14 @@ async fn main() {
     let args = Args::parse();
     log::info!("Arguments: {args:#?}");
     // Accelerometer descriptors
-    #[allow(clippy::useless_vec)]
-    let acceldescs = vec![
-        AccelDesc {
-            bus: Bus::Spi0,
-            ss: SlaveSelect::Ss0,
-            drdy: 25, // GPIO pin for data ready
-        },
-        AccelDesc {
-            bus: Bus::Spi1,
-            ss: SlaveSelect::Ss2,
-            drdy: 26, // GPIO pin for data ready
-        },
-    ];
+    let acceldescs = load_descs(&args).unwrap_or_else(|e| {
+        log::error!("{e}");
+        std::process::exit(1);
+    });
+    if args.list_devices {
+        list_devices(&acceldescs);
+        return;
+    }
     // Create a running flag
     let running = Arc::new(AtomicBool::new(true));
     // Handle Ctrl+C to stop the server gracefully
@@ -58,35 +367,447 @@ async fn main() {
         }
     });
     // Create a broadcast channel for sending accelerometer data
-    let (sink, _) = tokio::sync::broadcast::channel(100);
+    let (sink, _) = tokio::sync::broadcast::channel(args.channel_capacity as usize);
     // Initialize the accelerometer
-    let mut pins = match accelerator_init(&acceldescs, sink.clone()) {
-        Ok(pins) => {
-            log::info!("Accelerometer initialized with {} pins", pins.len());
-            pins
+    let odr = cap_odr(args.odr, args.max_rate);
+    if odr != args.odr {
+        log::info!("Output data rate capped to {odr} by --max-rate");
+    }
+    let mut accel_config = ADXLConfig::default();
+    accel_config
+        .odr(odr)
+        .hpf(HPF)
+        .range(args.range)
+        .verify(true);
+    if args.trace_spi {
+        accel_config.trace(trace_spi);
+    }
+    log::info!("Accelerometer configuration: {odr}, {}", args.range);
+    for (index, desc) in acceldescs.iter().enumerate() {
+        if desc.odr.is_some() || desc.range.is_some() || desc.hpf.is_some() {
+            log::info!(
+                "Device {index} configuration: {}, {}, HPF corner {}",
+                desc.odr.unwrap_or(odr),
+                desc.range.unwrap_or(args.range),
+                desc.hpf.unwrap_or(HPF).val()
+            );
         }
-        Err(e) => {
-            log::error!("Failed to initialize accelerometer: {e}");
-            return;
+    }
+    let timing = Timing {
+        mode: args.timestamp,
+        latency: std::time::Duration::from_micros(args.timestamp_latency_us),
+    };
+    let stats_interval = std::time::Duration::from_millis(args.stats_interval_ms);
+    let sampling = Sampling {
+        timing,
+        stats_interval,
+        temp_every: args.temp_every,
+        units: args.units,
+        freshness: args.freshness,
+        discard: args.discard_samples,
+        epoch: std::time::Instant::now(),
+        core: args.sensor_core,
+    };
+    // Clients connected to the servers
+    let clients = Arc::new(AtomicUsize::new(0));
+    let setup = SensorSetup {
+        config: accel_config,
+        sampling,
+        sink: sink.clone(),
+        // the history needs every sample
+        clients: (args.history == 0).then(|| clients.clone()),
+    };
+    let sensors = if args.simulate {
+        Vec::new()
+    } else {
+        match accelerator_init(&acceldescs, &setup, args.require_all, &running) {
+            Ok(sensors) => {
+                log::info!("Accelerometer initialized with {} sensors", sensors.len());
+                sensors
+            }
+            Err(e) => {
+                log::error!("Failed to initialize accelerometer: {e}");
+                std::process::exit(1);
+            }
+        }
+    };
+    // Sensors samples are served from, with their descriptors and
+    // configuration
+    let (active, infos): (Vec<_>, Vec<_>) = if args.simulate {
+        log::info!("Simulating {} sensors", acceldescs.len());
+        acceldescs
+            .iter()
+            .enumerate()
+            .map(|(index, desc)| {
+                let (info, source) = simulated_sensor(&args, index as u32, desc, odr);
+                tokio::spawn(source.run(sink.clone(), running.clone()));
+                ((index as u32, desc.clone()), info)
+            })
+            .unzip()
+    } else {
+        sensors
+            .iter()
+            .map(|sensor| ((sensor.index, sensor.desc.clone()), sensor.info()))
+            .unzip()
+    };
+    let raw_sink = sink.clone();
+    // Collect a fixed number of samples, then shut down
+    let oneshot_task = args.oneshot.map(|count| {
+        // counts as a client, keeping the sensors out of idle standby
+        clients.fetch_add(1, Ordering::Relaxed);
+        tokio::spawn(oneshot(
+            raw_sink.subscribe(),
+            count as usize,
+            infos.clone(),
+            args.oneshot_output.clone(),
+            args.oneshot_csv,
+            clients.clone(),
+            running.clone(),
+        ))
+    });
+    // Sensors currently running, updated on reload
+    let (handles, _) = tokio::sync::watch::channel(sensors.iter().map(Sensor::handle).collect());
+    // Apply the deadband filter to the data sent to clients
+    let sink = match args.deadband {
+        Some(threshold) => deadband_sink(
+            sink,
+            threshold,
+            std::time::Duration::from_millis(args.keepalive_ms),
+            args.channel_capacity as usize,
+            running.clone(),
+        ),
+        None => sink,
+    };
+    // or align the samples of the sensors
+    let sink = if args.coalesce {
+        if acceldescs
+            .iter()
+            .any(|desc| desc.odr.is_some_and(|rate| rate != odr))
+        {
+            log::warn!("Coalescing sensors at different output data rates");
         }
+        let coalescer = Coalescer::new(active.iter().map(|(index, _)| *index), sample_period(odr));
+        log::info!(
+            "Coalescing sensors {:?} every {:?}",
+            coalescer.sensors(),
+            sample_period(odr)
+        );
+        coalesce_sink(&args, coalescer, running.clone(), sink)
+    } else {
+        sink
     };
+    let mut config = ServerConfig::default();
+    config
+        .axes(args.axes)
+        .batch_size(args.tcp_batch_min, args.tcp_batch_max)
+        .stats_interval(stats_interval)
+        .nodelay(!args.tcp_delay)
+        .write_timeout(std::time::Duration::from_millis(args.write_timeout_ms))
+        .client_buffer(args.client_buffer)
+        .handshake(Handshake {
+            units: args.units,
+            names: active
+                .iter()
+                .filter_map(|(index, desc)| Some((*index, desc.name.clone()?)))
+                .collect(),
+            ..Handshake::new(infos)
+        });
+    if let Some(Secret(token)) = &args.auth_token {
+        log::info!("Client authentication enabled");
+        config.auth_token(token.as_str());
+    }
+    config.clients(clients.clone());
+    let idle = args.idle_timeout_ms.map(|ms| IdleShutdown {
+        timeout: std::time::Duration::from_millis(ms),
+        clients,
+    });
+    if let Some(size) = args.tcp_send_buffer {
+        config.send_buffer_size(size);
+    }
+    if let Some(ms) = args.tcp_max_latency_ms {
+        config.max_latency(std::time::Duration::from_millis(ms));
+    }
+    // Publish periodic status records
+    let (status, _) = tokio::sync::broadcast::channel(16);
+    tokio::spawn(accelerator_status(
+        handles.subscribe(),
+        args.status_temperature,
+        args.status_config,
+        args.status_errors,
+        status.clone(),
+        running.clone(),
+    ));
+    // Send spectral summaries with the status records
+    if let Some(size) = args.fft_size.filter(|size| *size > 0) {
+        let mut fft =
+            SpectrumAnalyzer::new(size as usize, args.fft_overlap, args.fft_peaks as usize);
+        log::info!(
+            "Spectrum enabled: {} samples per window, every {} samples",
+            fft.size(),
+            fft.hop()
+        );
+        tokio::spawn(relay(
+            raw_sink.clone(),
+            status.clone(),
+            running.clone(),
+            move |data| fft.push(data).map(Status::Spectrum),
+        ));
+    }
+    // Send per-window statistics with the status records
+    if let Some(ms) = args.summary_ms {
+        let mut summarizer = Summarizer::new(std::time::Duration::from_millis(ms));
+        log::info!("Summaries enabled: every {:?}", summarizer.window());
+        tokio::spawn(relay(
+            raw_sink.clone(),
+            status.clone(),
+            running.clone(),
+            move |data| summarizer.push(data).map(Status::Summary),
+        ));
+    }
+    // Send inclination changes with the status records
+    if let Some(threshold) = args.tilt_threshold_deg {
+        if args.units != Units::G {
+            log::warn!(
+                "Tilt detection assumes samples in g, but units are {}",
+                args.units
+            );
+        }
+        let smoothing = std::time::Duration::from_millis(args.tilt_smoothing_ms);
+        log::info!(
+            "Tilt detection enabled: {threshold}°, smoothing {smoothing:?}, tolerance {} g",
+            args.tilt_tolerance
+        );
+        let mut tilt = TiltDetector::new(threshold, smoothing, args.tilt_tolerance);
+        tokio::spawn(relay(
+            raw_sink.clone(),
+            status.clone(),
+            running.clone(),
+            move |data| tilt.push(data).map(Status::OrientationChanged),
+        ));
+    }
+    let adaptive = args.adaptive_odr_floor.map(|floor| AdaptiveOdr {
+        floor,
+        sustain: 3 * stats_interval,
+        status: status.clone(),
+    });
+    config.status(status).errors(args.status_errors);
+    // Log a snapshot of every sensor on SIGUSR1, for bug reports
+    tokio::spawn(accelerator_diagnostics(
+        handles.subscribe(),
+        running.clone(),
+    ));
+    // Keep recent samples for late clients
+    if args.history > 0 {
+        let history = History::new(args.history);
+        log::info!("History enabled: {} samples per sensor", history.depth());
+        tokio::spawn(history.clone().record(sink.clone(), running.clone()));
+        config.history(history);
+    }
+    // Offer derived channels
+    if let Some(alpha) = args.ema_alpha {
+        log::info!("EMA channel enabled: alpha {alpha}");
+        let (out, _) = tokio::sync::broadcast::channel(args.channel_capacity as usize);
+        let mut ema = Ema::new(alpha);
+        tokio::spawn(relay(
+            raw_sink.clone(),
+            out.clone(),
+            running.clone(),
+            move |data| Some(ema.filter(data)),
+        ));
+        config.channel("ema", out);
+    }
+    if let Some(hz) = args.magnitude_hz.filter(|hz| *hz > 0.0) {
+        if args.units != Units::G {
+            log::warn!(
+                "Magnitude channel assumes samples in g, but units are {}",
+                args.units
+            );
+        }
+        log::info!("Magnitude channel enabled: {hz} Hz");
+        let (out, _) = tokio::sync::broadcast::channel(args.channel_capacity as usize);
+        let mut magnitude = Magnitude::new(
+            std::time::Duration::try_from_secs_f32(1.0 / hz).unwrap_or(std::time::Duration::MAX),
+        );
+        tokio::spawn(relay(
+            raw_sink.clone(),
+            out.clone(),
+            running.clone(),
+            move |data| magnitude.filter(data),
+        ));
+        config.channel("magnitude", out);
+    }
+    if let Some(hz) = args.decimate_hz.filter(|hz| *hz > 0.0) {
+        // one decimator per sensor, from its own output data rate
+        let plan = |odr: ODR_LPF| {
+            DecimationPlan::new(f32::from(odr), odr.bandwidth_hz(), hz).unwrap_or_else(|e| {
+                log::error!("--decimate-hz {hz} at an output data rate of {odr}: {e}");
+                std::process::exit(1);
+            })
+        };
+        let mut decimators = BTreeMap::new();
+        for (index, desc) in &active {
+            let plan = plan(desc.odr.unwrap_or(odr));
+            if plan.exceeds_input() {
+                log::error!(
+                    "--decimate-hz {hz} is above the {} Hz output data rate of sensor {}",
+                    plan.input_hz,
+                    index
+                );
+                std::process::exit(1);
+            }
+            if !plan.is_exact() {
+                log::warn!(
+                    "Decimated channel of sensor {} runs at {} Hz: {} Hz is not an integer fraction of {} Hz",
+                    index,
+                    plan.output_hz,
+                    hz,
+                    plan.input_hz
+                );
+            }
+            log::info!(
+                "Decimated channel of sensor {}: {} Hz to {} Hz, passband {} Hz",
+                index,
+                plan.input_hz,
+                plan.output_hz,
+                plan.passband_hz
+            );
+            decimators.insert(*index, plan.decimator());
+        }
+        // sensors added on reload run at the shared rate
+        let mut fallback = plan(odr).decimator();
+        let (out, _) = tokio::sync::broadcast::channel(args.channel_capacity as usize);
+        tokio::spawn(relay(
+            raw_sink.clone(),
+            out.clone(),
+            running.clone(),
+            move |data| {
+                decimators
+                    .get_mut(&{ data.idx })
+                    .unwrap_or(&mut fallback)
+                    .filter(data)
+            },
+        ));
+        config.channel("decimated", out);
+    }
     // Start the TCP server
-    let srv_task = tokio::spawn(tcp_server(args.port, running.clone(), sink));
+    let srv_task = tokio::spawn(serve(
+        "TCP",
+        tcp_server(args.port, running.clone(), sink.clone(), config.clone()),
+        running.clone(),
+    ));
     log::info!("TCP server started on port {}", args.port);
+    // Start the Unix-domain socket server
+    let uds_task = args.uds.clone().map(|path| {
+        log::info!("Unix-domain server started on {}", path.display());
+        tokio::spawn(serve(
+            "Unix-domain",
+            uds_server(path, running.clone(), sink.clone(), config.clone()),
+            running.clone(),
+        ))
+    });
+    // Start the UDP server
+    let udp_task = args.udp_port.map(|port| {
+        log::info!("UDP server started on port {port}");
+        tokio::spawn(serve(
+            "UDP",
+            udp_server(port, running.clone(), sink.clone(), config.clone()),
+            running.clone(),
+        ))
+    });
+    // Start the WebSocket server
+    let ws_task = args.ws_port.map(|port| {
+        let mut config = config.clone();
+        if args.allow_commands {
+            let (tx, mut rx) = tokio::sync::mpsc::channel::<CommandRequest>(8);
+            let max_rate = args.max_rate;
+            let sensors = handles.subscribe();
+            // Apply commands to the addressed accelerometer
+            tokio::spawn(async move {
+                while let Some(request) = rx.recv().await {
+                    let cmd = request.cmd;
+                    let sensor = sensors
+                        .borrow()
+                        .iter()
+                        .find(|sensor| sensor.index == cmd.idx())
+                        .cloned();
+                    match sensor {
+                        Some(sensor) => {
+                            let (index, device) = (sensor.index, sensor.device);
+                            tokio::task::spawn_blocking(move || {
+                                let res = device
+                                    .with(|device| apply_command(index, device, cmd, max_rate));
+                                if let Err(e) = res {
+                                    request.reject(e);
+                                }
+                            });
+                        }
+                        None => {
+                            log::warn!("No accelerometer at index {}", cmd.idx());
+                            request.reject(format!("no sensor {}", cmd.idx()));
+                        }
+                    }
+                }
+            });
+            config.commands(tx);
+        }
+        log::info!("WebSocket server started on port {port}");
+        tokio::spawn(serve(
+            "WebSocket",
+            wsock_server(port, running.clone(), sink.clone(), config),
+            running.clone(),
+        ))
+    });
+    // Reload the sensor list on SIGHUP
+    let (reload, reloads) = tokio::sync::mpsc::channel(1);
+    if !args.simulate {
+        tokio::spawn(reload_on_hangup(args.clone(), reload));
+    }
+    // Supervise the accelerometers
+    let watchdog = match args.watchdog_ms {
+        Some(0) => Watchdog::Disabled,
+        Some(ms) => Watchdog::Timeout(std::time::Duration::from_millis(ms)),
+        None => Watchdog::Default,
+    };
+    let sensors = tokio::spawn(accelerator_supervisor(
+        sensors,
+        Supervision {
+            watchdog,
+            idle,
+            adaptive,
+        },
+        setup,
+        reloads,
+        handles,
+        running.clone(),
+    ));
     // Wait
     while running.load(Ordering::Relaxed) {
         tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
     }
     log::info!("Stopping TCP server...");
     srv_task.abort();
+    if let Some(ws_task) = ws_task {
+        ws_task.abort();
+    }
+    if let Some(udp_task) = udp_task {
+        udp_task.abort();
+    }
+    if let Some(uds_task) = uds_task {
+        uds_task.abort();
+        // dropping the server removes the socket file
+        let _ = uds_task.await;
+    }
     log::info!("Server stopped, exiting...");
     // Clean up GPIO pins
-    for mut pin in pins.drain(..) {
-        if let Err(e) = pin.clear_async_interrupt() {
-            log::error!("Failed to clear async interrupt for {pin:?}: {e}");
-        } else {
-            log::info!("Cleared async interrupt for {pin:?}");
-        }
+    let sensors = sensors.await.unwrap_or_else(|e| {
+        log::error!("Supervisor task failed: {e}");
+        Vec::new()
+    });
+    for sensor in sensors {
+        sensor.stop();
+    }
+    if let Some(oneshot_task) = oneshot_task {
+        let _ = oneshot_task.await;
     }
     if let Err(e) = srv_task.await {
         log::error!("TCP server task failed: {e}");
@@ -94,3 +815,434 @@ async fn main() {
         log::info!("TCP server task completed successfully");
     }
 }
+
+/// Run the `name` server, shutting the daemon down if it fails, e.g. to bind
+async fn serve(
+    name: &str,
+    server: impl Future<Output = std::io::Result<()>>,
+    running: Arc<AtomicBool>,
+) {
+    if let Err(e) = server.await {
+        log::error!("{name} server failed: {e}");
+        running.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Synthetic stand-in for sensor `index` of `desc`, for `--simulate`, and
+/// the configuration it reports. `odr` is the rate of sensors without their
+/// own
+fn simulated_sensor(
+    args: &Args,
+    index: u32,
+    desc: &AccelDesc,
+    odr: ODR_LPF,
+) -> (SensorInfo, impl SampleSource) {
+    let (odr, range) = (desc.odr.unwrap_or(odr), desc.range.unwrap_or(args.range));
+    let info = SensorInfo {
+        idx: index,
+        odr: odr.into(),
+        range: range.into(),
+        hpf: desc.hpf.unwrap_or(HPF).val(),
+        scale: 1.0 / range.sensitivity_lsb_per_g() as f32,
+    };
+    let scale = match args.units {
+        Units::G => 1.0,
+        Units::Mps2 => STANDARD_GRAVITY,
+        Units::Counts => 1.0 / info.scale,
+    };
+    let source = SyntheticSource::new(index, sample_period(odr), move |t| {
+        let vibration = 0.01 * (std::f32::consts::TAU * 10.0 * t).sin();
+        [vibration * scale, 0.0, scale]
+    });
+    (info, source)
+}
+
+/// Command line value kept out of the logged arguments
+#[derive(Clone)]
+struct Secret(String);
+
+impl std::str::FromStr for Secret {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err("empty secret".into());
+        }
+        Ok(Secret(s.to_owned()))
+    }
+}
+
+impl std::fmt::Debug for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Secret(..)")
+    }
+}
+
+/// Sensors without a `--sensors` file
+fn builtin_descs() -> Vec<AccelDesc> {
+    vec![
+        AccelDesc {
+            bus: Bus::Spi0,
+            ss: SlaveSelect::Ss0,
+            drdy: 25, // GPIO pin for data ready
+            orientation: AxisTransform::IDENTITY,
+            odr: None,
+            range: None,
+            hpf: None,
+            name: None,
+        },
+        AccelDesc {
+            bus: Bus::Spi1,
+            ss: SlaveSelect::Ss2,
+            drdy: 26, // GPIO pin for data ready
+            orientation: AxisTransform::IDENTITY,
+            odr: None,
+            range: None,
+            hpf: None,
+            name: None,
+        },
+    ]
+}
+
+/// Entry of a `--sensors` file
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SensorSpec {
+    /// SPI bus, 0-6
+    bus: u8,
+    /// Chip select, 0-15
+    ss: u8,
+    /// GPIO pin of the DRDY interrupt
+    drdy: u8,
+    /// Mounting orientation, as `--orientation`
+    orientation: Option<String>,
+    /// Output data rate, as `--odr`
+    odr: Option<String>,
+    /// Measurement range, as `--range`
+    range: Option<String>,
+    /// High pass filter corner, as `--sensor-hpf`
+    hpf: Option<u8>,
+    /// Name shown to clients, e.g. `motor mount`
+    name: Option<String>,
+}
+
+impl SensorSpec {
+    fn desc(&self) -> Result<AccelDesc, String> {
+        let bus = match self.bus {
+            0 => Bus::Spi0,
+            1 => Bus::Spi1,
+            2 => Bus::Spi2,
+            3 => Bus::Spi3,
+            4 => Bus::Spi4,
+            5 => Bus::Spi5,
+            6 => Bus::Spi6,
+            bus => return Err(format!("invalid SPI bus {bus}, expected 0-6")),
+        };
+        let ss = match self.ss {
+            0 => SlaveSelect::Ss0,
+            1 => SlaveSelect::Ss1,
+            2 => SlaveSelect::Ss2,
+            3 => SlaveSelect::Ss3,
+            4 => SlaveSelect::Ss4,
+            5 => SlaveSelect::Ss5,
+            6 => SlaveSelect::Ss6,
+            7 => SlaveSelect::Ss7,
+            8 => SlaveSelect::Ss8,
+            9 => SlaveSelect::Ss9,
+            10 => SlaveSelect::Ss10,
+            11 => SlaveSelect::Ss11,
+            12 => SlaveSelect::Ss12,
+            13 => SlaveSelect::Ss13,
+            14 => SlaveSelect::Ss14,
+            15 => SlaveSelect::Ss15,
+            ss => return Err(format!("invalid chip select {ss}, expected 0-15")),
+        };
+        let orientation = match &self.orientation {
+            Some(axes) => axes
+                .parse()
+                .map_err(|_| format!("invalid orientation `{axes}`"))?,
+            None => AxisTransform::IDENTITY,
+        };
+        let odr = self
+            .odr
+            .as_deref()
+            .map(|odr| {
+                odr.parse()
+                    .map_err(|_| format!("invalid output data rate `{odr}`"))
+            })
+            .transpose()?;
+        let range = self
+            .range
+            .as_deref()
+            .map(|range| {
+                range
+                    .parse()
+                    .map_err(|_| format!("invalid range `{range}`"))
+            })
+            .transpose()?;
+        let hpf = self
+            .hpf
+            .map(|corner| {
+                HPF_CORNER::try_from(corner)
+                    .map_err(|_| format!("invalid HPF corner {corner}, expected 0-6"))
+            })
+            .transpose()?;
+        Ok(AccelDesc {
+            bus,
+            ss,
+            drdy: self.drdy,
+            orientation,
+            odr,
+            range,
+            hpf,
+            name: self.name.clone(),
+        })
+    }
+}
+
+/// Sensor list: the `--sensors` file or the built-in sensors, with the
+/// per-sensor flags applied and output data rates capped to `--max-rate`
+fn load_descs(args: &Args) -> Result<Vec<AccelDesc>, String> {
+    let mut acceldescs = match &args.sensors {
+        Some(path) => {
+            let read_err = |e: &dyn std::fmt::Display| format!("{}: {e}", path.display());
+            let json = std::fs::read_to_string(path).map_err(|e| read_err(&e))?;
+            let specs: Vec<SensorSpec> = serde_json::from_str(&json).map_err(|e| read_err(&e))?;
+            specs
+                .iter()
+                .enumerate()
+                .map(|(index, spec)| {
+                    spec.desc()
+                        .map_err(|e| read_err(&format!("device {index}: {e}")))
+                })
+                .collect::<Result<Vec<_>, _>>()?
+        }
+        None => builtin_descs(),
+    };
+    for desc in &mut acceldescs {
+        desc.odr = desc.odr.map(|odr| cap_odr(odr, args.max_rate));
+    }
+    for &(index, orientation) in &args.orientation {
+        log::info!("Device {index} orientation: {orientation}");
+        sensor_desc(&mut acceldescs, index, "--orientation")?.orientation = orientation;
+    }
+    for &(index, odr) in &args.sensor_odr {
+        let capped = cap_odr(odr, args.max_rate);
+        if capped != odr {
+            log::info!("Device {index} output data rate capped to {capped} by --max-rate");
+        }
+        sensor_desc(&mut acceldescs, index, "--sensor-odr")?.odr = Some(capped);
+    }
+    for &(index, range) in &args.sensor_range {
+        sensor_desc(&mut acceldescs, index, "--sensor-range")?.range = Some(range);
+    }
+    for &(index, hpf) in &args.sensor_hpf {
+        sensor_desc(&mut acceldescs, index, "--sensor-hpf")?.hpf = Some(hpf);
+    }
+    validate_descs(&acceldescs)?;
+    Ok(acceldescs)
+}
+
+/// Descriptor of sensor `index` given to a per-sensor `flag`
+fn sensor_desc<'a>(
+    descs: &'a mut [AccelDesc],
+    index: usize,
+    flag: &str,
+) -> Result<&'a mut AccelDesc, String> {
+    descs
+        .get_mut(index)
+        .ok_or_else(|| format!("{flag}: no device {index}"))
+}
+
+/// Send the sensor list to `reload` whenever the daemon receives `SIGHUP`.
+/// Lists that fail to load are logged and skipped, keeping the sensors as
+/// they are
+async fn reload_on_hangup(args: Args, reload: tokio::sync::mpsc::Sender<Vec<AccelDesc>>) {
+    use tokio::signal::unix::{SignalKind, signal};
+    let mut hup = match signal(SignalKind::hangup()) {
+        Ok(hup) => hup,
+        Err(e) => {
+            log::error!("[ACCEL] Failed to listen for SIGHUP: {e}");
+            return;
+        }
+    };
+    while hup.recv().await.is_some() {
+        if args.sensors.is_none() {
+            log::warn!("[ACCEL] SIGHUP ignored: no --sensors file to reload");
+            continue;
+        }
+        log::info!("[ACCEL] SIGHUP: reloading the sensor list");
+        match load_descs(&args) {
+            Ok(descs) => {
+                if reload.send(descs).await.is_err() {
+                    return;
+                }
+            }
+            Err(e) => log::error!("[ACCEL] Reload failed, keeping the current sensors: {e}"),
+        }
+    }
+}
+
+/// Splits a per-sensor `INDEX=VALUE` argument
+fn split_index(arg: &str) -> Result<(usize, &str), String> {
+    let (index, value) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("expected INDEX=VALUE, got `{arg}`"))?;
+    let index = index
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid sensor index `{index}`"))?;
+    Ok((index, value))
+}
+
+/// Parses an `--orientation` argument
+fn parse_orientation(arg: &str) -> Result<(usize, AxisTransform), String> {
+    let (index, axes) = split_index(arg)?;
+    let axes = axes.parse().map_err(|_| {
+        format!("invalid axes `{axes}`, expected each of x, y and z once, e.g. `-y,x,z`")
+    })?;
+    Ok((index, axes))
+}
+
+/// Parses a `--decimate-hz` argument, 0 disables the channel
+fn parse_decimate_hz(arg: &str) -> Result<f32, String> {
+    match arg.parse::<f32>() {
+        Ok(hz) if hz.is_finite() && hz >= 0.0 => Ok(hz),
+        _ => Err(format!("invalid rate `{arg}`, expected a number of Hz")),
+    }
+}
+
+/// Parses a `--sensor-odr` argument
+fn parse_sensor_odr(arg: &str) -> Result<(usize, ODR_LPF), String> {
+    let (index, odr) = split_index(arg)?;
+    let odr = odr
+        .parse()
+        .map_err(|_| format!("invalid output data rate `{odr}`"))?;
+    Ok((index, odr))
+}
+
+/// Parses a `--sensor-range` argument
+fn parse_sensor_range(arg: &str) -> Result<(usize, Range), String> {
+    let (index, range) = split_index(arg)?;
+    let range = range
+        .parse()
+        .map_err(|_| format!("invalid range `{range}`, expected 2g, 4g or 8g"))?;
+    Ok((index, range))
+}
+
+/// Parses a `--sensor-hpf` argument
+fn parse_sensor_hpf(arg: &str) -> Result<(usize, HPF_CORNER), String> {
+    let (index, corner) = split_index(arg)?;
+    let hpf = corner
+        .trim()
+        .parse::<u8>()
+        .ok()
+        .and_then(|corner| HPF_CORNER::try_from(corner).ok())
+        .ok_or_else(|| format!("invalid HPF corner `{corner}`, expected 0-6"))?;
+    Ok((index, hpf))
+}
+
+/// Collects `count` samples of each of `sensors` from `source`, or fewer if
+/// the daemon is stopped first, emits them with [`emit_oneshot`] and stops
+/// the daemon. Counted in `clients` until done collecting, by the caller
+/// from before it is spawned
+async fn oneshot(
+    mut source: tokio::sync::broadcast::Receiver<AccelData>,
+    count: usize,
+    sensors: Vec<SensorInfo>,
+    output: Option<std::path::PathBuf>,
+    csv: bool,
+    clients: Arc<AtomicUsize>,
+    running: Arc<AtomicBool>,
+) {
+    use tokio::sync::broadcast::error::RecvError;
+    log::info!(
+        "Collecting {count} samples from each of {} sensors",
+        sensors.len()
+    );
+    let mut collected: BTreeMap<u32, Vec<AccelData>> = sensors
+        .iter()
+        .map(|sensor| (sensor.idx, Vec::with_capacity(count)))
+        .collect();
+    while running.load(Ordering::Relaxed) && collected.values().any(|s| s.len() < count) {
+        // wake up regularly to notice a shutdown while sensors are silent
+        let Ok(received) =
+            tokio::time::timeout(std::time::Duration::from_millis(100), source.recv()).await
+        else {
+            continue;
+        };
+        match received {
+            Ok(data) => {
+                if let Some(samples) = collected
+                    .get_mut(&{ data.idx })
+                    .filter(|samples| samples.len() < count)
+                {
+                    samples.push(data);
+                }
+            }
+            Err(RecvError::Lagged(n)) => {
+                log::warn!("Oneshot capture lagged behind, skipped {n} samples");
+            }
+            Err(RecvError::Closed) => break,
+        }
+    }
+    clients.fetch_sub(1, Ordering::Relaxed);
+    let mut samples: Vec<AccelData> = collected.into_values().flatten().collect();
+    samples.sort_by_key(|data| data.time);
+    match emit_oneshot(output.as_deref(), csv, sensors, &samples) {
+        Ok(()) => log::info!("Oneshot capture complete: {} samples", samples.len()),
+        Err(e) => log::error!("Failed to write the oneshot capture: {e}"),
+    }
+    running.store(false, Ordering::SeqCst);
+}
+
+/// Writes `samples` to `output`, a capture file or a CSV file if `csv`, or
+/// to stdout as JSON lines or CSV if `None`
+fn emit_oneshot(
+    output: Option<&std::path::Path>,
+    csv: bool,
+    sensors: Vec<SensorInfo>,
+    samples: &[AccelData],
+) -> std::io::Result<()> {
+    use std::io::Write;
+    let mut out: Box<dyn Write> = match output {
+        Some(path) => Box::new(std::io::BufWriter::new(std::fs::File::create(path)?)),
+        None => Box::new(std::io::BufWriter::new(std::io::stdout().lock())),
+    };
+    if csv {
+        writeln!(out, "{CSV_HEADER}")?;
+        for data in samples {
+            writeln!(out, "{}", data.to_csv_row())?;
+        }
+    } else if output.is_some() {
+        let start = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|since| since.as_micros() as u64)
+            .unwrap_or_default();
+        let mut writer = CaptureWriter::new(out, &CaptureHeader { start, sensors })?;
+        for data in samples {
+            writer.write(data)?;
+        }
+        return writer.flush();
+    } else {
+        for data in samples {
+            serde_json::to_writer(&mut out, data)?;
+            writeln!(out)?;
+        }
+    }
+    out.flush()
+}
+
+/// Returns a channel of the samples of `sink` coalesced into tuples
+fn coalesce_sink(
+    args: &Args,
+    mut coalescer: Coalescer,
+    running: Arc<AtomicBool>,
+    sink: tokio::sync::broadcast::Sender<AccelData>,
+) -> tokio::sync::broadcast::Sender<AccelData> {
+    let (out, _) = tokio::sync::broadcast::channel(args.channel_capacity as usize);
+    tokio::spawn(relay(sink, out.clone(), running, move |data| {
+        coalescer.push(data)
+    }));
+    out
+}
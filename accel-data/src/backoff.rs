@@ -0,0 +1,90 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    time::Duration,
+};
+
+/// Exponential backoff policy for reconnect and restart loops.
+///
+/// The first delay is `initial`, each following one is `multiplier` times
+/// the previous, up to `max`. Every delay is randomized by up to `jitter`
+/// (a fraction of the delay) in either direction, and never exceeds `max`.
+///
+/// ```
+/// use accel_data::Backoff;
+/// use std::time::Duration;
+///
+/// let mut backoff = Backoff::new(Duration::from_millis(100), Duration::from_secs(1));
+/// backoff.jitter(0.0);
+/// let delays: Vec<_> = backoff.by_ref().take(6).map(|d| d.as_millis()).collect();
+/// assert_eq!(delays, [100, 200, 400, 800, 1000, 1000]);
+///
+/// backoff.reset();
+/// backoff.jitter(0.25);
+/// for (i, delay) in backoff.take(100).enumerate() {
+///     let base = (100.0 * 2f64.powi(i as i32)).min(1000.0);
+///     let ms = delay.as_secs_f64() * 1e3;
+///     assert!(ms >= base * 0.75 - 1e-6 && ms <= (base * 1.25).min(1000.0) + 1e-6);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    multiplier: f64,
+    jitter: f64,
+    current: Option<Duration>,
+}
+
+impl Backoff {
+    /// Create a backoff from `initial` to `max`, doubling each time with
+    /// 10% jitter
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial: initial.min(max),
+            max,
+            multiplier: 2.0,
+            jitter: 0.1,
+            current: None,
+        }
+    }
+
+    /// Sets the growth factor between consecutive delays.
+    /// Default is 2, values below 1 are treated as 1
+    pub fn multiplier(&mut self, multiplier: f64) -> &mut Self {
+        self.multiplier = multiplier.max(1.0);
+        self
+    }
+
+    /// Sets the randomization of each delay, as a fraction of the delay.
+    /// Default is 0.1, clamped to `[0, 1]`
+    pub fn jitter(&mut self, jitter: f64) -> &mut Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Delay before the next attempt
+    pub fn next_delay(&mut self) -> Duration {
+        let base = match self.current {
+            None => self.initial,
+            Some(current) => current.mul_f64(self.multiplier).min(self.max),
+        };
+        self.current = Some(base);
+        // uniform in [-1, 1]
+        let unit = RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64 * 2.0 - 1.0;
+        base.mul_f64(1.0 + self.jitter * unit).min(self.max)
+    }
+
+    /// Start over from the initial delay, e.g. after a successful attempt
+    pub fn reset(&mut self) {
+        self.current = None;
+    }
+}
+
+impl Iterator for Backoff {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Duration> {
+        Some(self.next_delay())
+    }
+}
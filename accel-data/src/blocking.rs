@@ -0,0 +1,252 @@
+//! Thread-per-client TCP server without an async runtime
+//!
+//! Serves the same stream as the async TCP server: the stream header, a
+//! [`Handshake`] frame if configured, then [`DataFrame`]s (see [`crate::frame`]). Samples arrive on a
+//! [`std::sync::mpsc`] channel and are fanned out to a bounded queue per
+//! client. A client whose queue is full misses samples instead of holding up
+//! the others.
+use crate::{AccelData, AxisMask, DataFrame, Handshake, frame::stream_header, handshake_frame};
+use std::{
+    io::{self, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Interval at which idle loops check the running flag and accept clients
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Blocking server configuration
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub(crate) axes: AxisMask,
+    pub(crate) handshake: Option<Handshake>,
+    pub(crate) frame_size: usize,
+    pub(crate) queue_len: usize,
+    pub(crate) max_latency: Option<Duration>,
+    pub(crate) write_timeout: Duration,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            axes: AxisMask::default(),
+            handshake: None,
+            frame_size: 1024,
+            queue_len: 1024,
+            max_latency: None,
+            write_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Sets the axes forwarded to clients.
+    /// Default is all axes
+    pub fn axes(&mut self, axes: AxisMask) -> &mut Self {
+        self.axes = axes;
+        self
+    }
+
+    /// Sets the handshake sent to every client before any data
+    pub fn handshake(&mut self, handshake: Handshake) -> &mut Self {
+        self.handshake = Some(handshake);
+        self
+    }
+
+    /// Sets the size of the data frames in bytes, including the header.
+    /// Default is 1024 bytes
+    pub fn frame_size(&mut self, size: usize) -> &mut Self {
+        self.frame_size = size;
+        self
+    }
+
+    /// Sets the number of samples queued per client before it misses
+    /// samples. Default is 1024
+    pub fn queue_len(&mut self, len: usize) -> &mut Self {
+        self.queue_len = len.max(1);
+        self
+    }
+
+    /// Sets the longest a sample waits in a partially filled data frame.
+    /// Default is no limit
+    pub fn max_latency(&mut self, latency: Duration) -> &mut Self {
+        self.max_latency = Some(latency);
+        self
+    }
+
+    /// Sets the deadline of each write to a client.
+    /// Default is 5 seconds
+    ///
+    /// A client whose write does not complete in time is dropped.
+    pub fn write_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.write_timeout = timeout;
+        self
+    }
+}
+
+/// Queue of a connected client
+struct Client {
+    addr: SocketAddr,
+    queue: SyncSender<AccelData>,
+    /// Samples missed since the last one queued
+    dropped: u64,
+}
+
+impl Client {
+    /// Queue a sample, returning `false` once the client is gone
+    fn send(&mut self, data: AccelData) -> bool {
+        match self.queue.try_send(data) {
+            Ok(()) => {
+                if self.dropped > 0 {
+                    log::warn!("[NET] {}> Dropped {} samples", self.addr, self.dropped);
+                    self.dropped = 0;
+                }
+                true
+            }
+            Err(TrySendError::Full(_)) => {
+                self.dropped += 1;
+                true
+            }
+            Err(TrySendError::Disconnected(_)) => false,
+        }
+    }
+}
+
+/// Serve the samples received from `source` over TCP on `port`, using one
+/// thread per client.
+///
+/// Blocks until `running` is cleared or all senders of `source` are dropped.
+pub fn tcp_server(
+    port: u16,
+    running: Arc<AtomicBool>,
+    source: Receiver<AccelData>,
+    config: ServerConfig,
+) -> io::Result<()> {
+    log::info!("[NET] Starting blocking TCP server on port {port}");
+    let listener = TcpListener::bind(SocketAddr::from(([0, 0, 0, 0], port)))?;
+    listener.set_nonblocking(true)?;
+    log::info!("[NET] Blocking TCP server listening on port {port}");
+    let mut clients = Vec::new();
+    let mut accept_at = Instant::now();
+    while running.load(Ordering::Relaxed) {
+        if Instant::now() >= accept_at {
+            accept_clients(&listener, &running, &config, &mut clients);
+            accept_at = Instant::now() + POLL_INTERVAL;
+        }
+        match source.recv_timeout(POLL_INTERVAL) {
+            Ok(data) => clients.retain_mut(|client| client.send(data)),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+    log::info!("[NET] Blocking TCP server stopped");
+    Ok(())
+}
+
+/// Accept the pending connections, starting a thread for each
+fn accept_clients(
+    listener: &TcpListener,
+    running: &Arc<AtomicBool>,
+    config: &ServerConfig,
+    clients: &mut Vec<Client>,
+) {
+    loop {
+        match listener.accept() {
+            Ok((socket, addr)) => {
+                log::info!("[NET] Accepted connection from {addr}");
+                let (queue, rx) = mpsc::sync_channel(config.queue_len);
+                let (running, config) = (running.clone(), config.clone());
+                let spawned = thread::Builder::new()
+                    .name(format!("tcp-{addr}"))
+                    .spawn(move || handle_client(socket, addr, rx, running, config));
+                match spawned {
+                    Ok(_) => clients.push(Client {
+                        addr,
+                        queue,
+                        dropped: 0,
+                    }),
+                    Err(e) => log::error!("[NET] {addr}> Failed to start client thread: {e}"),
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+            Err(e) => {
+                log::error!("[NET] Failed to accept connection on server: {e}");
+                break;
+            }
+        }
+    }
+}
+
+fn handle_client(
+    mut socket: TcpStream,
+    addr: SocketAddr,
+    queue: Receiver<AccelData>,
+    running: Arc<AtomicBool>,
+    config: ServerConfig,
+) {
+    log::info!("[NET] {addr}> Handling client.");
+    if let Err(e) = socket
+        .set_nonblocking(false)
+        .and_then(|_| socket.set_nodelay(true))
+        .and_then(|_| socket.set_write_timeout(Some(config.write_timeout)))
+    {
+        log::warn!("[NET] {addr}> Failed to configure socket: {e}");
+    }
+    if let Err(e) = socket.write_all(&stream_header()) {
+        log::error!("[NET] {addr}> Error sending stream header: {e}");
+        return;
+    }
+    if let Some(handshake) = &config.handshake {
+        match handshake_frame(handshake) {
+            Some(frame) => {
+                if let Err(e) = socket.write_all(&frame) {
+                    log::error!("[NET] {addr}> Error sending handshake: {e}");
+                    return;
+                }
+            }
+            None => log::warn!("[NET] {addr}> Dropped a record too long for a frame"),
+        }
+    }
+    let mut frame = DataFrame::new(config.axes, config.frame_size);
+    // deadline of the oldest sample in the frame
+    let mut flush_at: Option<Instant> = None;
+    while running.load(Ordering::Relaxed) {
+        let timeout = flush_at.map_or(POLL_INTERVAL, |at| {
+            at.saturating_duration_since(Instant::now())
+                .min(POLL_INTERVAL)
+        });
+        match queue.recv_timeout(timeout) {
+            Ok(data) => {
+                if !frame.has_room() {
+                    if let Err(e) = socket.write_all(frame.as_bytes()) {
+                        log::error!("[NET] {addr}> Error sending data, dropping client: {e}");
+                        break;
+                    }
+                    frame.clear();
+                    flush_at = None;
+                }
+                frame.push(&data);
+                if flush_at.is_none() {
+                    flush_at = config.max_latency.map(|latency| Instant::now() + latency);
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+        if flush_at.is_some_and(|at| Instant::now() >= at) {
+            if let Err(e) = socket.write_all(frame.as_bytes()) {
+                log::error!("[NET] {addr}> Error sending data, dropping client: {e}");
+                break;
+            }
+            frame.clear();
+            flush_at = None;
+        }
+    }
+    log::info!("[NET] {addr}> Client disconnected.");
+}
@@ -0,0 +1,184 @@
+//! Capture file format
+//!
+//! A capture file starts with a header, followed by packed records:
+//!
+//! | Field      | Type          | Description                                 |
+//! |------------|---------------|---------------------------------------------|
+//! | magic      | `[u8; 4]`     | [`CAPTURE_MAGIC`]                           |
+//! | version    | `u8`          | [`CAPTURE_VERSION`]                         |
+//! | start      | `u64`         | Start time, microseconds since UNIX epoch   |
+//! | count      | `u16`         | Number of sensor index entries              |
+//! | sensors    | `count` × 17  | Sensor index: `idx: u32`, `odr: f32`, `range: f32`, `hpf: u8`, `scale: f32` |
+//! | records    | `n` × 32      | [`AccelData`] as written by [`AccelData::as_bytes`] |
+//!
+//! All fields are little endian. The sensor index records the configuration of
+//! every sensor, including the scale factor, so raw counts can be recovered
+//! from the samples later.
+use crate::{AccelData, SensorInfo};
+use std::io::{self, Read, Write};
+
+/// Magic bytes at the start of a capture file
+pub const CAPTURE_MAGIC: [u8; 4] = *b"ACAP";
+/// Current capture file format version
+pub const CAPTURE_VERSION: u8 = 3;
+
+#[derive(Debug, Clone, PartialEq)]
+/// Capture file header
+pub struct CaptureHeader {
+    /// Start time, in microseconds since UNIX epoch
+    pub start: u64,
+    /// Configuration of each recorded sensor
+    pub sensors: Vec<SensorInfo>,
+}
+
+impl CaptureHeader {
+    /// Look up the configuration of sensor `idx`
+    pub fn sensor(&self, idx: u32) -> Option<&SensorInfo> {
+        self.sensors.iter().find(|s| s.idx == idx)
+    }
+
+    fn write_to(&self, w: &mut impl Write) -> io::Result<()> {
+        let count = u16::try_from(self.sensors.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "too many sensors"))?;
+        w.write_all(&CAPTURE_MAGIC)?;
+        w.write_all(&[CAPTURE_VERSION])?;
+        w.write_all(&self.start.to_le_bytes())?;
+        w.write_all(&count.to_le_bytes())?;
+        for sensor in &self.sensors {
+            w.write_all(&sensor.idx.to_le_bytes())?;
+            w.write_all(&sensor.odr.to_le_bytes())?;
+            w.write_all(&sensor.range.to_le_bytes())?;
+            w.write_all(&[sensor.hpf])?;
+            w.write_all(&sensor.scale.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    fn read_from(r: &mut impl Read) -> io::Result<Self> {
+        let mut magic = [0u8; 4];
+        r.read_exact(&mut magic)?;
+        if magic != CAPTURE_MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a capture file",
+            ));
+        }
+        let version = read_array::<1>(r)?[0];
+        if version != CAPTURE_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported capture version {version}"),
+            ));
+        }
+        let start = u64::from_le_bytes(read_array(r)?);
+        let count = u16::from_le_bytes(read_array(r)?);
+        let sensors = (0..count)
+            .map(|_| {
+                Ok(SensorInfo {
+                    idx: u32::from_le_bytes(read_array(r)?),
+                    odr: f32::from_le_bytes(read_array(r)?),
+                    range: f32::from_le_bytes(read_array(r)?),
+                    hpf: read_array::<1>(r)?[0],
+                    scale: f32::from_le_bytes(read_array(r)?),
+                })
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        Ok(Self { start, sensors })
+    }
+}
+
+fn read_array<const N: usize>(r: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut buf = [0u8; N];
+    r.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+/// Writes samples to a capture file
+///
+/// ```
+/// use accel_data::{AccelData, CaptureHeader, CaptureReader, CaptureWriter, SensorInfo};
+///
+/// let header = CaptureHeader {
+///     start: 1_700_000_000_000_000,
+///     sensors: vec![SensorInfo { idx: 0, odr: 1000.0, range: 2.048, hpf: 6, scale: 2.048 / 524_287.0 }],
+/// };
+/// let samples = (0..5000u32)
+///     .map(|i| AccelData { idx: 0, seq: i, gap: 1000, time: 1000 * i as u64, x: i as f32, y: -(i as f32), z: 1.0, temp: None, fresh: None })
+///     .collect::<Vec<_>>();
+///
+/// let mut writer = CaptureWriter::new(Vec::new(), &header)?;
+/// for sample in &samples {
+///     writer.write(sample)?;
+/// }
+/// let file = writer.into_inner();
+///
+/// let reader = CaptureReader::new(file.as_slice())?;
+/// assert_eq!(reader.header(), &header);
+/// let read = reader.collect::<std::io::Result<Vec<_>>>()?;
+/// assert_eq!(read.len(), samples.len());
+/// assert!(read.iter().zip(&samples).all(|(a, b)| a.as_bytes() == b.as_bytes()));
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct CaptureWriter<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> CaptureWriter<W> {
+    /// Create a capture writer, writing the header to `inner`
+    pub fn new(mut inner: W, header: &CaptureHeader) -> io::Result<Self> {
+        header.write_to(&mut inner)?;
+        Ok(Self { inner })
+    }
+
+    /// Append a sample
+    pub fn write(&mut self, data: &AccelData) -> io::Result<()> {
+        self.inner.write_all(&data.as_bytes())
+    }
+
+    /// Flush the underlying writer
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    /// Get back the underlying writer
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+/// Reads samples from a capture file
+pub struct CaptureReader<R: Read> {
+    inner: R,
+    header: CaptureHeader,
+}
+
+impl<R: Read> CaptureReader<R> {
+    /// Create a capture reader, reading and validating the header from `inner`
+    pub fn new(mut inner: R) -> io::Result<Self> {
+        let header = CaptureHeader::read_from(&mut inner)?;
+        Ok(Self { inner, header })
+    }
+
+    /// Capture file header
+    pub fn header(&self) -> &CaptureHeader {
+        &self.header
+    }
+
+    /// Read the next sample, or `None` at the end of the file
+    pub fn read(&mut self) -> io::Result<Option<AccelData>> {
+        let mut buf = [0u8; AccelData::SIZE];
+        match self.inner.read_exact(&mut buf) {
+            Ok(()) => Ok(AccelData::from_bytes(&buf)),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl<R: Read> Iterator for CaptureReader<R> {
+    type Item = io::Result<AccelData>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read().transpose()
+    }
+}
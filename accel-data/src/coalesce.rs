@@ -0,0 +1,114 @@
+//! Time-aligned tuples of the samples of several sensors
+use crate::AccelData;
+use std::{collections::BTreeMap, time::Duration};
+
+/// Groups the samples of a fixed set of sensors sampling at the same rate
+/// into ticks of one sample period, and forwards each tick as one record per
+/// sensor in the order of the sensor list, so clients get time-aligned
+/// tuples instead of interleaved single-sensor records.
+///
+/// A sample belongs to the tick nearest its [`AccelData::time`], counted
+/// from the synchronization point shared by all sensors. The records of a
+/// tick carry its nominal time, a multiple of the period, as `time` and the
+/// period as `gap`, so a tuple can be told apart even when it is split over
+/// two frames. A tick is forwarded
+/// once every sensor has a sample in it, or once a later tick than the next
+/// one has samples: a sensor that dropped its sample is then forwarded as a
+/// record with NaN axes, see [`AccelData::is_missing`].
+///
+/// Sensors at different rates are not aligned: a slower sensor is missing
+/// from the ticks without its samples, and a faster one only forwards its
+/// latest sample of each tick. Samples of sensors not in the list are
+/// dropped, as are samples of ticks already forwarded.
+///
+/// ```
+/// use accel_data::{AccelData, Coalescer};
+/// use std::time::Duration;
+///
+/// let mut coalescer = Coalescer::new([0, 1], Duration::from_millis(1));
+/// let sample = |idx, time, x| AccelData { idx, time, x, ..Default::default() };
+/// // sensor 1 is a bit late, sensor 0 is reordered to come first
+/// assert!(coalescer.push(sample(1, 20, 1.0)).is_empty());
+/// let tuple = coalescer.push(sample(0, 0, 0.0));
+/// let order: Vec<_> = tuple.iter().map(|d| ({ d.idx }, { d.time }, { d.x })).collect();
+/// assert_eq!(order, [(0, 0, 0.0), (1, 0, 1.0)]);
+/// // sensor 1 dropped the sample of the second tick
+/// assert!(coalescer.push(sample(0, 1000, 0.0)).is_empty());
+/// assert!(coalescer.push(sample(0, 2000, 0.0)).is_empty());
+/// let tuple = coalescer.push(sample(0, 3000, 0.0));
+/// assert_eq!(tuple.len(), 2);
+/// assert!(!tuple[0].is_missing() && tuple[1].is_missing());
+/// assert_eq!(({ tuple[1].idx }, { tuple[1].time }), (1, 1000));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Coalescer {
+    /// Sensors of a tuple, in record order
+    sensors: Vec<u32>,
+    /// Tick length in microseconds
+    period: u64,
+    /// Samples of the ticks not forwarded yet, one slot per sensor
+    pending: BTreeMap<u64, Vec<Option<AccelData>>>,
+    /// First tick not forwarded yet
+    next: u64,
+}
+
+impl Coalescer {
+    /// Coalescer of `sensors` sampling every `period`, at least 1 µs
+    pub fn new(sensors: impl IntoIterator<Item = u32>, period: Duration) -> Self {
+        Self {
+            sensors: sensors.into_iter().collect(),
+            period: (period.as_micros() as u64).max(1),
+            pending: BTreeMap::new(),
+            next: 0,
+        }
+    }
+
+    /// Sensors of a tuple, in record order
+    pub fn sensors(&self) -> &[u32] {
+        &self.sensors
+    }
+
+    /// Add a sample, returning the records of the ticks it completed, one
+    /// tuple after the other
+    pub fn push(&mut self, data: AccelData) -> Vec<AccelData> {
+        let Some(slot) = self.sensors.iter().position(|idx| *idx == data.idx) else {
+            return Vec::new();
+        };
+        let tick = (data.time + self.period / 2) / self.period;
+        if tick < self.next {
+            return Vec::new();
+        }
+        let sensors = self.sensors.len();
+        self.pending
+            .entry(tick)
+            .or_insert_with(|| vec![None; sensors])[slot] = Some(data);
+        let mut out = Vec::new();
+        while let Some(entry) = self.pending.first_entry() {
+            let complete = entry.get().iter().all(Option::is_some);
+            if !complete && *entry.key() + 1 >= tick {
+                break;
+            }
+            let (done, slots) = entry.remove_entry();
+            let (time, gap) = (done * self.period, self.period as u32);
+            out.extend(
+                slots
+                    .into_iter()
+                    .zip(&self.sensors)
+                    .map(|(data, &idx)| match data {
+                        Some(data) => AccelData { time, gap, ..data },
+                        None => AccelData {
+                            idx,
+                            gap,
+                            time,
+                            x: f32::NAN,
+                            y: f32::NAN,
+                            z: f32::NAN,
+                            ..Default::default()
+                        },
+                    }),
+            );
+            self.next = done + 1;
+        }
+        out
+    }
+}
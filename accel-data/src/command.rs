@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+/// Sensor reconfiguration command sent by an admin client
+pub enum Command {
+    /// Set the output data rate of sensor `idx`; the closest supported
+    /// rate is selected
+    SetOdr {
+        /// Sensor Index
+        idx: u32,
+        /// Output data rate in Hz
+        hz: f32,
+    },
+    /// Set the measurement range of sensor `idx`
+    SetRange {
+        /// Sensor Index
+        idx: u32,
+        /// Full scale range in g (2, 4 or 8)
+        g: u8,
+    },
+    /// Set the high pass filter corner of sensor `idx`
+    SetHpf {
+        /// Sensor Index
+        idx: u32,
+        /// HPF_CORNER register value (0 disables the filter, 1-6)
+        corner: u8,
+    },
+}
+
+impl Command {
+    /// Index of the sensor the command is addressed to
+    pub fn idx(&self) -> u32 {
+        match self {
+            Command::SetOdr { idx, .. }
+            | Command::SetRange { idx, .. }
+            | Command::SetHpf { idx, .. } => *idx,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+/// Message sent by a client to the server
+///
+/// Messages are JSON encoded, e.g.
+/// `{"type":"admin"}` followed by
+/// `{"type":"command","cmd":"set_odr","idx":0,"hz":1000.0}`, or
+/// `{"type":"subscribe","channel":"ema"}`.
+pub enum ClientMessage {
+    /// Elevate the connection to admin, required before any [`Command`]
+    /// is accepted
+    Admin,
+    /// Sensor reconfiguration command
+    Command(Command),
+    /// Switch the data stream to a named derived channel, or back to the
+    /// unprocessed stream with `"raw"`
+    Subscribe {
+        /// Channel name
+        channel: String,
+    },
+}
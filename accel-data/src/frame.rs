@@ -0,0 +1,746 @@
+//! TCP stream framing
+//!
+//! The TCP stream starts with a stream header, followed by a sequence of
+//! frames. Every UDP datagram starts with the same header, followed by one
+//! frame. The header is [`STREAM_MAGIC`] followed by a [`STREAM_VERSION`]
+//! byte, and [`check_header`] validates it, so clients can reject streams of
+//! a layout they do not understand instead of misparsing them.
+//!
+//! Frames are:
+//!
+//! | Field   | Type     | Description                   |
+//! |---------|----------|-------------------------------|
+//! | kind    | `u8`     | [`FrameKind`]                 |
+//! | len     | `u16`    | Length of the payload         |
+//! | payload | `len`    | Frame contents                |
+//!
+//! A [`FrameKind::Data`] payload starts with an [`AxisMask`] byte followed by
+//! packed records: `idx: u32`, `seq: u32`, `gap: u32`, `time: u64`, then one
+//! `f32` for each axis enabled in the mask, in x, y, z order, and the
+//! temperature in °C if [`AxisMask::TEMP`] is set (NaN if not sampled), and
+//! the freshness as a `u32` if [`AxisMask::FRESH`] is set (1 for a new
+//! sample, 0 for a re-read, `u32::MAX` if not checked). With the x, y and z
+//! axes enabled a record is 32 bytes, the same as [`AccelData::as_bytes`].
+//!
+//! A [`FrameKind::Compact`] payload, sent instead to TCP and Unix socket
+//! clients that ask for it with a [`COMPACT_REQUEST`] line (after the token
+//! line if the server requires one), starts with the same mask byte and the
+//! scale of the batch as an `f32`, followed by records of `idx: u32`,
+//! `seq: u32`, `gap: u32`, `time: u64`, then one `i16` count of the scale for
+//! each enabled axis and the temperature and freshness as in data frames. A
+//! record with x, y and z is 26 bytes; see [`CompactAccelData`] for the
+//! precision given up for it.
+//!
+//! A [`FrameKind::Status`] payload is a [`Status`] record encoded as JSON.
+//! A [`FrameKind::Handshake`] frame, holding a [`Handshake`] encoded as JSON,
+//! is sent first on every connection.
+//!
+//! All fields are little endian. Clients should skip frames of unknown kinds
+//! using `len`.
+//!
+//! [`decode_records`] is the reference decoder of data records, with the
+//! layout spelled out in a [`RecordLayout`]; ports to other languages can be
+//! checked against the golden stream in `tests/data/golden-v1.bin` and the
+//! values `tests/decode.rs` expects from it.
+use crate::{AccelData, CompactAccelData, Handshake, Status};
+use std::{fmt, str::FromStr};
+
+/// Size of the frame header (kind and payload length)
+pub const FRAME_HEADER_LEN: usize = 3;
+
+/// Magic bytes at the start of the TCP stream and of every UDP datagram
+pub const STREAM_MAGIC: [u8; 3] = *b"ACS";
+/// Current version of the frame layout described in [`crate::frame`].
+/// Bumped whenever it changes, e.g. when fields such as `seq`, `time` or the
+/// temperature are added to the data records. Version 2 added
+/// [`AxisMask::FRESH`]; version 1 streams decode the same without it
+pub const STREAM_VERSION: u8 = 2;
+/// Oldest stream version accepted by [`check_header`]
+pub const MIN_STREAM_VERSION: u8 = 1;
+/// Size of the stream header, [`STREAM_MAGIC`] and the version byte
+pub const STREAM_HEADER_LEN: usize = STREAM_MAGIC.len() + 1;
+/// Line a stream client sends to receive [`FrameKind::Compact`] frames
+/// instead of [`FrameKind::Data`] frames
+pub const COMPACT_REQUEST: &str = "compact";
+
+/// Stream header of the current version
+pub fn stream_header() -> [u8; STREAM_HEADER_LEN] {
+    let [a, b, c] = STREAM_MAGIC;
+    [a, b, c, STREAM_VERSION]
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// Reason a stream header was rejected by [`check_header`]
+pub enum HeaderError {
+    /// Fewer than [`STREAM_HEADER_LEN`] bytes
+    Incomplete,
+    /// The stream does not start with [`STREAM_MAGIC`]
+    BadMagic,
+    /// The stream has a version outside [`MIN_STREAM_VERSION`] to
+    /// [`STREAM_VERSION`]
+    Version(u8),
+}
+
+impl fmt::Display for HeaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeaderError::Incomplete => write!(f, "incomplete stream header"),
+            HeaderError::BadMagic => write!(f, "not an accelerometer stream"),
+            HeaderError::Version(version) => write!(
+                f,
+                "unsupported stream version {version}, expected {MIN_STREAM_VERSION} to {STREAM_VERSION}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for HeaderError {}
+
+/// Check the stream header at the start of `bytes`, returning its length,
+/// the offset of the first frame.
+///
+/// ```
+/// use accel_data::frame::{
+///     HeaderError, STREAM_HEADER_LEN, STREAM_VERSION, check_header, stream_header,
+/// };
+///
+/// let mut stream = stream_header().to_vec();
+/// stream.extend_from_slice(&[0, 1, 0, 0b111]);
+/// assert_eq!(check_header(&stream), Ok(STREAM_HEADER_LEN));
+///
+/// // older versions are accepted, newer ones rejected
+/// stream[STREAM_HEADER_LEN - 1] = 1;
+/// assert_eq!(check_header(&stream), Ok(STREAM_HEADER_LEN));
+/// stream[STREAM_HEADER_LEN - 1] = STREAM_VERSION + 1;
+/// assert_eq!(check_header(&stream), Err(HeaderError::Version(STREAM_VERSION + 1)));
+/// // as is one without a header, such as that of an older server
+/// assert_eq!(check_header(&[0, 1, 0, 0b111]), Err(HeaderError::BadMagic));
+/// assert_eq!(check_header(b"AC"), Err(HeaderError::Incomplete));
+/// ```
+pub fn check_header(bytes: &[u8]) -> Result<usize, HeaderError> {
+    if !STREAM_MAGIC.starts_with(&bytes[..bytes.len().min(STREAM_MAGIC.len())]) {
+        return Err(HeaderError::BadMagic);
+    }
+    match bytes.get(STREAM_MAGIC.len()) {
+        None => Err(HeaderError::Incomplete),
+        Some(MIN_STREAM_VERSION..=STREAM_VERSION) => Ok(STREAM_HEADER_LEN),
+        Some(&version) => Err(HeaderError::Version(version)),
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u8)]
+/// Type of a frame
+pub enum FrameKind {
+    /// Batch of samples
+    Data = 0,
+    /// Status record
+    Status = 1,
+    /// Connection handshake
+    Handshake = 2,
+    /// Batch of samples with 16-bit axes
+    Compact = 3,
+}
+
+impl FrameKind {
+    /// Decode a frame kind, `None` if unknown
+    pub fn from_u8(kind: u8) -> Option<FrameKind> {
+        match kind {
+            0 => Some(FrameKind::Data),
+            1 => Some(FrameKind::Status),
+            2 => Some(FrameKind::Handshake),
+            3 => Some(FrameKind::Compact),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// Set of axes forwarded to clients, optionally with the temperature and
+/// freshness
+pub struct AxisMask(u8);
+
+impl AxisMask {
+    /// X-axis
+    pub const X: AxisMask = AxisMask(0b001);
+    /// Y-axis
+    pub const Y: AxisMask = AxisMask(0b010);
+    /// Z-axis
+    pub const Z: AxisMask = AxisMask(0b100);
+    /// All axes
+    pub const ALL: AxisMask = AxisMask(0b111);
+    /// Die temperature, see [`AccelData::temp`]. Not part of [`AxisMask::ALL`]
+    pub const TEMP: AxisMask = AxisMask(0b1000);
+    /// Freshness of the sample, see [`AccelData::fresh`]. Not part of
+    /// [`AxisMask::ALL`]
+    pub const FRESH: AxisMask = AxisMask(0b1_0000);
+
+    /// Create a mask from its bit representation (bit 0: x, 1: y, 2: z,
+    /// 3: temperature, 4: freshness)
+    pub fn from_bits(bits: u8) -> AxisMask {
+        AxisMask(bits & (Self::ALL.0 | Self::TEMP.0 | Self::FRESH.0))
+    }
+
+    /// Bit representation of the mask
+    pub fn bits(self) -> u8 {
+        self.0
+    }
+
+    /// Check if all axes in `other` are enabled
+    pub fn contains(self, other: AxisMask) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Number of enabled fields, axes, temperature and freshness
+    pub fn count(self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    /// Size of a data record with this mask
+    pub fn record_len(self) -> usize {
+        20 + 4 * self.count()
+    }
+
+    /// Size of a [`FrameKind::Compact`] record with this mask
+    pub fn compact_record_len(self) -> usize {
+        let axes = AxisMask(self.0 & Self::ALL.0).count();
+        self.record_len() - 2 * axes
+    }
+
+    /// Zero the disabled axes of a sample, and clear its temperature and
+    /// freshness unless enabled
+    pub fn apply(self, data: AccelData) -> AccelData {
+        let pick = |axis, value: f32| if self.contains(axis) { value } else { 0.0 };
+        AccelData {
+            x: pick(Self::X, data.x),
+            y: pick(Self::Y, data.y),
+            z: pick(Self::Z, data.z),
+            temp: data.temp.filter(|_| self.contains(Self::TEMP)),
+            fresh: data.fresh.filter(|_| self.contains(Self::FRESH)),
+            ..data
+        }
+    }
+}
+
+impl Default for AxisMask {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+impl std::ops::BitOr for AxisMask {
+    type Output = AxisMask;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        AxisMask(self.0 | rhs.0)
+    }
+}
+
+impl FromStr for AxisMask {
+    type Err = String;
+
+    /// Parse a set of axes such as `xyz` or `xz`, with `t` adding the
+    /// temperature and `f` the freshness
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut mask = AxisMask(0);
+        for c in s.chars() {
+            mask = mask
+                | match c.to_ascii_lowercase() {
+                    'x' => Self::X,
+                    'y' => Self::Y,
+                    'z' => Self::Z,
+                    't' => Self::TEMP,
+                    'f' => Self::FRESH,
+                    _ => return Err(format!("invalid axis '{c}'")),
+                };
+        }
+        if mask.0 & Self::ALL.0 == 0 {
+            return Err("no axis selected".into());
+        }
+        Ok(mask)
+    }
+}
+
+impl fmt::Display for AxisMask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (axis, name) in [
+            (Self::X, "x"),
+            (Self::Y, "y"),
+            (Self::Z, "z"),
+            (Self::TEMP, "t"),
+            (Self::FRESH, "f"),
+        ] {
+            if self.contains(axis) {
+                f.write_str(name)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Batch of samples encoded as a [`FrameKind::Data`] frame, or as a
+/// [`FrameKind::Compact`] frame once [`DataFrame::set_compact`] is set
+#[derive(Debug, Clone)]
+pub struct DataFrame {
+    buf: Vec<u8>,
+    axes: AxisMask,
+    capacity: usize,
+    /// Samples of a compact frame, encoded by [`DataFrame::as_bytes`] once
+    /// the scale of the batch is known
+    compact: Option<Vec<AccelData>>,
+}
+
+impl DataFrame {
+    /// Create an empty data frame of at most `capacity` bytes (including the
+    /// header), holding the axes in `axes`
+    pub fn new(axes: AxisMask, capacity: usize) -> Self {
+        let mut frame = Self {
+            buf: Vec::new(),
+            axes,
+            capacity: 0,
+            compact: None,
+        };
+        frame.set_capacity(capacity);
+        frame.clear();
+        frame
+    }
+
+    /// Maximum size of the frame in bytes, including the header
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Change the maximum size of the frame. Samples already in the frame
+    /// are kept even if they exceed the new size.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity.clamp(
+            FRAME_HEADER_LEN + 1 + self.axes.record_len(),
+            FRAME_HEADER_LEN + u16::MAX as usize,
+        );
+        self.buf
+            .reserve(self.capacity.saturating_sub(self.buf.len()));
+    }
+
+    /// Check if the frame is encoded as a [`FrameKind::Compact`] frame
+    pub fn is_compact(&self) -> bool {
+        self.compact.is_some()
+    }
+
+    /// Encode the frame as a [`FrameKind::Compact`] frame, or as a
+    /// [`FrameKind::Data`] frame. Samples already in the frame are kept
+    pub fn set_compact(&mut self, compact: bool) {
+        if compact == self.is_compact() {
+            return;
+        }
+        let samples = match self.compact.take() {
+            Some(samples) => samples,
+            None => Frame {
+                kind: FrameKind::Data as u8,
+                payload: &self.buf[FRAME_HEADER_LEN..],
+            }
+            .samples()
+            .unwrap_or_default(),
+        };
+        self.compact = compact.then(Vec::new);
+        self.clear();
+        for data in &samples {
+            self.push(data);
+        }
+    }
+
+    /// Size of the encoded frame in bytes
+    fn encoded_len(&self) -> usize {
+        match &self.compact {
+            Some(samples) => FRAME_HEADER_LEN + 5 + samples.len() * self.axes.compact_record_len(),
+            None => self.buf.len(),
+        }
+    }
+
+    /// Check if another sample fits in the frame
+    pub fn has_room(&self) -> bool {
+        let record_len = match self.compact {
+            Some(_) => self.axes.compact_record_len(),
+            None => self.axes.record_len(),
+        };
+        self.encoded_len() + record_len <= self.capacity
+    }
+
+    /// Append a sample
+    pub fn push(&mut self, data: &AccelData) {
+        if let Some(samples) = &mut self.compact {
+            samples.push(*data);
+            return;
+        }
+        self.buf.extend_from_slice(&{ data.idx }.to_le_bytes());
+        self.buf.extend_from_slice(&{ data.seq }.to_le_bytes());
+        self.buf.extend_from_slice(&{ data.gap }.to_le_bytes());
+        self.buf.extend_from_slice(&{ data.time }.to_le_bytes());
+        for (axis, value) in [
+            (AxisMask::X, data.x),
+            (AxisMask::Y, data.y),
+            (AxisMask::Z, data.z),
+        ] {
+            if self.axes.contains(axis) {
+                self.buf.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        if self.axes.contains(AxisMask::TEMP) {
+            let temp = data.temp.unwrap_or(f32::NAN);
+            self.buf.extend_from_slice(&temp.to_le_bytes());
+        }
+        if self.axes.contains(AxisMask::FRESH) {
+            let fresh = data.fresh.map_or(u32::MAX, u32::from);
+            self.buf.extend_from_slice(&fresh.to_le_bytes());
+        }
+    }
+
+    /// Number of samples in the frame
+    pub fn len(&self) -> usize {
+        match &self.compact {
+            Some(samples) => samples.len(),
+            None => (self.buf.len() - FRAME_HEADER_LEN - 1) / self.axes.record_len(),
+        }
+    }
+
+    /// Check if the frame holds no samples
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Encoded frame, including the header
+    pub fn as_bytes(&mut self) -> &[u8] {
+        if let Some(samples) = &self.compact {
+            self.buf.truncate(FRAME_HEADER_LEN + 1);
+            let scale = CompactAccelData::scale_for(samples);
+            self.buf.extend_from_slice(&scale.to_le_bytes());
+            for data in samples {
+                let data = CompactAccelData::from_accel(data, scale);
+                self.buf.extend_from_slice(&data.idx.to_le_bytes());
+                self.buf.extend_from_slice(&data.seq.to_le_bytes());
+                self.buf.extend_from_slice(&data.gap.to_le_bytes());
+                self.buf.extend_from_slice(&data.time.to_le_bytes());
+                for (axis, count) in [
+                    (AxisMask::X, data.x),
+                    (AxisMask::Y, data.y),
+                    (AxisMask::Z, data.z),
+                ] {
+                    if self.axes.contains(axis) {
+                        self.buf.extend_from_slice(&count.to_le_bytes());
+                    }
+                }
+                if self.axes.contains(AxisMask::TEMP) {
+                    let temp = data.temp.unwrap_or(f32::NAN);
+                    self.buf.extend_from_slice(&temp.to_le_bytes());
+                }
+                if self.axes.contains(AxisMask::FRESH) {
+                    let fresh = data.fresh.map_or(u32::MAX, u32::from);
+                    self.buf.extend_from_slice(&fresh.to_le_bytes());
+                }
+            }
+        }
+        let len = (self.buf.len() - FRAME_HEADER_LEN) as u16;
+        self.buf[1..FRAME_HEADER_LEN].copy_from_slice(&len.to_le_bytes());
+        &self.buf
+    }
+
+    /// Remove all samples
+    pub fn clear(&mut self) {
+        let kind = match &mut self.compact {
+            Some(samples) => {
+                samples.clear();
+                FrameKind::Compact
+            }
+            None => FrameKind::Data,
+        };
+        self.buf.clear();
+        self.buf
+            .extend_from_slice(&[kind as u8, 0, 0, self.axes.bits()]);
+    }
+}
+
+/// Encode a JSON payload as a frame of the given kind, `None` if it does not
+/// fit the 16-bit length of the frame header
+fn json_frame(kind: FrameKind, payload: &str) -> Option<Vec<u8>> {
+    let len = u16::try_from(payload.len()).ok()?;
+    let mut buf = Vec::with_capacity(FRAME_HEADER_LEN + payload.len());
+    buf.push(kind as u8);
+    buf.extend_from_slice(&len.to_le_bytes());
+    buf.extend_from_slice(payload.as_bytes());
+    Some(buf)
+}
+
+/// Encode a status record as a [`FrameKind::Status`] frame, `None` if its
+/// JSON is longer than a frame can hold
+///
+/// ```
+/// use accel_data::{Frame, Status, status_frame};
+///
+/// let status = Status::Health { idx: 0, temperature: None };
+/// let frame = status_frame(&status).unwrap();
+/// assert_eq!(Frame::parse(&frame).unwrap().0.status(), Some(status));
+/// ```
+pub fn status_frame(status: &Status) -> Option<Vec<u8>> {
+    json_frame(FrameKind::Status, &status.to_json())
+}
+
+/// Encode a handshake as a [`FrameKind::Handshake`] frame, `None` if its
+/// JSON is longer than a frame can hold
+///
+/// ```
+/// use accel_data::{Handshake, handshake_frame};
+///
+/// let mut handshake = Handshake::new(Vec::new());
+/// assert!(handshake_frame(&handshake).is_some());
+/// handshake.names.insert(0, "x".repeat(u16::MAX as usize));
+/// assert!(handshake_frame(&handshake).is_none());
+/// ```
+pub fn handshake_frame(handshake: &Handshake) -> Option<Vec<u8>> {
+    json_frame(FrameKind::Handshake, &handshake.to_json())
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// Frame read from a byte stream
+pub struct Frame<'a> {
+    /// Raw frame kind, see [`FrameKind`]
+    pub kind: u8,
+    /// Frame payload
+    pub payload: &'a [u8],
+}
+
+impl<'a> Frame<'a> {
+    /// Parse the frame at the start of `bytes`.
+    ///
+    /// Returns the frame and the number of bytes it spans, or `None` if
+    /// `bytes` does not hold a complete frame yet.
+    pub fn parse(bytes: &'a [u8]) -> Option<(Frame<'a>, usize)> {
+        let header = bytes.get(..FRAME_HEADER_LEN)?;
+        let len = u16::from_le_bytes([header[1], header[2]]) as usize;
+        let payload = bytes.get(FRAME_HEADER_LEN..FRAME_HEADER_LEN + len)?;
+        Some((
+            Frame {
+                kind: header[0],
+                payload,
+            },
+            FRAME_HEADER_LEN + len,
+        ))
+    }
+
+    /// Decode the samples of a data or compact frame. Disabled axes are set
+    /// to zero, the temperature to `None` unless enabled.
+    ///
+    /// Returns `None` if this is not a well-formed data or compact frame.
+    pub fn samples(&self) -> Option<Vec<AccelData>> {
+        let (&mask, records) = self.payload.split_first()?;
+        let axes = AxisMask::from_bits(mask);
+        match FrameKind::from_u8(self.kind)? {
+            FrameKind::Data => {
+                let layout = RecordLayout::for_version(STREAM_VERSION, axes)?;
+                decode_records(&layout, records)
+            }
+            FrameKind::Compact => {
+                let (scale, records) = records.split_first_chunk::<4>()?;
+                let scale = f32::from_le_bytes(*scale);
+                let samples = decode_compact_records(axes, records)?;
+                Some(samples.iter().map(|data| data.to_accel(scale)).collect())
+            }
+            _ => None,
+        }
+    }
+
+    /// Decode the record of a status frame
+    ///
+    /// Returns `None` if this is not a well-formed status frame.
+    pub fn status(&self) -> Option<Status> {
+        if FrameKind::from_u8(self.kind) != Some(FrameKind::Status) {
+            return None;
+        }
+        serde_json::from_slice(self.payload).ok()
+    }
+
+    /// Decode the record of a handshake frame
+    ///
+    /// Returns `None` if this is not a well-formed handshake frame.
+    pub fn handshake(&self) -> Option<Handshake> {
+        if FrameKind::from_u8(self.kind) != Some(FrameKind::Handshake) {
+            return None;
+        }
+        serde_json::from_slice(self.payload).ok()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// Byte order of the fields of a record
+pub enum ByteOrder {
+    /// Least significant byte first, as sent by this crate
+    Little,
+    /// Most significant byte first
+    Big,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// Layout of the data records of a [`FrameKind::Data`] payload, after the
+/// mask byte.
+///
+/// A record is `idx: u32`, then `seq: u32` if `seq` is set, `gap: u32`,
+/// `time: u64` if `time` is set, one `f32` per axis enabled in `axes` in x, y,
+/// z order, the temperature as an `f32` if [`AxisMask::TEMP`] is in `axes`
+/// (NaN if not sampled), and the freshness as a `u32` if [`AxisMask::FRESH`]
+/// is (1 if new, 0 if a re-read, anything else if not checked). Every field
+/// is in `byte_order`.
+pub struct RecordLayout {
+    /// Byte order of the fields
+    pub byte_order: ByteOrder,
+    /// Records carry [`AccelData::seq`]
+    pub seq: bool,
+    /// Records carry [`AccelData::time`]
+    pub time: bool,
+    /// Axes, temperature and freshness present in the records
+    pub axes: AxisMask,
+}
+
+impl RecordLayout {
+    /// Layout of the records of stream `version`, see [`STREAM_VERSION`],
+    /// with the `axes` of the mask byte. `None` for unknown versions
+    pub fn for_version(version: u8, axes: AxisMask) -> Option<RecordLayout> {
+        let axes = match version {
+            // the freshness bit did not exist yet
+            1 => AxisMask(axes.0 & !AxisMask::FRESH.0),
+            2 => axes,
+            _ => return None,
+        };
+        Some(RecordLayout {
+            byte_order: ByteOrder::Little,
+            seq: true,
+            time: true,
+            axes,
+        })
+    }
+
+    /// Size of a record in bytes
+    pub fn record_len(&self) -> usize {
+        8 + 4 * usize::from(self.seq) + 8 * usize::from(self.time) + 4 * self.axes.count()
+    }
+}
+
+/// Decode the packed records of a data frame payload, after its mask byte.
+/// Fields absent from the `layout` are zero, as are disabled axes, and the
+/// temperature and freshness are `None` unless present and sampled.
+///
+/// Returns `None` if `bytes` is not a whole number of records.
+pub fn decode_records(layout: &RecordLayout, bytes: &[u8]) -> Option<Vec<AccelData>> {
+    let len = layout.record_len();
+    if !bytes.len().is_multiple_of(len) {
+        return None;
+    }
+    let samples = bytes
+        .chunks_exact(len)
+        .map(|mut rec| {
+            let mut take = |n: usize| {
+                let (field, rest) = rec.split_at(n);
+                rec = rest;
+                let mut buf = [0u8; 8];
+                buf[..n].copy_from_slice(field);
+                if layout.byte_order == ByteOrder::Big {
+                    buf[..n].reverse();
+                }
+                u64::from_le_bytes(buf)
+            };
+            let idx = take(4) as u32;
+            let seq = if layout.seq { take(4) as u32 } else { 0 };
+            let gap = take(4) as u32;
+            let time = if layout.time { take(8) } else { 0 };
+            let mut value = |axis| {
+                if layout.axes.contains(axis) {
+                    f32::from_bits(take(4) as u32)
+                } else {
+                    0.0
+                }
+            };
+            let (x, y, z) = (value(AxisMask::X), value(AxisMask::Y), value(AxisMask::Z));
+            let temp = Some(value(AxisMask::TEMP))
+                .filter(|temp| layout.axes.contains(AxisMask::TEMP) && !temp.is_nan());
+            let fresh = if layout.axes.contains(AxisMask::FRESH) {
+                match take(4) {
+                    0 => Some(false),
+                    1 => Some(true),
+                    _ => None,
+                }
+            } else {
+                None
+            };
+            AccelData {
+                idx,
+                seq,
+                gap,
+                time,
+                x,
+                y,
+                z,
+                temp,
+                fresh,
+            }
+        })
+        .collect();
+    Some(samples)
+}
+
+/// Decode the records of a [`FrameKind::Compact`] payload, after its mask
+/// byte and scale, in the counts of the scale.
+///
+/// Returns `None` if `bytes` is not a whole number of records.
+pub fn decode_compact_records(axes: AxisMask, bytes: &[u8]) -> Option<Vec<CompactAccelData>> {
+    let len = axes.compact_record_len();
+    if !bytes.len().is_multiple_of(len) {
+        return None;
+    }
+    let samples = bytes
+        .chunks_exact(len)
+        .map(|mut rec| {
+            let mut take = |n: usize| {
+                let (field, rest) = rec.split_at(n);
+                rec = rest;
+                let mut buf = [0u8; 8];
+                buf[..n].copy_from_slice(field);
+                u64::from_le_bytes(buf)
+            };
+            let idx = take(4) as u32;
+            let seq = take(4) as u32;
+            let gap = take(4) as u32;
+            let time = take(8);
+            let mut count = |axis| {
+                if axes.contains(axis) {
+                    take(2) as u16 as i16
+                } else {
+                    0
+                }
+            };
+            let (x, y, z) = (count(AxisMask::X), count(AxisMask::Y), count(AxisMask::Z));
+            let temp = if axes.contains(AxisMask::TEMP) {
+                Some(f32::from_bits(take(4) as u32)).filter(|temp| !temp.is_nan())
+            } else {
+                None
+            };
+            let fresh = if axes.contains(AxisMask::FRESH) {
+                match take(4) {
+                    0 => Some(false),
+                    1 => Some(true),
+                    _ => None,
+                }
+            } else {
+                None
+            };
+            CompactAccelData {
+                idx,
+                seq,
+                gap,
+                time,
+                x,
+                y,
+                z,
+                temp,
+                fresh,
+            }
+        })
+        .collect();
+    Some(samples)
+}
@@ -0,0 +1,105 @@
+use crate::SensorInfo;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+/// Current version of the [`Handshake`] record
+pub const HANDSHAKE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// Record sent once to every client on connection, before any data, so
+/// samples can be labelled and scaled
+///
+/// Encoded as JSON, e.g.
+/// `{"handshake":1,"units":"g","sensors":[{"idx":0,"odr":1000.0,"range":2.048,"hpf":6,"scale":3.9e-6}],"names":{"0":"motor mount"}}`.
+/// Clients should ignore fields they do not know; incompatible changes
+/// increment the version.
+pub struct Handshake {
+    /// Handshake version, see [`HANDSHAKE_VERSION`]
+    #[serde(rename = "handshake")]
+    pub version: u32,
+    /// Units of the sample values
+    #[serde(default)]
+    pub units: Units,
+    /// Configuration of each sensor
+    pub sensors: Vec<SensorInfo>,
+    /// Human readable names of the sensors by index, for labels. Sensors
+    /// without a name are not listed
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub names: BTreeMap<u32, String>,
+}
+
+impl Handshake {
+    /// Create a handshake of the current version
+    pub fn new(sensors: Vec<SensorInfo>) -> Self {
+        Self {
+            version: HANDSHAKE_VERSION,
+            units: Units::default(),
+            sensors,
+            names: BTreeMap::new(),
+        }
+    }
+
+    /// Name of sensor `idx`, if it has one
+    ///
+    /// ```
+    /// use accel_data::Handshake;
+    ///
+    /// let mut handshake = Handshake::new(Vec::new());
+    /// handshake.names.insert(1, "motor mount".into());
+    /// let json = handshake.to_json();
+    /// assert!(json.contains(r#""names":{"1":"motor mount"}"#));
+    /// let decoded: Handshake = serde_json::from_str(&json).unwrap();
+    /// assert_eq!(decoded.name(1), Some("motor mount"));
+    /// assert_eq!(decoded.name(0), None);
+    /// // older servers send no names
+    /// assert!(!Handshake::new(Vec::new()).to_json().contains("names"));
+    /// ```
+    pub fn name(&self, idx: u32) -> Option<&str> {
+        self.names.get(&idx).map(String::as_str)
+    }
+
+    /// Encode as JSON, the payload of [`crate::FrameKind::Handshake`] frames
+    /// and the body of the first WebSocket message
+    pub fn to_json(&self) -> String {
+        // cannot fail: all fields are plain numbers and strings
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+/// Units of the `x`, `y` and `z` values of the samples
+pub enum Units {
+    /// Acceleration in g
+    #[default]
+    G,
+    /// Acceleration in m/s², using standard gravity
+    Mps2,
+    /// Raw 20-bit counts as read from the sensor, multiply by
+    /// [`SensorInfo::scale`] for g. Integer valued, so exact in an `f32`
+    Counts,
+}
+
+impl FromStr for Units {
+    type Err = String;
+
+    /// Parse `g`, `mps2` or `counts`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "g" => Ok(Units::G),
+            "mps2" => Ok(Units::Mps2),
+            "counts" => Ok(Units::Counts),
+            _ => Err(format!("invalid units '{s}', expected g, mps2 or counts")),
+        }
+    }
+}
+
+impl fmt::Display for Units {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Units::G => "g",
+            Units::Mps2 => "mps2",
+            Units::Counts => "counts",
+        })
+    }
+}
@@ -0,0 +1,98 @@
+//! Recent samples kept for clients that connect late
+use crate::AccelData;
+#[cfg(feature = "async")]
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{Arc, Mutex, MutexGuard},
+};
+#[cfg(feature = "async")]
+use tokio::sync::broadcast::{Sender, error::RecvError};
+
+/// Largest number of samples kept per sensor, about 2.6 MB of memory per
+/// sensor
+pub const MAX_HISTORY_DEPTH: usize = 65_536;
+
+/// Ring buffer of the most recent samples of every sensor, shared between
+/// the recording task and the servers replaying it (see
+/// [`crate::ServerConfig::history`]).
+///
+/// Samples are kept as recorded, with their original `seq`, `gap` and
+/// `time`.
+///
+/// ```
+/// use accel_data::{AccelData, History};
+///
+/// let history = History::new(3);
+/// for i in 0..10u32 {
+///     history.push(AccelData { idx: i % 2, seq: i / 2, time: u64::from(i), ..Default::default() });
+/// }
+/// // the last 2 samples of each sensor, oldest first
+/// let seqs: Vec<_> = history.recent(2).iter().map(|data| (data.idx, data.seq)).collect();
+/// assert_eq!(seqs, [(0, 3), (1, 3), (0, 4), (1, 4)]);
+/// assert_eq!(history.recent(100).len(), 6);
+/// ```
+#[derive(Debug, Clone)]
+pub struct History {
+    depth: usize,
+    sensors: Arc<Mutex<BTreeMap<u32, VecDeque<AccelData>>>>,
+}
+
+impl History {
+    /// Create a history of the last `depth` samples per sensor, clamped to
+    /// 1 ..= [`MAX_HISTORY_DEPTH`]
+    pub fn new(depth: usize) -> Self {
+        Self {
+            depth: depth.clamp(1, MAX_HISTORY_DEPTH),
+            sensors: Arc::default(),
+        }
+    }
+
+    /// Samples kept per sensor
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Add a sample, dropping the oldest one of its sensor when full
+    pub fn push(&self, data: AccelData) {
+        let mut sensors = self.lock();
+        let samples = sensors.entry(data.idx).or_default();
+        if samples.len() == self.depth {
+            samples.pop_front();
+        }
+        samples.push_back(data);
+    }
+
+    /// Up to `count` most recent samples of every sensor, ordered by time
+    pub fn recent(&self, count: usize) -> Vec<AccelData> {
+        let sensors = self.lock();
+        let mut out: Vec<_> = sensors
+            .values()
+            .flat_map(|samples| samples.iter().skip(samples.len().saturating_sub(count)))
+            .copied()
+            .collect();
+        out.sort_by_key(|data| data.time);
+        out
+    }
+
+    /// Record the samples sent on `sink` until `running` is cleared or all
+    /// senders are dropped
+    #[cfg(feature = "async")]
+    pub async fn record(self, sink: Sender<AccelData>, running: Arc<AtomicBool>) {
+        let mut source = sink.subscribe();
+        drop(sink);
+        while running.load(Ordering::Relaxed) {
+            match source.recv().await {
+                Ok(data) => self.push(data),
+                Err(RecvError::Lagged(n)) => {
+                    log::warn!("[HISTORY] Lagged behind, skipped {n} samples");
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<u32, VecDeque<AccelData>>> {
+        self.sensors.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
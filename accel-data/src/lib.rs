@@ -1,46 +1,303 @@
 use micromath::vector::F32x3;
+mod backoff;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+mod capture;
+mod coalesce;
+mod command;
+pub mod frame;
+mod handshake;
+mod history;
+#[cfg(feature = "async")]
 mod net;
+#[cfg(feature = "async")]
+mod source;
+mod spectrum;
+mod stats;
+mod status;
+#[cfg(feature = "async")]
+mod stream;
+mod summary;
+mod tilt;
+mod transform;
 
-pub use net::{tcp_server, udp_server};
+pub use backoff::Backoff;
+pub use capture::{CAPTURE_MAGIC, CAPTURE_VERSION, CaptureHeader, CaptureReader, CaptureWriter};
+pub use coalesce::Coalescer;
+pub use command::{ClientMessage, Command};
+pub use frame::{
+    AxisMask, COMPACT_REQUEST, DataFrame, Frame, FrameKind, HeaderError, MIN_STREAM_VERSION,
+    STREAM_MAGIC, STREAM_VERSION, check_header, handshake_frame, status_frame, stream_header,
+};
+pub use handshake::{HANDSHAKE_VERSION, Handshake, Units};
+pub use history::{History, MAX_HISTORY_DEPTH};
+#[cfg(all(feature = "async", unix))]
+pub use net::uds_server;
+#[cfg(feature = "async")]
+pub use net::{
+    AUTH_TIMEOUT, CommandRequest, RAW_CHANNEL, ServerConfig, UDP_HELLO, UDP_MAX_PEERS,
+    UDP_PEER_TIMEOUT, tcp_server, udp_server, wsock_server,
+};
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "async")]
+pub use source::{SampleSource, SeededSource, SyntheticSource};
+pub use spectrum::Spectrum;
+#[cfg(feature = "fft")]
+pub use spectrum::{MAX_FFT_SIZE, SpectrumAnalyzer};
+pub use stats::{Histogram, LinkStats, Percentiles, RateMeter, Rates, StreamStats};
+pub use status::{ErrorKind, Status};
+#[cfg(feature = "async")]
+pub use stream::{StreamError, sample_stream};
+pub use summary::{AxisSummary, Summarizer, Summary};
+pub use tilt::{OrientationChange, TiltDetector};
+pub use transform::{
+    Deadband, DecimationError, DecimationPlan, Decimator, Ema, MAX_DECIMATION_FACTOR, Magnitude,
+};
+#[cfg(feature = "async")]
+pub use transform::{deadband_sink, relay};
 
-#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+/// Column names of [`AccelData::to_csv_row`], in order
+pub const CSV_HEADER: &str = "idx,seq,gap,time,x,y,z,temp,fresh";
+
+#[derive(Debug, Copy, Clone, Default, Serialize, Deserialize)]
 #[repr(C, packed)]
 /// Accelerometer data structure
 pub struct AccelData {
     /// Sensor Index
     pub idx: u32,
+    /// Per-sensor sample number, wrapping from `u32::MAX` to 0 (after about
+    /// 12 days at 4 kHz). Compare sequence numbers modulo 2^32, e.g.
+    /// `seq.wrapping_sub(prev)`; a jump means samples were dropped, see
+    /// [`StreamStats`]
+    #[serde(default)]
+    pub seq: u32,
     /// Time in microseconds since the last data point
     /// (0 if this is the first data point)
     pub gap: u32,
+    /// Time of the sample in microseconds since the synchronization point
+    /// shared by all sensors of the source, so samples of different sensors
+    /// can be compared directly
+    #[serde(default)]
+    pub time: u64,
     /// X-axis acceleration
     pub x: f32,
     /// Y-axis acceleration
     pub y: f32,
     /// Z-axis acceleration
     pub z: f32,
+    /// Most recent die temperature in °C, if sampled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temp: Option<f32>,
+    /// Whether STATUS reported a new sample when this one was read:
+    /// `Some(false)` marks a re-read of the previous sample, which analysis
+    /// can discard. `None` unless checked
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fresh: Option<bool>,
 }
 
 impl From<(u32, u32, F32x3)> for AccelData {
     fn from(val: (u32, u32, F32x3)) -> Self {
         AccelData {
             idx: val.0,
+            seq: 0,
             gap: val.1,
+            time: 0,
             x: val.2.x,
             y: val.2.y,
             z: val.2.z,
+            temp: None,
+            fresh: None,
         }
     }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+/// Per-sensor configuration, needed to interpret its samples
+pub struct SensorInfo {
+    /// Sensor Index
+    pub idx: u32,
+    /// Output data rate in Hz
+    pub odr: f32,
+    /// Full scale range in g
+    pub range: f32,
+    /// High pass filter corner register value (0 if disabled)
+    pub hpf: u8,
+    /// Acceleration per raw count in g
+    pub scale: f32,
+}
+
 impl AccelData {
+    /// Size of a serialized record in bytes
+    pub const SIZE: usize = 32;
+
+    /// Decode a record serialized by [`AccelData::as_bytes`].
+    ///
+    /// Returns `None` if `bytes` is shorter than [`AccelData::SIZE`].
+    pub fn from_bytes(bytes: &[u8]) -> Option<AccelData> {
+        let bytes = bytes.get(..Self::SIZE)?;
+        let word = |i: usize| [bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]];
+        let (time, _) = bytes[12..].split_first_chunk::<8>()?;
+        Some(AccelData {
+            idx: u32::from_le_bytes(word(0)),
+            seq: u32::from_le_bytes(word(4)),
+            gap: u32::from_le_bytes(word(8)),
+            time: u64::from_le_bytes(*time),
+            x: f32::from_le_bytes(word(20)),
+            y: f32::from_le_bytes(word(24)),
+            z: f32::from_le_bytes(word(28)),
+            temp: None,
+            fresh: None,
+        })
+    }
+
+    /// Whether this marks a sample a sensor dropped, see [`Coalescer`]:
+    /// all axes are NaN
+    pub fn is_missing(&self) -> bool {
+        let (x, y, z) = (self.x, self.y, self.z);
+        x.is_nan() && y.is_nan() && z.is_nan()
+    }
+
+    /// Format as a CSV row of the [`CSV_HEADER`] columns, without a line
+    /// break. Axes and temperature are written in the shortest form that
+    /// reads back to the same `f32`; a temperature or freshness that was not
+    /// sampled is left empty.
+    ///
+    /// ```
+    /// use accel_data::{AccelData, CSV_HEADER};
+    ///
+    /// let data = AccelData { idx: 1, seq: 7, gap: 1000, time: 7000, x: 0.5, y: -0.25, z: 1.0, temp: Some(25.5), fresh: Some(true) };
+    /// assert_eq!(data.to_csv_row(), "1,7,1000,7000,0.5,-0.25,1,25.5,true");
+    /// let unsampled = AccelData { temp: None, fresh: None, ..data };
+    /// assert_eq!(unsampled.to_csv_row(), "1,7,1000,7000,0.5,-0.25,1,,");
+    /// // every row has a field per column
+    /// let columns = CSV_HEADER.split(',').count();
+    /// assert_eq!(columns, 9);
+    /// for row in [data.to_csv_row(), unsampled.to_csv_row()] {
+    ///     assert_eq!(row.split(',').count(), columns);
+    /// }
+    /// ```
+    pub fn to_csv_row(&self) -> String {
+        let (idx, seq, gap, time, x, y, z) = (
+            self.idx, self.seq, self.gap, self.time, self.x, self.y, self.z,
+        );
+        let temp = self.temp.map(|temp| temp.to_string()).unwrap_or_default();
+        let fresh = self
+            .fresh
+            .map(|fresh| fresh.to_string())
+            .unwrap_or_default();
+        format!("{idx},{seq},{gap},{time},{x},{y},{z},{temp},{fresh}")
+    }
+
+    /// Serialize `idx`, `seq`, `gap`, `time`, `x`, `y` and `z`, little endian. The
+    /// temperature and freshness are not included.
     pub fn as_bytes(self) -> Vec<u8> {
-        let mut buf = Vec::with_capacity(std::mem::size_of::<AccelData>());
+        let mut buf = Vec::with_capacity(Self::SIZE);
         buf.extend_from_slice(&self.idx.to_le_bytes());
+        buf.extend_from_slice(&self.seq.to_le_bytes());
         buf.extend_from_slice(&self.gap.to_le_bytes());
+        buf.extend_from_slice(&self.time.to_le_bytes());
         buf.extend_from_slice(&self.x.to_le_bytes());
         buf.extend_from_slice(&self.y.to_le_bytes());
         buf.extend_from_slice(&self.z.to_le_bytes());
         buf
     }
 }
+
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+/// [`AccelData`] with the axes as 16-bit counts of a scale shared by a batch
+/// of samples, the records of a [`FrameKind::Compact`] frame.
+///
+/// With the scale set by the largest axis value of the batch, the counts
+/// keep 15 bits relative to that peak: about 31 µg per count with gravity
+/// on one axis, against the 3.9 µg of a 20-bit count at ±2 g and the
+/// 24 significant bits an `f32` keeps of every value. That is still below
+/// the noise of the sensor at most output rates, but a shock in the batch
+/// coarsens all of its samples. A record with x, y and z is 26 bytes instead
+/// of 32.
+///
+/// ```
+/// use accel_data::{AccelData, CompactAccelData};
+///
+/// let samples = [
+///     AccelData { x: 0.01, z: 1.0, ..Default::default() },
+///     AccelData { x: -0.02, z: -2.0, ..Default::default() },
+/// ];
+/// let scale = CompactAccelData::scale_for(&samples);
+/// assert_eq!(scale, 2.0 / i16::MAX as f32);
+/// let compact = CompactAccelData::from_accel(&samples[1], scale);
+/// assert_eq!((compact.x, compact.z), (-328, -i16::MAX));
+/// let data = compact.to_accel(scale);
+/// assert!((data.x + 0.02).abs() <= scale / 2.0);
+/// assert!((data.z + 2.0).abs() < 1e-6);
+/// // values beyond the scale saturate
+/// assert_eq!(CompactAccelData::from_accel(&samples[1], scale / 4.0).z, i16::MIN);
+/// ```
+pub struct CompactAccelData {
+    /// Sensor Index
+    pub idx: u32,
+    /// See [`AccelData::seq`]
+    pub seq: u32,
+    /// See [`AccelData::gap`]
+    pub gap: u32,
+    /// See [`AccelData::time`]
+    pub time: u64,
+    /// X-axis acceleration in counts of the scale
+    pub x: i16,
+    /// Y-axis acceleration in counts of the scale
+    pub y: i16,
+    /// Z-axis acceleration in counts of the scale
+    pub z: i16,
+    /// See [`AccelData::temp`]
+    pub temp: Option<f32>,
+    /// See [`AccelData::fresh`]
+    pub fresh: Option<bool>,
+}
+
+impl CompactAccelData {
+    /// Scale, in sample units per count, fitting the largest finite axis
+    /// value of `samples` in an `i16`. 1 if every axis is zero
+    pub fn scale_for<'a>(samples: impl IntoIterator<Item = &'a AccelData>) -> f32 {
+        let peak = samples
+            .into_iter()
+            .flat_map(|data| [data.x, data.y, data.z])
+            .filter(|value| value.is_finite())
+            .fold(0.0f32, |peak, value| peak.max(value.abs()));
+        if peak > 0.0 {
+            peak / i16::MAX as f32
+        } else {
+            1.0
+        }
+    }
+
+    /// Quantize the axes of `data` to counts of `scale`, rounding to the
+    /// nearest count and saturating. NaN axes are 0
+    pub fn from_accel(data: &AccelData, scale: f32) -> Self {
+        let count = |value: f32| (value / scale).round() as i16;
+        CompactAccelData {
+            idx: data.idx,
+            seq: data.seq,
+            gap: data.gap,
+            time: data.time,
+            x: count(data.x),
+            y: count(data.y),
+            z: count(data.z),
+            temp: data.temp,
+            fresh: data.fresh,
+        }
+    }
+
+    /// Sample with the counts converted back to units at `scale`
+    pub fn to_accel(&self, scale: f32) -> AccelData {
+        AccelData {
+            idx: self.idx,
+            seq: self.seq,
+            gap: self.gap,
+            time: self.time,
+            x: self.x as f32 * scale,
+            y: self.y as f32 * scale,
+            z: self.z as f32 * scale,
+            temp: self.temp,
+            fresh: self.fresh,
+        }
+    }
+}
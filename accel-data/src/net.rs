@@ -1,58 +1,724 @@
-use crate::AccelData;
-use futures_util::{SinkExt, stream::StreamExt};
-use std::sync::{
-    Arc,
-    atomic::{AtomicBool, Ordering},
+use crate::{
+    AccelData, AxisMask, ClientMessage, Command, DataFrame, ErrorKind, Handshake, History,
+    RateMeter, Status,
+    frame::{COMPACT_REQUEST, stream_header},
+    handshake_frame, status_frame,
 };
+use futures_util::{Sink, SinkExt, stream::StreamExt};
+use std::{
+    collections::HashMap,
+    fmt, io,
+    net::SocketAddr,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    sync::{
+        broadcast::{self, Sender, error::RecvError},
+        mpsc,
+    },
+};
+use tokio_tungstenite::tungstenite::{
+    self,
+    handshake::server::{ErrorResponse, Request, Response},
+    http::{StatusCode, header::AUTHORIZATION},
+    protocol::{CloseFrame, Message, WebSocketConfig, frame::coding::CloseCode},
+};
+
+/// Server configuration
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub(crate) axes: AxisMask,
+    pub(crate) commands: Option<mpsc::Sender<CommandRequest>>,
+    pub(crate) status: Option<Sender<Status>>,
+    pub(crate) batch: BatchLimits,
+    pub(crate) channels: Vec<(String, Sender<AccelData>)>,
+    pub(crate) handshake: Option<Handshake>,
+    pub(crate) stats_interval: Duration,
+    pub(crate) nodelay: bool,
+    pub(crate) send_buffer: Option<u32>,
+    pub(crate) write_timeout: Duration,
+    pub(crate) max_latency: Option<Duration>,
+    pub(crate) history: Option<History>,
+    pub(crate) max_message_size: usize,
+    pub(crate) client_buffer: usize,
+    pub(crate) auth_token: Option<String>,
+    pub(crate) clients: Arc<AtomicUsize>,
+    pub(crate) errors: bool,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            axes: AxisMask::default(),
+            commands: None,
+            status: None,
+            batch: BatchLimits::default(),
+            channels: Vec::new(),
+            handshake: None,
+            stats_interval: Duration::from_secs(1),
+            nodelay: true,
+            send_buffer: None,
+            write_timeout: Duration::from_secs(5),
+            max_latency: None,
+            history: None,
+            max_message_size: 4096,
+            client_buffer: 0,
+            auth_token: None,
+            clients: Arc::new(AtomicUsize::new(0)),
+            errors: false,
+        }
+    }
+}
+
+/// Name of the channel served to clients by default
+pub const RAW_CHANNEL: &str = "raw";
+
+/// Bounds of the TCP data frame size
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct BatchLimits {
+    min: usize,
+    max: usize,
+}
+
+impl Default for BatchLimits {
+    fn default() -> Self {
+        Self { min: 64, max: 8192 }
+    }
+}
+
+impl ServerConfig {
+    /// Sets the axes forwarded to clients.
+    /// Default is all axes
+    ///
+    /// Disabled axes are omitted from TCP/UDP data frames and zeroed in
+    /// WebSocket messages.
+    pub fn axes(&mut self, axes: AxisMask) -> &mut Self {
+        self.axes = axes;
+        self
+    }
+
+    /// Sets the channel [`Command`]s from admin clients are forwarded on.
+    /// Default is none, where all commands are rejected
+    ///
+    /// Clients must elevate themselves with [`ClientMessage::Admin`] before
+    /// sending commands. Rejected commands are answered with a
+    /// [`ErrorKind::CommandFailed`] record.
+    pub fn commands(&mut self, commands: mpsc::Sender<CommandRequest>) -> &mut Self {
+        self.commands = Some(commands);
+        self
+    }
+
+    /// Sets the channel [`Status`] records are forwarded to clients from.
+    /// Default is none, where no status records are sent
+    pub fn status(&mut self, status: Sender<Status>) -> &mut Self {
+        self.status = Some(status);
+        self
+    }
+
+    /// Sets the [`Handshake`] sent to TCP and WebSocket clients on
+    /// connection. Default is none, where data flows immediately
+    pub fn handshake(&mut self, handshake: Handshake) -> &mut Self {
+        self.handshake = Some(handshake);
+        self
+    }
+
+    /// Sets the interval of the per-client `[STATS]` log lines, which report
+    /// samples (`count_per_s`) and bytes sent per second as `key=value`
+    /// pairs. Default is 1 second
+    pub fn stats_interval(&mut self, interval: Duration) -> &mut Self {
+        self.stats_interval = interval;
+        self
+    }
+
+    /// Adds a derived data channel WebSocket clients can switch to with
+    /// [`ClientMessage::Subscribe`], e.g. a smoothed copy of the data
+    pub fn channel(&mut self, name: &str, channel: Sender<AccelData>) -> &mut Self {
+        self.channels.push((name.to_owned(), channel));
+        self
+    }
+
+    /// Sets `TCP_NODELAY` on TCP and WebSocket connections.
+    /// Default is on
+    ///
+    /// Without it, Nagle's algorithm holds back small writes until the
+    /// previous segment is acknowledged, which delays data frames by up to a
+    /// round trip. Turning it off saves packets on slow links at the cost of
+    /// latency.
+    pub fn nodelay(&mut self, nodelay: bool) -> &mut Self {
+        self.nodelay = nodelay;
+        self
+    }
+
+    /// Sets the socket send buffer size (`SO_SNDBUF`) of TCP and WebSocket
+    /// connections in bytes. Default is the system default
+    ///
+    /// A larger buffer keeps high bandwidth or high latency links busy and
+    /// absorbs stalls, but lets more data queue up before a slow client is
+    /// noticed, adding latency. The kernel may round or double the value.
+    pub fn send_buffer_size(&mut self, size: u32) -> &mut Self {
+        self.send_buffer = Some(size);
+        self
+    }
+
+    /// Sets the deadline of each write to a TCP or WebSocket client.
+    /// Default is 5 seconds
+    ///
+    /// A client whose write does not complete in time is dropped, so a
+    /// stalled consumer cannot hold its subscription and buffers forever.
+    pub fn write_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.write_timeout = timeout;
+        self
+    }
+
+    /// Sets the longest a sample waits in a partially filled TCP data frame
+    /// or WebSocket message. Default is no limit
+    ///
+    /// Frames and messages are otherwise only sent when full, which at low
+    /// sample rates (few sensors, deadband or derived channels) can hold
+    /// samples back for seconds, or minutes on a 1 Hz channel. Frames sent
+    /// on the deadline do not change the frame size chosen by
+    /// [`ServerConfig::batch_size`].
+    pub fn max_latency(&mut self, latency: Duration) -> &mut Self {
+        self.max_latency = Some(latency);
+        self
+    }
+
+    /// Sets the [`History`] WebSocket clients can have replayed on connect.
+    /// Default is none, where clients only receive new samples
+    ///
+    /// Clients request the last `n` samples of every sensor with a `replay`
+    /// query parameter, e.g. `ws://host:port/?replay=1000`, limited to the
+    /// history depth. Replayed samples are sent before any new ones, ordered
+    /// by time, with their original `seq` and `time`. Samples of derived
+    /// channels are not replayed. The history must be recorded from the
+    /// channel the server is started with, see [`History::record`].
+    pub fn history(&mut self, history: History) -> &mut Self {
+        self.history = Some(history);
+        self
+    }
+
+    /// Sets the largest message accepted from a WebSocket client in bytes.
+    /// Default is 4096 bytes
+    ///
+    /// Client messages are small JSON objects (see [`ClientMessage`]); a
+    /// client sending a longer message, or one that does not parse, is
+    /// disconnected with a close frame giving the reason.
+    pub fn max_message_size(&mut self, size: usize) -> &mut Self {
+        self.max_message_size = size;
+        self
+    }
+
+    /// Sets the number of samples buffered per TCP and WebSocket client.
+    /// Default is 0, where clients read straight from the broadcast channel
+    ///
+    /// A client that stalls, e.g. a dashboard pausing for garbage
+    /// collection, falls behind by the capacity of the channel the server is
+    /// started with plus this buffer before samples are skipped. Skipped
+    /// samples are logged, counted in the `[ACCESS]` line, and seen by the
+    /// client as a jump in `seq`; the connection is kept. Each buffered
+    /// sample takes about 50 bytes per client: at 4 kHz with two sensors,
+    /// 16000 samples cover a 2 s stall for about 800 kB per client.
+    pub fn client_buffer(&mut self, samples: usize) -> &mut Self {
+        self.client_buffer = samples;
+        self
+    }
+
+    /// Sets the counter of the clients connected to the servers started
+    /// with this configuration, e.g. to idle the source while there are
+    /// none. Default is a counter of its own
+    ///
+    /// Stream and WebSocket clients count once served, after any auth
+    /// token, until disconnected; UDP clients while subscribed.
+    pub fn clients(&mut self, clients: Arc<AtomicUsize>) -> &mut Self {
+        self.clients = clients;
+        self
+    }
+
+    /// Sets whether a client is sent a [`Status::Error`] record of kind
+    /// [`ErrorKind::Overrun`] when samples are skipped for it.
+    /// Default is false
+    ///
+    /// The record takes the place of the skipped samples in the stream, so
+    /// clients can tell the gap from a sensor failure. UDP clients are all
+    /// sent the records of the server falling behind.
+    pub fn errors(&mut self, errors: bool) -> &mut Self {
+        self.errors = errors;
+        self
+    }
+
+    /// Sets the shared secret clients must present before they are served.
+    /// Default is none, where every client is served
+    ///
+    /// TCP and Unix-domain clients send the token as the first line, ended
+    /// by `\n`, within [`AUTH_TIMEOUT`] of connecting; nothing is sent
+    /// before. WebSocket clients pass it as a `token` query parameter, e.g.
+    /// `ws://host:port/?token=secret`, or an `Authorization: Bearer secret`
+    /// header, and are refused with 401 otherwise. UDP clients send
+    /// `hello secret` instead of [`UDP_HELLO`]. Tokens are compared in
+    /// constant time, but travel in clear text: on untrusted networks,
+    /// tunnel the connections.
+    pub fn auth_token(&mut self, token: impl Into<String>) -> &mut Self {
+        self.auth_token = Some(token.into());
+        self
+    }
+
+    /// Sets the bounds of the TCP data frame size in bytes.
+    /// Default is 64 to 8192 bytes
+    ///
+    /// Each client starts at 1024 bytes (clamped to the bounds). Frames shrink
+    /// while writes complete quickly, cutting latency, and grow while writes
+    /// are slow, cutting syscall overhead. Set `min == max` for a fixed size.
+    pub fn batch_size(&mut self, min: usize, max: usize) -> &mut Self {
+        self.batch = BatchLimits {
+            min: min.min(max),
+            max,
+        };
+        self
+    }
+}
+
+/// Time a TCP or Unix-domain client has to send its token, see
+/// [`ServerConfig::auth_token`]
+pub const AUTH_TIMEOUT: Duration = Duration::from_secs(5);
+/// Longest token line accepted from a stream client
+const MAX_TOKEN_LINE: usize = 256;
 
-use tokio::{io::AsyncWriteExt, sync::broadcast::Sender};
-use tokio_tungstenite::tungstenite::protocol::Message;
+/// Compare a presented token with the expected one in time independent of
+/// where they differ. Only the length of the expected token leaks
+fn token_matches(expected: &str, presented: &[u8]) -> bool {
+    let expected = expected.as_bytes();
+    expected.len() == presented.len()
+        && expected
+            .iter()
+            .zip(presented)
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Read the token line of a stream client, without its line ending
+async fn read_token(reader: &mut (impl AsyncRead + Unpin)) -> io::Result<Vec<u8>> {
+    let mut line = Vec::new();
+    let mut byte = [0u8];
+    // byte by byte, so nothing after the line is consumed
+    while line.len() <= MAX_TOKEN_LINE {
+        if reader.read(&mut byte).await? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        if byte[0] == b'\n' {
+            return Ok(line);
+        }
+        line.push(byte[0]);
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "token line too long",
+    ))
+}
+
+/// Writes faster than this shrink the TCP data frames
+const FAST_WRITE: Duration = Duration::from_micros(200);
+/// Writes slower than this grow the TCP data frames
+const SLOW_WRITE: Duration = Duration::from_millis(2);
+
+/// Adapt the data frame size to the time the last write took
+fn adapt_batch(frame: &mut DataFrame, limits: BatchLimits, write: Duration) {
+    let size = frame.capacity();
+    let size = if write > SLOW_WRITE {
+        size * 2
+    } else if write < FAST_WRITE {
+        size * 3 / 4
+    } else {
+        size
+    };
+    frame.set_capacity(size.clamp(limits.min, limits.max));
+}
 
-pub async fn udp_server(port: u16, running: Arc<AtomicBool>, sink: Sender<AccelData>) {
+/// Bind a TCP listener on all interfaces. The send buffer size is set on the
+/// listener, accepted connections inherit it.
+fn bind_tcp(port: u16, config: &ServerConfig) -> std::io::Result<tokio::net::TcpListener> {
+    let socket = tokio::net::TcpSocket::new_v4()?;
+    socket.set_reuseaddr(true)?;
+    if let Some(size) = config.send_buffer {
+        socket.set_send_buffer_size(size)?;
+    }
+    socket.bind(SocketAddr::from(([0, 0, 0, 0], port)))?;
+    socket.listen(1024)
+}
+
+/// Apply the per-connection socket options
+fn tune_socket(socket: &tokio::net::TcpStream, addr: SocketAddr, config: &ServerConfig) {
+    if let Err(e) = socket.set_nodelay(config.nodelay) {
+        log::warn!("[NET] {addr}> Failed to set TCP_NODELAY: {e}");
+    }
+}
+
+/// Timeout error of a client write, reporting the bytes left unsent
+fn timed_out(deadline: Duration, pending: usize) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::TimedOut,
+        format!("timed out after {deadline:?} with {pending} bytes pending"),
+    )
+}
+
+/// Write all of `buf` to a stream client within `deadline`
+async fn write_within(
+    writer: &mut (impl AsyncWrite + Unpin),
+    buf: &[u8],
+    deadline: Duration,
+) -> io::Result<()> {
+    let mut written = 0;
+    let write = async {
+        while written < buf.len() {
+            match writer.write(&buf[written..]).await? {
+                0 => return Err(io::Error::from(io::ErrorKind::WriteZero)),
+                n => written += n,
+            }
+        }
+        Ok(())
+    };
+    let res = tokio::time::timeout(deadline, write).await;
+    res.unwrap_or_else(|_| Err(timed_out(deadline, buf.len() - written)))
+}
+
+/// Send a message to a WebSocket client within `deadline`
+async fn send_within<S>(outgoing: &mut S, msg: Message, deadline: Duration) -> io::Result<()>
+where
+    S: Sink<Message, Error = tungstenite::Error> + Unpin,
+{
+    let pending = msg.len();
+    match tokio::time::timeout(deadline, outgoing.send(msg)).await {
+        Ok(res) => res.map_err(io::Error::other),
+        Err(_) => Err(timed_out(deadline, pending)),
+    }
+}
+
+/// Wait until `at`, never completes without a deadline
+async fn flush_due(at: Option<tokio::time::Instant>) {
+    match at {
+        Some(at) => tokio::time::sleep_until(at).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Samples of one client, read from the broadcast channel directly or
+/// through a forwarding task filling a buffer of [`ServerConfig::client_buffer`]
+enum ClientSource {
+    Direct(broadcast::Receiver<AccelData>),
+    Buffered(mpsc::Receiver<Result<AccelData, RecvError>>),
+}
+
+impl ClientSource {
+    fn new(source: broadcast::Receiver<AccelData>, buffer: usize) -> Self {
+        if buffer == 0 {
+            return ClientSource::Direct(source);
+        }
+        let (tx, rx) = mpsc::channel(buffer);
+        tokio::spawn(forward(source, tx));
+        ClientSource::Buffered(rx)
+    }
+
+    async fn recv(&mut self) -> Result<AccelData, RecvError> {
+        match self {
+            ClientSource::Direct(source) => source.recv().await,
+            ClientSource::Buffered(rx) => rx.recv().await.unwrap_or(Err(RecvError::Closed)),
+        }
+    }
+}
+
+/// Move samples from `source` to `tx` until either end closes. Waiting for
+/// room in `tx` leaves the samples in the broadcast channel, so a client
+/// lags only once both are full
+async fn forward(
+    mut source: broadcast::Receiver<AccelData>,
+    tx: mpsc::Sender<Result<AccelData, RecvError>>,
+) {
+    loop {
+        let msg = tokio::select! {
+            msg = source.recv() => msg,
+            _ = tx.closed() => break,
+        };
+        let closed = matches!(msg, Err(RecvError::Closed));
+        if tx.send(msg).await.is_err() || closed {
+            break;
+        }
+    }
+}
+
+/// Per-client totals, logged as one `[ACCESS]` line when the client handler
+/// ends, however the connection ended
+struct AccessLog {
+    source: &'static str,
+    /// Connected clients, counting this one
+    clients: Arc<AtomicUsize>,
+    addr: String,
+    start: Instant,
+    /// Samples written to the client
+    samples: u64,
+    /// Bytes written to the client, including handshake and status records
+    bytes: u64,
+    /// Samples skipped because the client lagged behind
+    dropped: u64,
+    /// Times the client lagged behind
+    lags: u64,
+}
+
+impl AccessLog {
+    fn new(source: &'static str, addr: impl fmt::Display, clients: &Arc<AtomicUsize>) -> Self {
+        clients.fetch_add(1, Ordering::Relaxed);
+        Self {
+            source,
+            clients: clients.clone(),
+            addr: addr.to_string(),
+            start: Instant::now(),
+            samples: 0,
+            bytes: 0,
+            dropped: 0,
+            lags: 0,
+        }
+    }
+
+    fn wrote(&mut self, samples: usize, bytes: usize) {
+        self.samples += samples as u64;
+        self.bytes += bytes as u64;
+    }
+
+    fn lagged(&mut self, skipped: u64) {
+        self.dropped += skipped;
+        self.lags += 1;
+    }
+}
+
+impl Drop for AccessLog {
+    fn drop(&mut self) {
+        self.clients.fetch_sub(1, Ordering::Relaxed);
+        log::info!(
+            "[ACCESS] source={} peer={} duration_s={:.3} samples={} bytes={} dropped={} lags={}",
+            self.source,
+            self.addr,
+            self.start.elapsed().as_secs_f64(),
+            self.samples,
+            self.bytes,
+            self.dropped,
+            self.lags
+        );
+    }
+}
+
+/// Receive the next status record, never completes if status records are
+/// disabled
+async fn recv_status(
+    status: &mut Option<broadcast::Receiver<Status>>,
+) -> Result<Status, RecvError> {
+    match status {
+        Some(status) => status.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Datagram clients send to the UDP server to subscribe, and periodically
+/// afterwards to stay subscribed
+pub const UDP_HELLO: &[u8] = b"hello";
+/// Time after its last hello a UDP client is dropped
+pub const UDP_PEER_TIMEOUT: Duration = Duration::from_secs(10);
+/// Maximum number of subscribed UDP clients
+pub const UDP_MAX_PEERS: usize = 16;
+
+/// Serve accelerometer data over UDP, as datagrams holding the stream header
+/// and one frame each (see [`crate::frame`]).
+///
+/// Clients subscribe by sending [`UDP_HELLO`], followed by a space and the
+/// token if [`ServerConfig::auth_token`] is set, are answered with the
+/// handshake if one is configured, and must repeat it within
+/// [`UDP_PEER_TIMEOUT`] to keep receiving data. At most [`UDP_MAX_PEERS`]
+/// clients are served at once.
+///
+/// Runs until `running` is cleared. Fails if the socket cannot be bound.
+pub async fn udp_server(
+    port: u16,
+    running: Arc<AtomicBool>,
+    sink: Sender<AccelData>,
+    config: ServerConfig,
+) -> io::Result<()> {
     log::info!("[NET] Starting UDP server on port {port}");
-    let listener = tokio::net::UdpSocket::bind(format!("0.0.0.0:{port}"))
-        .await
-        .expect("[NET] Failed to bind UDP listener");
+    let listener = tokio::net::UdpSocket::bind(format!("0.0.0.0:{port}")).await?;
     log::info!("[NET] UDP server listening on port {port}");
     let mut source = sink.subscribe();
+    let mut status = config.status.as_ref().map(Sender::subscribe);
+    let mut peers: HashMap<SocketAddr, Instant> = HashMap::new();
+    // peers counted in the connected clients
+    let mut counted = 0;
+    let mut frame = DataFrame::new(config.axes, 1024);
+    let mut rbuf = [0u8; UDP_HELLO.len() + 1 + MAX_TOKEN_LINE];
     while running.load(Ordering::Relaxed) {
-        let mut buf = Vec::with_capacity(1024);
-        match source.recv().await {
-            Ok(data) => {
-                let data = data.as_bytes();
-                if buf.len() + data.len() < buf.capacity() {
-                    buf.extend_from_slice(&data);
-                } else {
-                    if listener.send(&buf).await.is_err() {
-                        log::error!("[NET] Failed to send data over UDP");
-                        break;
+        tokio::select! {
+            msg = source.recv() => {
+                match msg {
+                    Ok(data) => {
+                        if !frame.has_room() {
+                            send_to_peers(&listener, &mut peers, frame.as_bytes()).await;
+                            frame.clear();
+                        }
+                        frame.push(&data);
                     }
-                    buf.clear();
-                    buf.extend_from_slice(&data);
+                    Err(RecvError::Lagged(n)) => {
+                        log::warn!("[NET] UDP server dropped {n} samples");
+                        if config.errors
+                            && let Some(frame) = encoded("UDP", status_frame(&overrun(n)))
+                        {
+                            send_to_peers(&listener, &mut peers, &frame).await;
+                        }
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            },
+            msg = recv_status(&mut status) => {
+                match msg {
+                    Ok(msg) => {
+                        if let Some(frame) = encoded("UDP", status_frame(&msg)) {
+                            send_to_peers(&listener, &mut peers, &frame).await;
+                        }
+                    }
+                    Err(RecvError::Lagged(n)) => {
+                        log::warn!("[NET] UDP server dropped {n} status records");
+                    }
+                    Err(RecvError::Closed) => status = None,
+                }
+            },
+            res = listener.recv_from(&mut rbuf) => {
+                match res {
+                    Ok((len, addr)) if hello_token(&rbuf[..len]).is_some_and(|presented| {
+                        config.auth_token.as_ref().is_some_and(|token| !token_matches(token, presented))
+                    }) => {
+                        log::warn!("[NET] {addr}> Rejected UDP client: invalid auth token");
+                    }
+                    Ok((len, addr)) if hello_token(&rbuf[..len]).is_some() => {
+                        peers.retain(|_, last| last.elapsed() < UDP_PEER_TIMEOUT);
+                        if peers.contains_key(&addr) || peers.len() < UDP_MAX_PEERS {
+                            if peers.insert(addr, Instant::now()).is_none() {
+                                log::info!("[NET] {addr}> UDP client subscribed.");
+                                if let Some(handshake) = &config.handshake
+                                    && let Some(frame) = encoded(addr, handshake_frame(handshake))
+                                    && let Err(e) = listener.send_to(&datagram(&frame), addr).await
+                                {
+                                    log::error!("[NET] {addr}> Error sending handshake: {e}");
+                                }
+                            }
+                        } else {
+                            log::warn!("[NET] {addr}> Rejected UDP client: too many clients");
+                        }
+                    }
+                    Ok((_, addr)) => log::warn!("[NET] {addr}> Ignoring unrecognized datagram"),
+                    // e.g. ICMP port unreachable reported for an earlier send
+                    Err(e) => log::debug!("[NET] UDP receive failed: {e}"),
                 }
-            }
-            Err(e) => {
-                log::error!("[NET] Failed to receive data: {e}");
             }
         }
+        recount(&config.clients, &mut counted, peers.len());
     }
+    recount(&config.clients, &mut counted, 0);
     log::info!("[NET] UDP server stopped");
+    Ok(())
+}
+
+/// Update `clients` for the UDP peers going from `counted` to `peers`
+fn recount(clients: &AtomicUsize, counted: &mut usize, peers: usize) {
+    if peers > *counted {
+        clients.fetch_add(peers - *counted, Ordering::Relaxed);
+    } else {
+        clients.fetch_sub(*counted - peers, Ordering::Relaxed);
+    }
+    *counted = peers;
+}
+
+/// [`ErrorKind::Overrun`] record of `skipped` samples
+fn overrun(skipped: u64) -> Status {
+    Status::Error {
+        idx: None,
+        kind: ErrorKind::Overrun,
+        count: u32::try_from(skipped).unwrap_or(u32::MAX),
+        time: 0,
+        message: String::new(),
+    }
 }
 
-pub async fn tcp_server(port: u16, running: Arc<AtomicBool>, sink: Sender<AccelData>) {
+/// Token of a hello datagram, empty if it has none. `None` if the datagram
+/// is not a hello
+fn hello_token(datagram: &[u8]) -> Option<&[u8]> {
+    let rest = datagram.trim_ascii().strip_prefix(UDP_HELLO)?;
+    if rest.is_empty() {
+        return Some(rest);
+    }
+    rest.strip_prefix(b" ").map(<[u8]>::trim_ascii)
+}
+
+/// Prefix a frame with the stream header
+fn datagram(frame: &[u8]) -> Vec<u8> {
+    [&stream_header()[..], frame].concat()
+}
+
+/// `frame` encoded for `peer` by [`status_frame`] or [`handshake_frame`],
+/// logging the record it drops if that was too long for a frame
+fn encoded(peer: impl fmt::Display, frame: Option<Vec<u8>>) -> Option<Vec<u8>> {
+    if frame.is_none() {
+        log::warn!("[NET] {peer}> Dropped a record too long for a frame");
+    }
+    frame
+}
+
+/// Send a frame as a datagram to every live peer, dropping expired ones
+async fn send_to_peers(
+    socket: &tokio::net::UdpSocket,
+    peers: &mut HashMap<SocketAddr, Instant>,
+    frame: &[u8],
+) {
+    peers.retain(|addr, last| {
+        let live = last.elapsed() < UDP_PEER_TIMEOUT;
+        if !live {
+            log::info!("[NET] {addr}> UDP client expired.");
+        }
+        live
+    });
+    if peers.is_empty() {
+        return;
+    }
+    let buf = datagram(frame);
+    for addr in peers.keys() {
+        if let Err(e) = socket.send_to(&buf, addr).await {
+            log::error!("[NET] {addr}> Error sending data over UDP: {e}");
+        }
+    }
+}
+
+/// Serve accelerometer data over TCP, as a stream of frames described in
+/// [`crate::frame`].
+///
+/// Runs until `running` is cleared. Fails if the listener cannot be bound.
+pub async fn tcp_server(
+    port: u16,
+    running: Arc<AtomicBool>,
+    sink: Sender<AccelData>,
+    config: ServerConfig,
+) -> io::Result<()> {
     log::info!("[NET] Starting TCP server on port {port}");
-    let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{port}"))
-        .await
-        .expect("[NET] Failed to bind TCP listener");
+    let listener = bind_tcp(port, &config)?;
     log::info!("[NET] TCP server listening on port {port}");
     while running.load(Ordering::Relaxed) {
         match listener.accept().await {
             Ok((socket, addr)) => {
                 log::info!("[NET] Accepted connection from {addr}");
+                tune_socket(&socket, addr, &config);
                 let running = running.clone();
                 let sink = sink.clone();
+                let config = config.clone();
                 tokio::spawn(async move {
-                    handle_client_tcp(socket, addr, running, sink).await;
+                    let (reader, writer) = socket.into_split();
+                    handle_client_stream(reader, writer, "tcp", addr, running, sink, config).await;
                 });
             }
             Err(e) => {
@@ -61,128 +727,655 @@ pub async fn tcp_server(port: u16, running: Arc<AtomicBool>, sink: Sender<AccelD
         }
     }
     log::info!("[NET] TCP server stopped");
+    Ok(())
 }
 
-#[allow(dead_code)]
-async fn handle_client_tcp(
-    socket: tokio::net::TcpStream,
-    addr: std::net::SocketAddr,
+/// Socket file of a Unix-domain listener, removed when dropped
+#[cfg(unix)]
+struct SocketFile(std::path::PathBuf);
+
+#[cfg(unix)]
+impl Drop for SocketFile {
+    fn drop(&mut self) {
+        match std::fs::remove_file(&self.0) {
+            Ok(()) => log::info!("[NET] Removed socket {}", self.0.display()),
+            Err(e) => log::warn!("[NET] Failed to remove socket {}: {e}", self.0.display()),
+        }
+    }
+}
+
+/// Serve the same stream as [`tcp_server`] on a Unix-domain socket at
+/// `path`, for local clients. A stale socket left at `path` is replaced, and
+/// the socket file is removed when the server stops or its task is aborted.
+/// [`ServerConfig::nodelay`] and [`ServerConfig::send_buffer_size`] do not
+/// apply. Fails if the socket cannot be bound, e.g. in a missing directory.
+#[cfg(unix)]
+pub async fn uds_server(
+    path: impl AsRef<std::path::Path>,
     running: Arc<AtomicBool>,
     sink: Sender<AccelData>,
+    config: ServerConfig,
+) -> io::Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+    let path = path.as_ref();
+    log::info!("[NET] Starting Unix-domain server on {}", path.display());
+    if std::fs::symlink_metadata(path).is_ok_and(|meta| meta.file_type().is_socket()) {
+        log::info!("[NET] Replacing stale socket {}", path.display());
+        let _ = std::fs::remove_file(path);
+    }
+    let listener = tokio::net::UnixListener::bind(path)?;
+    let _socket = SocketFile(path.to_path_buf());
+    log::info!("[NET] Unix-domain server listening on {}", path.display());
+    let mut clients = 0u64;
+    while running.load(Ordering::Relaxed) {
+        match listener.accept().await {
+            Ok((socket, _)) => {
+                clients += 1;
+                // local peers are unnamed, number them instead
+                let addr = format!("uds#{clients}");
+                log::info!("[NET] Accepted connection from {addr}");
+                let running = running.clone();
+                let sink = sink.clone();
+                let config = config.clone();
+                tokio::spawn(async move {
+                    let (reader, writer) = socket.into_split();
+                    handle_client_stream(reader, writer, "uds", addr, running, sink, config).await;
+                });
+            }
+            Err(e) => {
+                log::error!("[NET] Failed to accept connection on server: {e}");
+            }
+        }
+    }
+    log::info!("[NET] Unix-domain server stopped");
+    Ok(())
+}
+
+/// Serve the frames of [`crate::frame`] to a TCP or Unix-domain stream
+/// client, logged with `protocol` and `addr`
+async fn handle_client_stream(
+    mut reader: impl AsyncRead + Unpin,
+    mut writer: impl AsyncWrite + Unpin,
+    protocol: &'static str,
+    addr: impl fmt::Display,
+    running: Arc<AtomicBool>,
+    sink: Sender<AccelData>,
+    config: ServerConfig,
 ) {
     log::info!("[NET] {addr}> Handling client.");
-    let (_, mut writer) = socket.into_split();
-    let mut source = sink.subscribe();
-    let mut buf = Vec::with_capacity(1024);
-    let mut counter = 0;
-    let mut dcounter = 0;
-    let mut now = std::time::Instant::now();
+    if let Some(token) = &config.auth_token {
+        match tokio::time::timeout(AUTH_TIMEOUT, read_token(&mut reader)).await {
+            Ok(Ok(line)) if token_matches(token, line.trim_ascii()) => {
+                log::info!("[NET] {addr}> Client authenticated.");
+            }
+            Ok(Ok(_)) => {
+                log::warn!("[NET] {addr}> Rejected client: invalid auth token");
+                return;
+            }
+            Ok(Err(e)) => {
+                log::warn!("[NET] {addr}> Rejected client: no auth token: {e}");
+                return;
+            }
+            Err(_) => {
+                log::warn!("[NET] {addr}> Rejected client: no auth token within {AUTH_TIMEOUT:?}");
+                return;
+            }
+        }
+    }
+    let mut access = AccessLog::new(protocol, &addr, &config.clients);
+    let header = stream_header();
+    if let Err(e) = write_within(&mut writer, &header, config.write_timeout).await {
+        log::error!("[NET] {addr}> Error sending stream header: {e}");
+        return;
+    }
+    access.wrote(0, header.len());
+    if let Some(handshake) = &config.handshake
+        && let Some(frame) = encoded(&addr, handshake_frame(handshake))
+    {
+        if let Err(e) = write_within(&mut writer, &frame, config.write_timeout).await {
+            log::error!("[NET] {addr}> Error sending handshake: {e}");
+            return;
+        }
+        access.wrote(0, frame.len());
+    }
+    let mut source = ClientSource::new(sink.subscribe(), config.client_buffer);
+    let mut status = config.status.as_ref().map(Sender::subscribe);
+    let mut frame = DataFrame::new(config.axes, 1024.clamp(config.batch.min, config.batch.max));
+    let mut rbuf = [0u8; 256];
+    // partial line of client input
+    let mut line = Vec::new();
+    let mut stats = RateMeter::new(config.stats_interval);
+    // deadline of the oldest sample in the frame
+    let mut flush_at = None;
 
     while running.load(Ordering::Relaxed) {
-        match source.recv().await {
-            Ok(data) => {
-                let data = data.as_bytes();
-                if buf.len() + data.len() < buf.capacity() {
-                    buf.extend_from_slice(&data);
-                } else {
-                    dcounter += buf.len();
-                    if writer.write_all(&buf).await.is_err() {
-                        log::error!("[NET] {addr}> Error sending data");
+        tokio::select! {
+            msg = source.recv() => {
+                match msg {
+                    Ok(data) => {
+                        let mut bytes = 0;
+                        if !frame.has_room() {
+                            let buf = frame.as_bytes();
+                            bytes = buf.len() as u64;
+                            let start = std::time::Instant::now();
+                            if let Err(e) = write_within(&mut writer, buf, config.write_timeout).await {
+                                log::error!("[NET] {addr}> Error sending data, dropping client: {e}");
+                                break;
+                            }
+                            adapt_batch(&mut frame, config.batch, start.elapsed());
+                            access.wrote(frame.len(), bytes as usize);
+                            frame.clear();
+                            flush_at = None;
+                        }
+                        frame.push(&data);
+                        if flush_at.is_none() {
+                            flush_at = config.max_latency.map(|latency| tokio::time::Instant::now() + latency);
+                        }
+                        if let Some(rates) = stats.record(1, bytes) {
+                            log::info!(
+                                "[STATS] source={protocol} peer={addr} {rates} batch_bytes={}",
+                                frame.capacity()
+                            );
+                        }
+                    }
+                    Err(RecvError::Lagged(n)) => {
+                        log::warn!("[NET] {addr}> Lagged behind, skipped {n} samples");
+                        access.lagged(n);
+                        if config.errors
+                            && let Some(frame) = encoded(&addr, status_frame(&overrun(n)))
+                        {
+                            if let Err(e) = write_within(&mut writer, &frame, config.write_timeout).await {
+                                log::error!("[NET] {addr}> Error sending status, dropping client: {e}");
+                                break;
+                            }
+                            access.wrote(0, frame.len());
+                        }
+                    }
+                    Err(RecvError::Closed) => {
+                        log::info!("[NET] {addr}> Data channel closed.");
                         break;
                     }
-                    let nnow = std::time::Instant::now();
-                    let dur = nnow.duration_since(now).as_secs_f32();
-                    if dur > 1.0 {
-                        let mut drate = (dcounter * 8) as f32 / dur;
-                        let mut unit = "bps";
-                        if drate > 1024.0 {
-                            drate /= 1024.0;
-                            unit = "kbps";
+                }
+            },
+            _ = flush_due(flush_at) => {
+                let buf = frame.as_bytes();
+                let bytes = buf.len();
+                if let Err(e) = write_within(&mut writer, buf, config.write_timeout).await {
+                    log::error!("[NET] {addr}> Error sending data, dropping client: {e}");
+                    break;
+                }
+                access.wrote(frame.len(), bytes);
+                frame.clear();
+                flush_at = None;
+                if let Some(rates) = stats.record(0, bytes as u64) {
+                    log::info!(
+                        "[STATS] source={protocol} peer={addr} {rates} batch_bytes={}",
+                        frame.capacity()
+                    );
+                }
+            },
+            msg = recv_status(&mut status) => {
+                match msg {
+                    Ok(msg) => {
+                        let Some(frame) = encoded(&addr, status_frame(&msg)) else {
+                            continue;
+                        };
+                        if let Err(e) = write_within(&mut writer, &frame, config.write_timeout).await {
+                            log::error!("[NET] {addr}> Error sending status, dropping client: {e}");
+                            break;
                         }
-                        log::info!(
-                            "[NET] {addr}> Packet rate: {:.3} packets/s ({drate:.3} {unit})",
-                            counter as f32 / dur,
-                        );
-                        now = nnow;
-                        counter = 0;
-                        dcounter = 0;
+                        access.wrote(0, frame.len());
+                    }
+                    Err(RecvError::Lagged(n)) => {
+                        log::warn!("[NET] {addr}> Dropped {n} status records");
+                    }
+                    Err(RecvError::Closed) => status = None,
+                }
+            },
+            // Detect closed connections without waiting for a write to fail
+            res = reader.read(&mut rbuf) => {
+                match res {
+                    Ok(0) => {
+                        log::info!("[NET] {addr}> Client disconnected.");
+                        break;
+                    }
+                    Ok(n) => {
+                        // Lines other than a compact request are ignored
+                        for &byte in &rbuf[..n] {
+                            if byte != b'\n' {
+                                if line.len() < MAX_TOKEN_LINE {
+                                    line.push(byte);
+                                }
+                                continue;
+                            }
+                            if line.trim_ascii() == COMPACT_REQUEST.as_bytes() && !frame.is_compact() {
+                                log::info!("[NET] {addr}> Sending compact frames.");
+                                frame.set_compact(true);
+                            }
+                            line.clear();
+                        }
+                    }
+                    Err(e) => {
+                        log::info!("[NET] {addr}> Client disconnected: {e}");
+                        break;
                     }
-                    buf.clear();
-                    buf.extend_from_slice(&data);
                 }
-                counter += 1;
+            }
+        }
+    }
+}
+
+/// [`Command`] of an admin client, forwarded on [`ServerConfig::commands`]
+#[derive(Debug)]
+pub struct CommandRequest {
+    /// The command
+    pub cmd: Command,
+    /// Connection of the client
+    reply: mpsc::Sender<Status>,
+}
+
+impl CommandRequest {
+    /// Tell the client its command failed, with an
+    /// [`ErrorKind::CommandFailed`] record. Dropped if the client is gone or
+    /// has too many replies pending
+    pub fn reject(&self, message: impl Into<String>) {
+        let _ = self.reply.try_send(rejection(self.cmd, message.into()));
+    }
+}
+
+/// [`ErrorKind::CommandFailed`] record of `cmd`
+fn rejection(cmd: Command, message: String) -> Status {
+    Status::Error {
+        idx: Some(cmd.idx()),
+        kind: ErrorKind::CommandFailed,
+        count: 1,
+        time: 0,
+        message,
+    }
+}
+
+/// Replies to the commands of a client pending at once, beyond which they
+/// are dropped
+const PENDING_REPLIES: usize = 8;
+
+/// Serve accelerometer data over WebSocket.
+///
+/// Data is sent as JSON arrays of [`AccelData`], the [`Handshake`] and
+/// [`Status`] records as JSON objects. Admin clients may send
+/// [`Command`]s if [`ServerConfig::commands`] is set.
+///
+/// Runs until `running` is cleared. Fails if the listener cannot be bound.
+pub async fn wsock_server(
+    port: u16,
+    running: Arc<AtomicBool>,
+    sink: Sender<AccelData>,
+    config: ServerConfig,
+) -> io::Result<()> {
+    log::info!("[NET] Starting WebSocket server on port {port}");
+    let listener = bind_tcp(port, &config)?;
+    log::info!("[NET] WebSocket server listening on port {port}");
+    while running.load(Ordering::Relaxed) {
+        match listener.accept().await {
+            Ok((socket, addr)) => {
+                log::info!("[NET] Accepted connection from {addr}");
+                tune_socket(&socket, addr, &config);
+                let running = running.clone();
+                let sink = sink.clone();
+                let config = config.clone();
+                tokio::spawn(async move {
+                    handle_client_wsock(socket, addr, running, sink, config).await;
+                });
             }
             Err(e) => {
-                log::error!("[NET] {addr}> Error receiving data: {e}");
+                log::error!("[NET] Failed to accept connection on server: {e}");
             }
         }
     }
+    log::info!("[NET] WebSocket server stopped");
+    Ok(())
 }
 
-#[allow(dead_code)]
 async fn handle_client_wsock(
     socket: tokio::net::TcpStream,
     addr: std::net::SocketAddr,
     running: Arc<AtomicBool>,
     sink: Sender<AccelData>,
+    config: ServerConfig,
 ) {
     log::info!("[NET] {addr}> Handling client.");
-    let ws_stream = match tokio_tungstenite::accept_async(socket).await {
+    let mut replay = 0;
+    let mut authorized = true;
+    // the error type is fixed by tungstenite
+    #[allow(clippy::result_large_err)]
+    let upgrade = |req: &Request, resp: Response| {
+        replay = replay_request(req.uri().query());
+        if let Some(token) = &config.auth_token
+            && !upgrade_authorized(req, token)
+        {
+            authorized = false;
+            let mut refusal = ErrorResponse::new(Some("invalid auth token".into()));
+            *refusal.status_mut() = StatusCode::UNAUTHORIZED;
+            return Err(refusal);
+        }
+        Ok(resp)
+    };
+    let ws_config = WebSocketConfig::default()
+        .max_message_size(Some(config.max_message_size))
+        .max_frame_size(Some(config.max_message_size));
+    let upgraded =
+        tokio_tungstenite::accept_hdr_async_with_config(socket, upgrade, Some(ws_config));
+    let ws_stream = match upgraded.await {
         Ok(ws_stream) => ws_stream,
+        Err(_) if !authorized => {
+            log::warn!("[NET] {addr}> Rejected client: invalid auth token");
+            return;
+        }
         Err(e) => {
             log::error!("[NET] {addr}> Failed to upgrade connection: {e}");
             return;
         }
     };
+    let mut access = AccessLog::new("ws", addr, &config.clients);
     let (mut outgoing, mut incoming) = ws_stream.split();
-    let mut source = sink.subscribe();
-    let mut counter = 0;
-    let mut now = std::time::Instant::now();
+    if let Some(handshake) = &config.handshake {
+        let msg = Message::from(handshake.to_json());
+        let len = msg.len();
+        if let Err(e) = send_within(&mut outgoing, msg, config.write_timeout).await {
+            log::error!("[NET] {addr}> Error sending handshake: {e}");
+            return;
+        }
+        access.wrote(0, len);
+    }
+    let mut source = ClientSource::new(sink.subscribe(), config.client_buffer);
+    let mut status = config.status.as_ref().map(Sender::subscribe);
+    let mut stats = RateMeter::new(config.stats_interval);
     let mut buf = Vec::with_capacity(128);
+    let mut admin = false;
+    let (reply, mut replies) = mpsc::channel::<Status>(PENDING_REPLIES);
+    // last replayed sequence number per sensor, newer samples are sent live
+    let mut replayed = HashMap::new();
+    if let Some(history) = config.history.as_ref().filter(|_| replay > 0) {
+        let samples = history.recent(replay);
+        log::info!("[NET] {addr}> Replaying {} samples.", samples.len());
+        for chunk in samples.chunks(buf.capacity()) {
+            let data: Vec<_> = chunk.iter().map(|data| config.axes.apply(*data)).collect();
+            let msg = match serde_json::to_string(&data) {
+                Ok(msg) => msg,
+                Err(e) => {
+                    log::error!("[NET] {addr}> Failed to encode data, dropping client: {e}");
+                    return;
+                }
+            };
+            let len = msg.len();
+            if let Err(e) =
+                send_within(&mut outgoing, Message::from(msg), config.write_timeout).await
+            {
+                log::error!("[NET] {addr}> Error replaying data, dropping client: {e}");
+                return;
+            }
+            access.wrote(chunk.len(), len);
+        }
+        replayed.extend(samples.iter().map(|data| (data.idx, data.seq)));
+    }
+    // deadline of the oldest sample in `buf`
+    let mut flush_at = None;
     while running.load(Ordering::Relaxed) {
         tokio::select! {
             msg = source.recv() => {
                 match msg {
                     Ok(data) => {
-                        if buf.len() + 1 < buf.capacity() {
-                            buf.push(data);
-                        } else {
-                            let msg = serde_json::to_string(&buf).unwrap();
-                            log::debug!("[NET] {addr}> Sending data: {msg}");
-                            if let Err(e) = outgoing.send(Message::from(
-                                msg.as_str(),
-                            )).await {
-                                log::error!("[NET] {addr}> Error sending data: {e}");
-                                break;
+                        if let Some(&seq) = replayed.get(&{ data.idx }) {
+                            if data.seq.wrapping_sub(seq) as i32 <= 0 {
+                                continue;
+                            }
+                            replayed.remove(&{ data.idx });
+                        }
+                        let data = config.axes.apply(data);
+                        let mut bytes = 0;
+                        if buf.len() + 1 >= buf.capacity() {
+                            match send_samples(&mut outgoing, addr, &buf, config.write_timeout).await {
+                                Ok(len) => bytes = len as u64,
+                                Err(e) => {
+                                    log::error!("[NET] {addr}> Error sending data, dropping client: {e}");
+                                    break;
+                                }
                             }
+                            access.wrote(buf.len(), bytes as usize);
                             buf.clear();
-                            buf.push(data);
+                            flush_at = None;
+                        }
+                        buf.push(data);
+                        if flush_at.is_none() {
+                            flush_at = config.max_latency.map(|latency| tokio::time::Instant::now() + latency);
+                        }
+                        if let Some(rates) = stats.record(1, bytes) {
+                            log::info!("[STATS] source=ws peer={addr} {rates}");
+                        }
+                    }
+                    Err(RecvError::Lagged(n)) => {
+                        log::warn!("[NET] {addr}> Lagged behind, skipped {n} samples");
+                        access.lagged(n);
+                        if config.errors {
+                            let msg = Message::from(overrun(n).to_json());
+                            let len = msg.len();
+                            if let Err(e) = send_within(&mut outgoing, msg, config.write_timeout).await {
+                                log::error!("[NET] {addr}> Error sending status, dropping client: {e}");
+                                break;
+                            }
+                            access.wrote(0, len);
                         }
-                        counter += 1;
                     }
+                    Err(RecvError::Closed) => {
+                        log::info!("[NET] {addr}> Data channel closed.");
+                        break;
+                    }
+                }
+            },
+            _ = flush_due(flush_at) => {
+                let bytes = match send_samples(&mut outgoing, addr, &buf, config.write_timeout).await {
+                    Ok(len) => len,
                     Err(e) => {
-                        log::error!("[NET] {addr}> Error receiving data: {e}");
+                        log::error!("[NET] {addr}> Error sending data, dropping client: {e}");
                         break;
                     }
+                };
+                access.wrote(buf.len(), bytes);
+                buf.clear();
+                flush_at = None;
+                if let Some(rates) = stats.record(0, bytes as u64) {
+                    log::info!("[STATS] source=ws peer={addr} {rates}");
+                }
+            },
+            Some(msg) = replies.recv() => {
+                let msg = Message::from(msg.to_json());
+                let len = msg.len();
+                if let Err(e) = send_within(&mut outgoing, msg, config.write_timeout).await {
+                    log::error!("[NET] {addr}> Error sending status, dropping client: {e}");
+                    break;
+                }
+                access.wrote(0, len);
+            },
+            msg = recv_status(&mut status) => {
+                match msg {
+                    Ok(msg) => {
+                        let msg = Message::from(msg.to_json());
+                        let len = msg.len();
+                        if let Err(e) = send_within(&mut outgoing, msg, config.write_timeout).await {
+                            log::error!("[NET] {addr}> Error sending status, dropping client: {e}");
+                            break;
+                        }
+                        access.wrote(0, len);
+                    }
+                    Err(RecvError::Lagged(n)) => {
+                        log::warn!("[NET] {addr}> Dropped {n} status records");
+                    }
+                    Err(RecvError::Closed) => status = None,
                 }
             },
             msg = incoming.next() => {
-                if let Some(Ok(msg)) = msg {
-                    log::info!("[NET] {addr}> Received message: {msg}");
-                    if msg.is_close() {
+                match msg {
+                    Some(Ok(msg)) => {
+                        log::info!("[NET] {addr}> Received message: {msg}");
+                        if msg.is_close() {
+                            log::info!("[NET] {addr}> Client disconnected.");
+                            break;
+                        }
+                        if let Message::Text(text) = msg
+                            && !handle_client_message(addr, &text, &mut admin, &reply, &config, &sink, &mut source)
+                                .await
+                        {
+                            let reason = "malformed message";
+                            close(&mut outgoing, CloseCode::Invalid, reason, config.write_timeout).await;
+                            break;
+                        }
+                    }
+                    Some(Err(tungstenite::Error::Capacity(e))) => {
+                        log::warn!("[NET] {addr}> Dropping client: {e}");
+                        let reason = "message too long";
+                        close(&mut outgoing, CloseCode::Size, reason, config.write_timeout).await;
+                        break;
+                    }
+                    Some(Err(e)) => {
+                        log::info!("[NET] {addr}> Client disconnected: {e}");
+                        break;
+                    }
+                    None => {
                         log::info!("[NET] {addr}> Client disconnected.");
                         break;
                     }
-                } else {
-                    log::info!("[NET] {addr}> Client disconnected or error occurred.");
-                    break;
                 }
             }
         }
-        if now.elapsed() > std::time::Duration::from_millis(1000) {
-            log::info!("[NET] {addr}> Sent {counter} packets.");
-            counter = 0;
-            now = std::time::Instant::now();
+    }
+}
+
+/// Send `samples` to WebSocket client `addr` as one JSON message, returning
+/// its length
+async fn send_samples<S>(
+    outgoing: &mut S,
+    addr: std::net::SocketAddr,
+    samples: &[AccelData],
+    deadline: Duration,
+) -> io::Result<usize>
+where
+    S: Sink<Message, Error = tungstenite::Error> + Unpin,
+{
+    let msg = serde_json::to_string(samples)?;
+    log::debug!("[NET] {addr}> Sending data: {msg}");
+    let len = msg.len();
+    send_within(outgoing, Message::from(msg), deadline).await?;
+    Ok(len)
+}
+
+/// Close a WebSocket connection with a reason, best effort
+async fn close<S>(outgoing: &mut S, code: CloseCode, reason: &str, deadline: Duration)
+where
+    S: Sink<Message, Error = tungstenite::Error> + Unpin,
+{
+    let frame = CloseFrame {
+        code,
+        reason: reason.into(),
+    };
+    if let Err(e) = send_within(outgoing, Message::Close(Some(frame)), deadline).await {
+        log::debug!("[NET] Failed to send close frame: {e}");
+    }
+}
+
+/// Value of the parameter `name` of a WebSocket upgrade query
+fn query_param<'a>(query: Option<&'a str>, name: &str) -> Option<&'a str> {
+    query
+        .unwrap_or_default()
+        .split('&')
+        .find_map(|param| param.strip_prefix(name)?.strip_prefix('='))
+}
+
+/// Number of samples requested by the `replay` parameter of a WebSocket
+/// upgrade query, 0 if absent or invalid
+fn replay_request(query: Option<&str>) -> usize {
+    query_param(query, "replay")
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(0)
+}
+
+/// Check the token of a WebSocket upgrade request, from its `token` query
+/// parameter or `Authorization: Bearer` header
+fn upgrade_authorized(req: &Request, token: &str) -> bool {
+    let bearer = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    query_param(req.uri().query(), "token")
+        .into_iter()
+        .chain(bearer)
+        .any(|presented| token_matches(token, presented.trim().as_bytes()))
+}
+
+/// Handle a message from a client, forwarding commands from admin clients
+/// with `reply` to answer them on. Returns `false` if the message is
+/// malformed
+async fn handle_client_message(
+    addr: std::net::SocketAddr,
+    text: &str,
+    admin: &mut bool,
+    reply: &mpsc::Sender<Status>,
+    config: &ServerConfig,
+    sink: &Sender<AccelData>,
+    source: &mut ClientSource,
+) -> bool {
+    let commands = config.commands.as_ref();
+    match serde_json::from_str::<ClientMessage>(text) {
+        Ok(ClientMessage::Admin) => {
+            if commands.is_some() {
+                log::info!("[NET] {addr}> Client elevated to admin.");
+                *admin = true;
+            } else {
+                log::warn!("[NET] {addr}> Admin requested but commands are disabled.");
+            }
+        }
+        Ok(ClientMessage::Command(cmd)) => match commands {
+            Some(commands) if *admin => {
+                log::info!("[NET] {addr}> Forwarding command: {cmd:?}");
+                let request = CommandRequest {
+                    cmd,
+                    reply: reply.clone(),
+                };
+                if commands.send(request).await.is_err() {
+                    log::error!("[NET] {addr}> Command channel closed");
+                    let _ = reply.try_send(rejection(cmd, "commands unavailable".into()));
+                }
+            }
+            Some(_) => {
+                log::warn!("[NET] {addr}> Rejected command from non-admin client: {cmd:?}");
+                let _ = reply.try_send(rejection(cmd, "not an admin client".into()));
+            }
+            None => {
+                log::warn!("[NET] {addr}> Rejected command, commands are disabled: {cmd:?}");
+                let _ = reply.try_send(rejection(cmd, "commands are disabled".into()));
+            }
+        },
+        Ok(ClientMessage::Subscribe { channel }) => {
+            let sender = if channel == RAW_CHANNEL {
+                Some(sink)
+            } else {
+                config
+                    .channels
+                    .iter()
+                    .find(|(name, _)| *name == channel)
+                    .map(|(_, sender)| sender)
+            };
+            match sender {
+                Some(sender) => {
+                    log::info!("[NET] {addr}> Subscribed to channel {channel}.");
+                    *source = ClientSource::new(sender.subscribe(), config.client_buffer);
+                }
+                None => log::warn!("[NET] {addr}> Unknown channel {channel}"),
+            }
+        }
+        Err(e) => {
+            log::warn!("[NET] {addr}> Closing connection on malformed message: {e}");
+            return false;
         }
     }
+    true
 }
@@ -0,0 +1,181 @@
+//! Producers of samples for the servers
+use crate::AccelData;
+use std::{
+    future::Future,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+use tokio::{sync::broadcast::Sender, time::MissedTickBehavior};
+
+/// Producer of [`AccelData`] for a broadcast channel, e.g. a sensor or a
+/// simulation of one, so binaries and tests can run the servers from either
+pub trait SampleSource: Send + 'static {
+    /// Send samples on `sink` until `running` is cleared
+    fn run(
+        self,
+        sink: Sender<AccelData>,
+        running: Arc<AtomicBool>,
+    ) -> impl Future<Output = ()> + Send + 'static;
+}
+
+/// Deterministic source of sensor `idx`: one sample per `interval`, with
+/// nominal `gap` and `time` and the axes computed by `generate` from the
+/// sample time in seconds.
+///
+/// Samples are paced by a tokio interval that catches up after a late tick,
+/// so the sample rate matches the nominal times on average.
+pub struct SyntheticSource<F> {
+    idx: u32,
+    interval: Duration,
+    generate: F,
+}
+
+impl<F> SyntheticSource<F>
+where
+    F: FnMut(f32) -> [f32; 3] + Send + 'static,
+{
+    /// Create a source of sensor `idx` sampling `generate` every `interval`
+    pub fn new(idx: u32, interval: Duration, generate: F) -> Self {
+        Self {
+            idx,
+            interval,
+            generate,
+        }
+    }
+}
+
+impl<F> SampleSource for SyntheticSource<F>
+where
+    F: FnMut(f32) -> [f32; 3] + Send + 'static,
+{
+    async fn run(mut self, sink: Sender<AccelData>, running: Arc<AtomicBool>) {
+        log::info!("Starting synthetic data generation for index {}", self.idx);
+        let gap = self.interval.as_micros() as u32;
+        let mut ticker = tokio::time::interval(self.interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Burst);
+        let mut count = 0u64;
+        while running.load(Ordering::Relaxed) {
+            ticker.tick().await;
+            let time = count * u64::from(gap);
+            let [x, y, z] = (self.generate)((time as f64 / 1e6) as f32);
+            let data = AccelData {
+                idx: self.idx,
+                seq: count as u32,
+                gap,
+                time,
+                x,
+                y,
+                z,
+                temp: None,
+                fresh: None,
+            };
+            if sink.receiver_count() > 0 && sink.send(data).is_err() {
+                log::error!("Failed to send synthetic data");
+            }
+            count += 1;
+        }
+    }
+}
+
+/// Reproducible source of sensor `idx`: `count` samples of uniform noise in
+/// [-1, 1) on every axis, drawn from a generator seeded with `seed`, one per
+/// `interval` with nominal `gap` and `time`.
+///
+/// The same seed always gives the same samples, listed by
+/// [`SeededSource::samples`], so tests can assert the exact output of the
+/// servers. Sending starts once the sink has a receiver, so no sample is
+/// lost to a client that is still connecting.
+///
+/// ```
+/// use accel_data::SeededSource;
+/// use std::time::Duration;
+///
+/// let a = SeededSource::new(0, 42, 100, Duration::from_millis(1));
+/// let b = SeededSource::new(0, 42, 100, Duration::from_millis(1));
+/// let c = SeededSource::new(0, 43, 100, Duration::from_millis(1));
+/// let bytes = |source: &SeededSource| -> Vec<_> { source.samples().map(|d| d.as_bytes()).collect() };
+/// assert_eq!(bytes(&a), bytes(&b));
+/// assert_ne!(bytes(&a), bytes(&c));
+/// let last = a.samples().last().unwrap();
+/// assert_eq!(({ last.seq }, { last.gap }, { last.time }), (99, 1000, 99_000));
+/// assert!(a.samples().all(|d| (-1.0..1.0).contains(&{ d.x })));
+/// ```
+pub struct SeededSource {
+    idx: u32,
+    seed: u64,
+    count: u64,
+    interval: Duration,
+}
+
+impl SeededSource {
+    /// Create a source of `count` samples of sensor `idx` from `seed`, one
+    /// every `interval`
+    pub fn new(idx: u32, seed: u64, count: u64, interval: Duration) -> Self {
+        Self {
+            idx,
+            seed,
+            count,
+            interval,
+        }
+    }
+
+    /// The samples the source sends, in order
+    pub fn samples(&self) -> impl Iterator<Item = AccelData> + use<> {
+        let (idx, gap) = (self.idx, self.interval.as_micros() as u32);
+        let mut state = self.seed;
+        (0..self.count).map(move |n| {
+            let mut uniform = || {
+                // splitmix64, then the top 24 bits as a fraction
+                state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+                let mut z = state;
+                z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+                z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+                z ^= z >> 31;
+                (z >> 40) as f32 / (1u32 << 24) as f32 * 2.0 - 1.0
+            };
+            let (x, y, z) = (uniform(), uniform(), uniform());
+            AccelData {
+                idx,
+                seq: n as u32,
+                gap,
+                time: n * u64::from(gap),
+                x,
+                y,
+                z,
+                temp: None,
+                fresh: None,
+            }
+        })
+    }
+}
+
+impl SampleSource for SeededSource {
+    async fn run(self, sink: Sender<AccelData>, running: Arc<AtomicBool>) {
+        log::info!(
+            "Starting seeded data generation for index {} (seed {}, {} samples)",
+            self.idx,
+            self.seed,
+            self.count
+        );
+        while sink.receiver_count() == 0 {
+            if !running.load(Ordering::Relaxed) {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        let mut ticker = tokio::time::interval(self.interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Burst);
+        for data in self.samples() {
+            if !running.load(Ordering::Relaxed) {
+                break;
+            }
+            ticker.tick().await;
+            if sink.send(data).is_err() {
+                log::error!("Failed to send seeded data");
+            }
+        }
+    }
+}
@@ -0,0 +1,284 @@
+//! Spectral summaries of the samples
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "fft")]
+use {crate::AccelData, std::collections::HashMap, std::collections::VecDeque};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// Dominant frequencies of the latest window of samples of sensor `idx`,
+/// sent as a [`crate::Status::Spectrum`] record
+pub struct Spectrum {
+    /// Sensor index
+    pub idx: u32,
+    /// [`AccelData::time`](crate::AccelData::time) of the last sample of the
+    /// window
+    pub time: u64,
+    /// Width of a frequency bin in Hz, the resolution of the peak
+    /// frequencies
+    pub bin_hz: f32,
+    /// Peaks of the x-axis as (frequency in Hz, amplitude in sample units),
+    /// strongest first
+    pub x: Vec<(f32, f32)>,
+    /// Peaks of the y-axis
+    pub y: Vec<(f32, f32)>,
+    /// Peaks of the z-axis
+    pub z: Vec<(f32, f32)>,
+}
+
+/// Largest window of a [`SpectrumAnalyzer`] in samples, 16 s at 4 kHz
+#[cfg(feature = "fft")]
+pub const MAX_FFT_SIZE: usize = 65536;
+
+/// Running FFT of each sensor, reduced to the strongest peaks per axis.
+///
+/// Every `hop` samples of a sensor, the last `size` samples of each axis have
+/// their mean (gravity) removed, are weighted with a periodic Hann window
+/// `0.5 - 0.5 cos(2πn / size)` and transformed. Consecutive windows overlap
+/// by `size - hop` samples; at 50% overlap every sample is
+/// weighted equally across windows. Amplitudes are corrected for the window
+/// gain, so a sine of amplitude `a` centered on a bin is reported as `a`, and
+/// up to 15% lower between bins. The sample rate is taken from the median
+/// `gap` of the window.
+///
+/// The transform is a built-in iterative radix-2 FFT rather than `rustfft`
+/// or `microfft`: window sizes are powers of two anyway, and the `fft`
+/// feature stays free of dependencies.
+///
+/// ```
+/// use accel_data::{AccelData, SpectrumAnalyzer};
+///
+/// let mut fft = SpectrumAnalyzer::new(256, 0.5, 2);
+/// assert_eq!((fft.size(), fft.hop()), (256, 128));
+/// // at 1000 Hz, a bin is 1000 / 256 Hz: 0.5 g at bin 13 and 0.2 g at bin 31
+/// let bin = 1000.0 / 256.0;
+/// let signal = |t: f32| {
+///     let tau = std::f32::consts::TAU;
+///     1.0 + 0.5 * (tau * 13.0 * bin * t).sin() + 0.2 * (tau * 31.0 * bin * t).cos()
+/// };
+/// let spectra: Vec<_> = (0..1024u32)
+///     .filter_map(|n| {
+///         let z = signal(n as f32 / 1000.0);
+///         fft.push(AccelData { idx: 0, seq: n, gap: 1000, z, ..Default::default() })
+///     })
+///     .collect();
+/// // one window after 256 samples, then one every 128
+/// assert_eq!(spectra.len(), 7);
+/// let spectrum = &spectra[0];
+/// assert!((spectrum.bin_hz - bin).abs() < 1e-3);
+/// let [(f1, a1), (f2, a2)] = spectrum.z[..] else { panic!() };
+/// assert!((f1 - 13.0 * bin).abs() < 1e-3 && (a1 - 0.5).abs() < 1e-3);
+/// assert!((f2 - 31.0 * bin).abs() < 1e-3 && (a2 - 0.2).abs() < 1e-3);
+/// // the constant x and y axes have no peaks
+/// assert!(spectrum.x.is_empty() && spectrum.y.is_empty());
+/// ```
+#[cfg(feature = "fft")]
+#[derive(Debug, Clone)]
+pub struct SpectrumAnalyzer {
+    size: usize,
+    hop: usize,
+    peaks: usize,
+    window: Vec<f32>,
+    /// `exp(-2πik / size)` for `k < size / 2`
+    twiddles: Vec<(f32, f32)>,
+    state: HashMap<u32, SpectrumState>,
+}
+
+#[cfg(feature = "fft")]
+#[derive(Debug, Clone, Default)]
+struct SpectrumState {
+    /// Latest samples, x, y and z, and their gaps
+    samples: VecDeque<([f32; 3], u32)>,
+    /// Samples since the last spectrum
+    pending: usize,
+}
+
+#[cfg(feature = "fft")]
+impl SpectrumAnalyzer {
+    /// Analyzer of windows of `size` samples, rounded up to a power of two of
+    /// at least 8 and at most [`MAX_FFT_SIZE`], overlapping by the fraction
+    /// `overlap` in [0, 1), reporting up to `peaks` peaks per axis
+    ///
+    /// ```
+    /// use accel_data::{MAX_FFT_SIZE, SpectrumAnalyzer};
+    ///
+    /// assert_eq!(SpectrumAnalyzer::new(100, 0.5, 1).size(), 128);
+    /// assert_eq!(SpectrumAnalyzer::new(usize::MAX, 0.5, 1).size(), MAX_FFT_SIZE);
+    /// ```
+    pub fn new(size: usize, overlap: f32, peaks: usize) -> Self {
+        let size = size.clamp(8, MAX_FFT_SIZE).next_power_of_two();
+        let overlap = if overlap.is_finite() {
+            overlap.clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let hop = ((size as f32 * (1.0 - overlap)).round() as usize).clamp(1, size);
+        let tau = std::f64::consts::TAU;
+        let window = (0..size)
+            .map(|n| (0.5 - 0.5 * (tau * n as f64 / size as f64).cos()) as f32)
+            .collect();
+        Self {
+            size,
+            hop,
+            peaks,
+            window,
+            twiddles: twiddles(size),
+            state: HashMap::new(),
+        }
+    }
+
+    /// Samples per window
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Samples between consecutive windows
+    pub fn hop(&self) -> usize {
+        self.hop
+    }
+
+    /// Add a sample, returning the spectrum of its sensor once a window is
+    /// complete and every `hop` samples after
+    pub fn push(&mut self, data: AccelData) -> Option<Spectrum> {
+        let idx = data.idx;
+        let state = self.state.entry(idx).or_default();
+        if state.samples.len() == self.size {
+            state.samples.pop_front();
+        }
+        state
+            .samples
+            .push_back(([data.x, data.y, data.z], data.gap));
+        state.pending += 1;
+        if state.samples.len() < self.size || state.pending < self.hop {
+            return None;
+        }
+        state.pending = 0;
+        let mut gaps: Vec<u32> = state.samples.iter().map(|(_, gap)| *gap).collect();
+        gaps.sort_unstable();
+        let gap = gaps[gaps.len() / 2].max(1);
+        let bin_hz = 1e6 / (gap as f32 * self.size as f32);
+        let state = &self.state[&idx];
+        let [x, y, z] = [0, 1, 2].map(|axis| self.peaks_of(state, axis, bin_hz));
+        Some(Spectrum {
+            idx,
+            time: data.time,
+            bin_hz,
+            x,
+            y,
+            z,
+        })
+    }
+
+    /// Strongest local maxima of the amplitude spectrum of one axis
+    fn peaks_of(&self, state: &SpectrumState, axis: usize, bin_hz: f32) -> Vec<(f32, f32)> {
+        let values = state.samples.iter().map(|(values, _)| values[axis]);
+        let mean = values.clone().sum::<f32>() / self.size as f32;
+        let mut re: Vec<f32> = values
+            .zip(&self.window)
+            .map(|(value, w)| (value - mean) * w)
+            .collect();
+        let mut im = vec![0.0; self.size];
+        fft(&mut re, &mut im, &self.twiddles);
+        let gain = 2.0 / self.window.iter().sum::<f32>();
+        let amplitude: Vec<f32> = (0..=self.size / 2)
+            .map(|k| re[k].hypot(im[k]) * gain)
+            .collect();
+        // rounding noise of constant axes is not a peak
+        let floor = 1e-6 * amplitude.iter().fold(1.0f32, |m, a| m.max(*a));
+        let mut peaks: Vec<(f32, f32)> = (1..self.size / 2)
+            .filter(|&k| {
+                amplitude[k] > floor
+                    && amplitude[k] > amplitude[k - 1]
+                    && amplitude[k] >= amplitude[k + 1]
+            })
+            .map(|k| (k as f32 * bin_hz, amplitude[k]))
+            .collect();
+        peaks.sort_by(|a, b| b.1.total_cmp(&a.1));
+        peaks.truncate(self.peaks);
+        peaks
+    }
+}
+
+/// `exp(-2πik / size)` for `k < size / 2`, the twiddle factors of [`fft`]
+#[cfg(feature = "fft")]
+fn twiddles(size: usize) -> Vec<(f32, f32)> {
+    (0..size / 2)
+        .map(|k| {
+            let phase = -std::f64::consts::TAU * k as f64 / size as f64;
+            (phase.cos() as f32, phase.sin() as f32)
+        })
+        .collect()
+}
+
+/// In-place iterative radix-2 FFT of `re + i im`, of a power of two length
+/// with `twiddles` for that length
+#[cfg(feature = "fft")]
+fn fft(re: &mut [f32], im: &mut [f32], twiddles: &[(f32, f32)]) {
+    let n = re.len();
+    let bits = n.trailing_zeros();
+    for i in 0..n {
+        let j = i.reverse_bits() >> (usize::BITS - bits);
+        if j > i {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+    let mut len = 2;
+    while len <= n {
+        let step = n / len;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (wr, wi) = twiddles[k * step];
+                let (a, b) = (start + k, start + k + len / 2);
+                let tr = re[b] * wr - im[b] * wi;
+                let ti = re[b] * wi + im[b] * wr;
+                re[b] = re[a] - tr;
+                im[b] = im[a] - ti;
+                re[a] += tr;
+                im[a] += ti;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+#[cfg(all(test, feature = "fft"))]
+mod tests {
+    use super::*;
+
+    /// Direct DFT of `re + i im` in double precision
+    fn dft(re: &[f32], im: &[f32]) -> Vec<(f64, f64)> {
+        let n = re.len();
+        (0..n)
+            .map(|k| {
+                (0..n).fold((0.0, 0.0), |(sr, si), j| {
+                    let phase = -std::f64::consts::TAU * (j * k % n) as f64 / n as f64;
+                    let (wr, wi) = (phase.cos(), phase.sin());
+                    let (xr, xi) = (f64::from(re[j]), f64::from(im[j]));
+                    (sr + xr * wr - xi * wi, si + xr * wi + xi * wr)
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fft_matches_direct_dft() {
+        // deterministic pseudo-random input in [-1, 1)
+        let mut seed = 0x2545_f491_u32;
+        let mut next = move || {
+            seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (seed >> 8) as f32 / (1 << 23) as f32 - 1.0
+        };
+        for size in [8, 16, 32, 128, 512, 2048] {
+            let re: Vec<f32> = (0..size).map(|_| next()).collect();
+            let im: Vec<f32> = (0..size).map(|_| next()).collect();
+            let expected = dft(&re, &im);
+            let (mut fre, mut fim) = (re.clone(), im.clone());
+            fft(&mut fre, &mut fim, &twiddles(size));
+            // single precision rounding grows with log2(size) per output
+            let tolerance = 1e-5 * size as f64;
+            for (k, (er, ei)) in expected.into_iter().enumerate() {
+                let err = (f64::from(fre[k]) - er).hypot(f64::from(fim[k]) - ei);
+                assert!(err < tolerance, "size {size}, bin {k}: error {err}");
+            }
+        }
+    }
+}
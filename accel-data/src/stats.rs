@@ -0,0 +1,339 @@
+use crate::AccelData;
+use std::{
+    collections::BTreeMap,
+    fmt,
+    time::{Duration, Instant},
+};
+
+/// Counts events and bytes, reporting their rates once per interval
+#[derive(Debug, Clone)]
+pub struct RateMeter {
+    interval: Duration,
+    start: Instant,
+    count: u64,
+    bytes: u64,
+}
+
+/// Rates measured by a [`RateMeter`] over one interval.
+///
+/// Displayed as `key=value` pairs for log shippers, e.g.
+/// `count_per_s=1000.000 bytes_per_s=20000.000`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Rates {
+    /// Events per second
+    pub count_per_s: f32,
+    /// Bytes per second
+    pub bytes_per_s: f32,
+}
+
+impl RateMeter {
+    /// Create a meter reporting every `interval`
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            start: Instant::now(),
+            count: 0,
+            bytes: 0,
+        }
+    }
+
+    /// Record `count` events of `bytes` total size, returning the rates if
+    /// the interval elapsed
+    pub fn record(&mut self, count: u64, bytes: u64) -> Option<Rates> {
+        self.count += count;
+        self.bytes += bytes;
+        let elapsed = self.start.elapsed();
+        if elapsed < self.interval {
+            return None;
+        }
+        let secs = elapsed.as_secs_f32();
+        let rates = Rates {
+            count_per_s: self.count as f32 / secs,
+            bytes_per_s: self.bytes as f32 / secs,
+        };
+        self.start = Instant::now();
+        self.count = 0;
+        self.bytes = 0;
+        Some(rates)
+    }
+}
+
+impl fmt::Display for Rates {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "count_per_s={:.3} bytes_per_s={:.3}",
+            self.count_per_s, self.bytes_per_s
+        )
+    }
+}
+
+/// Values below this are counted exactly
+const HIST_LINEAR: u32 = 16;
+/// Sub-buckets per power of two above [`HIST_LINEAR`], so values are
+/// resolved to within 1/16 (6.25%)
+const HIST_SUB_BITS: u32 = 4;
+const HIST_BUCKETS: usize = (HIST_LINEAR + (32 - HIST_SUB_BITS) * HIST_LINEAR) as usize;
+
+/// Fixed-size histogram of `u32` values with HDR-style log-linear buckets:
+/// exact below 16, then 16 buckets per power of two. Recording never
+/// allocates.
+///
+/// Percentiles report the upper bound of their bucket, capped at the
+/// maximum recorded value.
+///
+/// ```
+/// use accel_data::Histogram;
+///
+/// let mut hist = Histogram::new();
+/// for gap in (0..98).map(|_| 1000).chain([1500, 9000]) {
+///     hist.record(gap);
+/// }
+/// let p = hist.percentiles();
+/// assert!((1000..1064).contains(&p.p50));
+/// assert!((1500..1536).contains(&p.p99));
+/// assert_eq!((p.max, hist.count()), (9000, 100));
+/// hist.reset();
+/// assert_eq!(hist.percentiles().max, 0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Histogram {
+    counts: [u32; HIST_BUCKETS],
+    count: u64,
+    max: u32,
+}
+
+/// Summary of a [`Histogram`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct Percentiles {
+    /// Median
+    pub p50: u32,
+    /// 99th percentile
+    pub p99: u32,
+    /// Largest value
+    pub max: u32,
+}
+
+impl Histogram {
+    /// Create an empty histogram
+    pub fn new() -> Self {
+        Self {
+            counts: [0; HIST_BUCKETS],
+            count: 0,
+            max: 0,
+        }
+    }
+
+    fn bucket(value: u32) -> usize {
+        if value < HIST_LINEAR {
+            return value as usize;
+        }
+        let shift = 31 - value.leading_zeros() - HIST_SUB_BITS;
+        ((shift + 1) * HIST_LINEAR + ((value >> shift) - HIST_LINEAR)) as usize
+    }
+
+    /// Largest value counted in `bucket`
+    fn upper_bound(bucket: usize) -> u32 {
+        let bucket = bucket as u32;
+        if bucket < HIST_LINEAR {
+            return bucket;
+        }
+        let shift = bucket / HIST_LINEAR - 1;
+        let sub = bucket % HIST_LINEAR + HIST_LINEAR;
+        ((sub as u64 + 1) << shift)
+            .saturating_sub(1)
+            .min(u32::MAX as u64) as u32
+    }
+
+    /// Count a value
+    pub fn record(&mut self, value: u32) {
+        self.counts[Self::bucket(value)] += 1;
+        self.count += 1;
+        self.max = self.max.max(value);
+    }
+
+    /// Number of values recorded
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Value at or below which the fraction `q` (0 to 1) of the recorded
+    /// values fall, 0 if empty
+    pub fn quantile(&self, q: f64) -> u32 {
+        let rank = ((q.clamp(0.0, 1.0) * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (bucket, &n) in self.counts.iter().enumerate() {
+            seen += n as u64;
+            if seen >= rank {
+                return Self::upper_bound(bucket).min(self.max);
+            }
+        }
+        0
+    }
+
+    /// Median, 99th percentile and maximum
+    pub fn percentiles(&self) -> Percentiles {
+        Percentiles {
+            p50: self.quantile(0.5),
+            p99: self.quantile(0.99),
+            max: self.max,
+        }
+    }
+
+    /// Forget all values
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Link quality of one sensor's stream, as tracked by [`StreamStats`].
+///
+/// Displayed as `key=value` pairs, e.g.
+/// `idx=0 received=1000 gaps=1 lost=3 rate=999.000`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LinkStats {
+    /// Sensor index
+    pub idx: u32,
+    /// Samples received
+    pub received: u64,
+    /// Jumps in [`AccelData::seq`]
+    pub gaps: u64,
+    /// Samples missing in those jumps
+    pub lost: u64,
+    /// Samples received per second, from the first to the latest sample
+    pub rate: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Stream {
+    first: Instant,
+    latest: Instant,
+    seq: u32,
+    received: u64,
+    gaps: u64,
+    lost: u64,
+}
+
+/// Client-side link monitor: fed the decoded samples, counts received and
+/// dropped samples per sensor from their [`AccelData::seq`].
+///
+/// A jump forward in `seq` (modulo 2³²) is a gap. A jump backward by less
+/// than half the sequence space, as after a daemon restart, starts the count
+/// over from the new `seq` without recording a gap.
+///
+/// ```
+/// use accel_data::{AccelData, StreamStats};
+/// use std::time::{Duration, Instant};
+///
+/// let mut stats = StreamStats::new();
+/// let start = Instant::now();
+/// let sample = |seq| AccelData { idx: 1, seq, ..Default::default() };
+/// // 3 is lost across the wraparound
+/// for (i, seq) in [u32::MAX - 1, u32::MAX, 0, 1, 2, 4].into_iter().enumerate() {
+///     stats.record_at(&sample(seq), start + Duration::from_millis(i as u64));
+/// }
+/// let link = stats.get(1).unwrap();
+/// assert_eq!((link.received, link.gaps, link.lost), (6, 1, 1));
+/// assert_eq!(link.rate, 1000.0);
+/// assert!(stats.get(0).is_none());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct StreamStats {
+    streams: BTreeMap<u32, Stream>,
+}
+
+impl StreamStats {
+    /// Create an empty monitor
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a sample received now
+    pub fn record(&mut self, data: &AccelData) {
+        self.record_at(data, Instant::now());
+    }
+
+    /// Record a sample received at `now`
+    pub fn record_at(&mut self, data: &AccelData, now: Instant) {
+        let seq = data.seq;
+        let stream = self.streams.entry(data.idx).or_insert(Stream {
+            first: now,
+            latest: now,
+            seq: seq.wrapping_sub(1),
+            received: 0,
+            gaps: 0,
+            lost: 0,
+        });
+        let missing = seq.wrapping_sub(stream.seq).wrapping_sub(1);
+        if missing != 0 && missing < u32::MAX / 2 {
+            stream.gaps += 1;
+            stream.lost += missing as u64;
+        }
+        stream.seq = seq;
+        stream.latest = now;
+        stream.received += 1;
+    }
+
+    /// Link quality of sensor `idx`, `None` if none of its samples were seen
+    pub fn get(&self, idx: u32) -> Option<LinkStats> {
+        self.streams.get(&idx).map(|stream| link_stats(idx, stream))
+    }
+
+    /// Link quality of every sensor seen, by index
+    pub fn iter(&self) -> impl Iterator<Item = LinkStats> + '_ {
+        self.streams
+            .iter()
+            .map(|(&idx, stream)| link_stats(idx, stream))
+    }
+
+    /// Forget all sensors
+    pub fn clear(&mut self) {
+        self.streams.clear();
+    }
+}
+
+fn link_stats(idx: u32, stream: &Stream) -> LinkStats {
+    let secs = stream.latest.duration_since(stream.first).as_secs_f32();
+    // the first sample opens the measurement window
+    let rate = if secs > 0.0 {
+        (stream.received - 1) as f32 / secs
+    } else {
+        0.0
+    };
+    LinkStats {
+        idx,
+        received: stream.received,
+        gaps: stream.gaps,
+        lost: stream.lost,
+        rate,
+    }
+}
+
+impl fmt::Display for LinkStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "idx={} received={} gaps={} lost={} rate={:.3}",
+            self.idx, self.received, self.gaps, self.lost, self.rate
+        )
+    }
+}
+
+/// One line per sensor
+impl fmt::Display for StreamStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, link) in self.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{link}")?;
+        }
+        Ok(())
+    }
+}
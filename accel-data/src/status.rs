@@ -0,0 +1,130 @@
+use crate::{OrientationChange, SensorInfo, Spectrum, Summary};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+/// Status record, sent to clients separately from the data records
+pub enum Status {
+    /// Periodic health report of sensor `idx`
+    Health {
+        /// Sensor Index
+        idx: u32,
+        /// Die temperature in °C, if enabled
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        temperature: Option<f32>,
+    },
+    /// Current configuration of sensor `idx`, sent periodically if enabled
+    /// so clients joining late or sensors reconfigured after the
+    /// [`crate::Handshake`] still decode correctly
+    Config(SensorInfo),
+    /// Dominant frequencies of sensor `idx`, sent at the window rate of a
+    /// spectrum analyzer if enabled
+    Spectrum(Spectrum),
+    /// Min, max and mean of each axis of sensor `idx` over a window, sent
+    /// once per window if enabled
+    Summary(Summary),
+    /// Gravity direction of sensor `idx` changed by more than the threshold
+    /// of a [`crate::TiltDetector`], sent as it happens if enabled
+    OrientationChanged(OrientationChange),
+    /// Output data rate of sensor `idx` lowered because the host could not
+    /// keep up, sent as it happens. The rate in the [`crate::Handshake`] is
+    /// stale afterwards
+    RateReduced {
+        /// Sensor index
+        idx: u32,
+        /// Previous output data rate in Hz
+        from: f32,
+        /// New output data rate in Hz
+        to: f32,
+        /// Sample rate measured before the change in Hz
+        measured: f32,
+    },
+    /// Problem with sensor `idx` or with the stream of this client, sent if
+    /// enabled so clients can tell why samples are missing
+    Error {
+        /// Sensor index, `None` for problems of the stream, e.g. skipped
+        /// samples of every sensor
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        idx: Option<u32>,
+        /// What went wrong
+        kind: ErrorKind,
+        /// Occurrences since the last record of this kind
+        count: u32,
+        /// [`crate::AccelData::time`] of the latest occurrence, 0 if unknown
+        #[serde(default)]
+        time: u64,
+        /// Description of the latest occurrence
+        #[serde(default, skip_serializing_if = "String::is_empty")]
+        message: String,
+    },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// Kind of a [`Status::Error`]
+pub enum ErrorKind {
+    /// Reading samples from the sensor failed; each failure is a missing
+    /// sample
+    ReadFailed,
+    /// The sensor stopped producing samples and was restarted
+    Restarted,
+    /// Restarting a stalled sensor failed, it is retried later
+    RestartFailed,
+    /// The client fell behind and `count` samples were skipped for it
+    Overrun,
+    /// A [`crate::Command`] of this client was rejected or failed to apply
+    CommandFailed,
+}
+
+impl Status {
+    /// Encode as JSON, the payload of [`crate::FrameKind::Status`] frames and
+    /// the body of WebSocket status messages
+    ///
+    /// ```
+    /// use accel_data::{ErrorKind, SensorInfo, Spectrum, Status};
+    ///
+    /// let status = Status::Health { idx: 0, temperature: Some(25.5) };
+    /// assert_eq!(status.to_json(), r#"{"status":"health","idx":0,"temperature":25.5}"#);
+    ///
+    /// let info = SensorInfo { idx: 1, odr: 31.25, range: 8.192, hpf: 0, scale: 1.6e-5 };
+    /// assert_eq!(
+    ///     Status::Config(info).to_json(),
+    ///     r#"{"status":"config","idx":1,"odr":31.25,"range":8.192,"hpf":0,"scale":0.000016}"#
+    /// );
+    ///
+    /// let spectrum = Spectrum {
+    ///     idx: 0,
+    ///     time: 256_000,
+    ///     bin_hz: 3.90625,
+    ///     x: vec![(50.78125, 0.5)],
+    ///     y: vec![],
+    ///     z: vec![],
+    /// };
+    /// assert_eq!(
+    ///     Status::Spectrum(spectrum).to_json(),
+    ///     r#"{"status":"spectrum","idx":0,"time":256000,"bin_hz":3.90625,"x":[[50.78125,0.5]],"y":[],"z":[]}"#
+    /// );
+    ///
+    /// let reduced = Status::RateReduced { idx: 0, from: 4000.0, to: 2000.0, measured: 3100.0 };
+    /// assert_eq!(
+    ///     reduced.to_json(),
+    ///     r#"{"status":"rate_reduced","idx":0,"from":4000.0,"to":2000.0,"measured":3100.0}"#
+    /// );
+    ///
+    /// let error = Status::Error {
+    ///     idx: Some(0),
+    ///     kind: ErrorKind::ReadFailed,
+    ///     count: 3,
+    ///     time: 1_500_000,
+    ///     message: "SPI transfer failed".into(),
+    /// };
+    /// assert_eq!(
+    ///     error.to_json(),
+    ///     r#"{"status":"error","idx":0,"kind":"read_failed","count":3,"time":1500000,"message":"SPI transfer failed"}"#
+    /// );
+    /// ```
+    pub fn to_json(&self) -> String {
+        // cannot fail: all fields are plain numbers and strings
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
@@ -0,0 +1,57 @@
+//! In-process consumers of samples
+use crate::AccelData;
+use futures_util::{Stream, stream};
+use std::fmt;
+use tokio::sync::broadcast::{Sender, error::RecvError};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// Gap in a [`sample_stream`]
+pub enum StreamError {
+    /// The consumer fell behind the producer, this many samples were dropped
+    /// before the next one
+    Lagged(u64),
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StreamError::Lagged(n) => write!(f, "lagged behind, skipped {n} samples"),
+        }
+    }
+}
+
+impl std::error::Error for StreamError {}
+
+/// Stream of the samples sent on `sink` from now on, for consumers in the
+/// same process.
+///
+/// Samples lost because the consumer lagged behind the channel capacity are
+/// reported as one [`StreamError::Lagged`] item before the next sample, and
+/// the stream ends once all senders are dropped.
+///
+/// ```
+/// use accel_data::{AccelData, StreamError, sample_stream};
+/// use futures_util::StreamExt;
+///
+/// let (sink, _) = tokio::sync::broadcast::channel(16);
+/// let samples = sample_stream(&sink);
+/// sink.send(AccelData { seq: 1, ..Default::default() }).unwrap();
+/// drop(sink);
+/// let items: Vec<Result<AccelData, StreamError>> = tokio::runtime::Builder::new_current_thread()
+///     .build()
+///     .unwrap()
+///     .block_on(samples.collect());
+/// assert!(matches!(items[..], [Ok(data)] if { data.seq } == 1));
+/// ```
+pub fn sample_stream(
+    sink: &Sender<AccelData>,
+) -> impl Stream<Item = Result<AccelData, StreamError>> + Send + use<> {
+    stream::unfold(sink.subscribe(), |mut source| async move {
+        let item = match source.recv().await {
+            Ok(data) => Ok(data),
+            Err(RecvError::Lagged(n)) => Err(StreamError::Lagged(n)),
+            Err(RecvError::Closed) => return None,
+        };
+        Some((item, source))
+    })
+}
@@ -0,0 +1,148 @@
+//! Per-window statistics of the samples
+use crate::AccelData;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, time::Duration};
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+/// Statistics of one axis over a window
+pub struct AxisSummary {
+    /// Smallest value
+    pub min: f32,
+    /// Largest value
+    pub max: f32,
+    /// Mean value
+    pub mean: f32,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+/// Statistics of the samples of sensor `idx` in one window, sent as a
+/// [`crate::Status::Summary`] record
+pub struct Summary {
+    /// Sensor index
+    pub idx: u32,
+    /// Start of the window in [`AccelData::time`] microseconds, a multiple of
+    /// the window length
+    pub start: u64,
+    /// Samples in the window
+    pub count: u32,
+    /// Statistics of the x-axis
+    pub x: AxisSummary,
+    /// Statistics of the y-axis
+    pub y: AxisSummary,
+    /// Statistics of the z-axis
+    pub z: AxisSummary,
+}
+
+/// Running min, max and mean of each axis of each sensor over consecutive
+/// windows of [`AccelData::time`].
+///
+/// Windows are aligned to multiples of the window length since the
+/// synchronization point of the source, so the windows of all sensors line
+/// up. A window is summarized when the first sample of a later window of
+/// its sensor arrives; windows without samples are skipped.
+///
+/// ```
+/// use accel_data::{AccelData, Summarizer};
+/// use std::time::Duration;
+///
+/// let mut summarizer = Summarizer::new(Duration::from_millis(10));
+/// // 1 kHz, so ten samples per window
+/// let summaries: Vec<_> = (0..25u32)
+///     .filter_map(|n| {
+///         let time = 1000 * u64::from(n);
+///         let z = n as f32;
+///         summarizer.push(AccelData { idx: 0, seq: n, time, z, ..Default::default() })
+///     })
+///     .collect();
+/// // the third window is not complete yet
+/// assert_eq!(summaries.len(), 2);
+/// let second = summaries[1];
+/// assert_eq!((second.start, second.count), (10_000, 10));
+/// assert_eq!((second.z.min, second.z.max, second.z.mean), (10.0, 19.0, 14.5));
+/// assert_eq!((second.x.min, second.x.max), (0.0, 0.0));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Summarizer {
+    /// Window length in microseconds
+    window: u64,
+    /// Window index and statistics so far of each sensor
+    state: HashMap<u32, (u64, SummaryState)>,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct SummaryState {
+    count: u32,
+    min: [f32; 3],
+    max: [f32; 3],
+    sum: [f64; 3],
+}
+
+impl SummaryState {
+    fn new() -> Self {
+        Self {
+            count: 0,
+            min: [f32::INFINITY; 3],
+            max: [f32::NEG_INFINITY; 3],
+            sum: [0.0; 3],
+        }
+    }
+
+    fn record(&mut self, values: [f32; 3]) {
+        self.count += 1;
+        for (axis, value) in values.into_iter().enumerate() {
+            self.min[axis] = self.min[axis].min(value);
+            self.max[axis] = self.max[axis].max(value);
+            self.sum[axis] += f64::from(value);
+        }
+    }
+
+    fn summary(&self, idx: u32, start: u64) -> Summary {
+        let [x, y, z] = [0, 1, 2].map(|axis| AxisSummary {
+            min: self.min[axis],
+            max: self.max[axis],
+            mean: (self.sum[axis] / f64::from(self.count)) as f32,
+        });
+        Summary {
+            idx,
+            start,
+            count: self.count,
+            x,
+            y,
+            z,
+        }
+    }
+}
+
+impl Summarizer {
+    /// Summarizer over windows of `window`, at least 1 µs
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window: (window.as_micros() as u64).max(1),
+            state: HashMap::new(),
+        }
+    }
+
+    /// Window length
+    pub fn window(&self) -> Duration {
+        Duration::from_micros(self.window)
+    }
+
+    /// Add a sample, returning the summary of the previous window of its
+    /// sensor if the sample starts a new one
+    pub fn push(&mut self, data: AccelData) -> Option<Summary> {
+        let window = data.time / self.window;
+        let idx = data.idx;
+        let (current, state) = self
+            .state
+            .entry(idx)
+            .or_insert_with(|| (window, SummaryState::new()));
+        let done = (*current != window).then(|| {
+            let summary = state.summary(idx, *current * self.window);
+            *current = window;
+            *state = SummaryState::new();
+            summary
+        });
+        state.record([data.x, data.y, data.z]);
+        done
+    }
+}
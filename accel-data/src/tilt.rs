@@ -0,0 +1,142 @@
+//! Events of the gravity direction changing
+use crate::AccelData;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, time::Duration};
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+/// Change of the gravity direction of sensor `idx`, sent as a
+/// [`crate::Status::OrientationChanged`] record
+pub struct OrientationChange {
+    /// Sensor index
+    pub idx: u32,
+    /// [`AccelData::time`] of the sample that completed the change
+    pub time: u64,
+    /// Angle between the previous and the new direction in degrees
+    pub angle: f32,
+    /// Previous gravity direction, a unit vector in sensor axes
+    pub from: [f32; 3],
+    /// New gravity direction, a unit vector in sensor axes
+    pub to: [f32; 3],
+}
+
+/// Detector of inclination changes: tracks the direction of gravity of each
+/// sensor and reports when it moved by more than a threshold angle since the
+/// last report, instead of streaming every sample.
+///
+/// Gravity is estimated by a first order low pass filter over the samples
+/// with time constant `smoothing`, weighted by their `gap`, so vibration
+/// averages out and the estimate does not depend on the output data rate. A
+/// change is reported once the estimate turned by more than `threshold`
+/// degrees from the direction of the previous report, or from the first
+/// estimate, taken after `smoothing` of samples. A slow turn is thus
+/// reported in steps of about `threshold`.
+///
+/// While the sensor accelerates, the measured vector is not gravity: samples
+/// whose magnitude differs from 1 g by more than `tolerance` g are left out
+/// of the estimate, which holds its last value until the sensor is at rest
+/// again. Samples must be in g.
+///
+/// ```
+/// use accel_data::{AccelData, TiltDetector};
+/// use std::time::Duration;
+///
+/// let mut tilt = TiltDetector::new(20.0, Duration::from_millis(100), 0.2);
+/// let sample = |y: f32, z: f32| AccelData { idx: 0, gap: 1000, y, z, ..Default::default() };
+/// // lying flat, then tipped over by 30° about x
+/// let (sin, cos) = 30f32.to_radians().sin_cos();
+/// let flat = (0..500).filter_map(|_| tilt.push(sample(0.0, 1.0)));
+/// assert_eq!(flat.count(), 0);
+/// // a shock while tipping is ignored, it is far from 1 g
+/// assert!(tilt.push(sample(3.0, 0.0)).is_none());
+/// let events: Vec<_> = (0..500).filter_map(|_| tilt.push(sample(sin, cos))).collect();
+/// // reported as the estimate passes 20°, the remaining 10° are not enough
+/// // for another report
+/// assert_eq!(events.len(), 1);
+/// let event = events[0];
+/// assert!(event.angle > 20.0 && event.angle < 21.0);
+/// assert_eq!(event.from, [0.0, 0.0, 1.0]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct TiltDetector {
+    /// Cosine of the threshold angle
+    threshold: f32,
+    /// Filter time constant in microseconds
+    smoothing: u64,
+    /// Largest deviation of a usable sample from 1 g
+    tolerance: f32,
+    state: HashMap<u32, TiltState>,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct TiltState {
+    /// Smoothed acceleration
+    gravity: [f32; 3],
+    /// Microseconds of usable samples so far, up to the time constant
+    settled: u64,
+    /// Direction of the last report or of the first estimate
+    reference: Option<[f32; 3]>,
+}
+
+/// `v` scaled to unit length, `None` for a zero vector
+fn unit(v: [f32; 3]) -> Option<[f32; 3]> {
+    let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    (norm > 0.0).then(|| v.map(|x| x / norm))
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+impl TiltDetector {
+    /// Detector of changes larger than `threshold` degrees, smoothing over
+    /// `smoothing` and ignoring samples more than `tolerance` g from 1 g
+    pub fn new(threshold: f32, smoothing: Duration, tolerance: f32) -> Self {
+        Self {
+            threshold: threshold.clamp(0.0, 180.0).to_radians().cos(),
+            smoothing: smoothing.as_micros() as u64,
+            tolerance: tolerance.abs(),
+            state: HashMap::new(),
+        }
+    }
+
+    /// Add a sample, returning the change it completed, if any
+    pub fn push(&mut self, data: AccelData) -> Option<OrientationChange> {
+        let sample = [data.x, data.y, data.z];
+        let magnitude = dot(sample, sample).sqrt();
+        // NaN axes mark missing samples
+        if magnitude.is_nan() || (magnitude - 1.0).abs() > self.tolerance {
+            return None;
+        }
+        let state = self.state.entry(data.idx).or_insert(TiltState {
+            gravity: sample,
+            settled: 0,
+            reference: None,
+        });
+        let gap = u64::from(data.gap);
+        let alpha = gap as f32 / (self.smoothing + gap).max(1) as f32;
+        for (avg, x) in state.gravity.iter_mut().zip(sample) {
+            *avg += alpha * (x - *avg);
+        }
+        state.settled = (state.settled + gap).min(self.smoothing);
+        if state.settled < self.smoothing {
+            return None;
+        }
+        let to = unit(state.gravity)?;
+        let Some(from) = state.reference else {
+            state.reference = Some(to);
+            return None;
+        };
+        let cos = dot(from, to).clamp(-1.0, 1.0);
+        if cos >= self.threshold {
+            return None;
+        }
+        state.reference = Some(to);
+        Some(OrientationChange {
+            idx: data.idx,
+            time: data.time,
+            angle: cos.acos().to_degrees(),
+            from,
+            to,
+        })
+    }
+}
@@ -0,0 +1,561 @@
+use crate::AccelData;
+#[cfg(feature = "async")]
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+use std::{collections::HashMap, fmt, time::Duration};
+#[cfg(feature = "async")]
+use tokio::sync::broadcast::{Sender, error::RecvError};
+
+/// Forward samples from `input` to `output` through a transform.
+///
+/// Everything `f` returns is forwarded in order: samples for which it
+/// returns `None` or an empty `Vec` are dropped.
+#[cfg(feature = "async")]
+pub async fn relay<T, I, F>(
+    input: Sender<AccelData>,
+    output: Sender<T>,
+    running: Arc<AtomicBool>,
+    mut f: F,
+) where
+    T: Clone,
+    I: IntoIterator<Item = T>,
+    F: FnMut(AccelData) -> I,
+{
+    let mut source = input.subscribe();
+    while running.load(Ordering::Relaxed) {
+        match source.recv().await {
+            Ok(data) => {
+                for out in f(data) {
+                    if output.receiver_count() > 0 && output.send(out).is_err() {
+                        log::error!("[RELAY] Failed to forward data");
+                    }
+                }
+            }
+            Err(RecvError::Lagged(n)) => {
+                log::warn!("[RELAY] Lagged behind, skipped {n} samples");
+            }
+            Err(RecvError::Closed) => break,
+        }
+    }
+}
+
+/// Spawn a [`Deadband`] filter from `input` into a new channel holding up
+/// to `capacity` samples, and return the new channel, until `running` is
+/// cleared. Samples are forwarded when any axis moved by more than
+/// `threshold` or after `keepalive`; clients must then use `gap` for timing.
+#[cfg(feature = "async")]
+pub fn deadband_sink(
+    input: Sender<AccelData>,
+    threshold: f32,
+    keepalive: Duration,
+    capacity: usize,
+    running: Arc<AtomicBool>,
+) -> Sender<AccelData> {
+    log::info!("[RELAY] Deadband filter enabled: {threshold}, keepalive {keepalive:?}");
+    let (output, _) = tokio::sync::broadcast::channel(capacity);
+    let mut deadband = Deadband::new(threshold, keepalive);
+    tokio::spawn(relay(input, output.clone(), running, move |data| {
+        deadband.filter(data)
+    }));
+    output
+}
+
+#[derive(Debug, Clone, Copy)]
+struct DeadbandState {
+    last: AccelData,
+    /// Microseconds since the last forwarded sample
+    elapsed: u32,
+}
+
+/// Deadband filter: forwards a sample only if any axis changed by more than
+/// the threshold since the last forwarded sample of the same sensor, or if the
+/// keepalive interval elapsed.
+///
+/// Forwarded samples have their `gap` set to the time since the previous
+/// *forwarded* sample, so output samples are not uniformly spaced.
+#[derive(Debug, Clone)]
+pub struct Deadband {
+    threshold: f32,
+    keepalive: u32,
+    state: HashMap<u32, DeadbandState>,
+}
+
+impl Deadband {
+    /// Create a deadband filter with the given per-axis `threshold` (in the
+    /// units of the samples) and minimum `keepalive` interval.
+    pub fn new(threshold: f32, keepalive: Duration) -> Self {
+        Self {
+            threshold,
+            keepalive: keepalive.as_micros().min(u32::MAX as u128) as u32,
+            state: HashMap::new(),
+        }
+    }
+
+    /// Filter a sample, returning it if it should be forwarded
+    pub fn filter(&mut self, data: AccelData) -> Option<AccelData> {
+        let Some(state) = self.state.get_mut(&{ data.idx }) else {
+            self.state.insert(
+                data.idx,
+                DeadbandState {
+                    last: data,
+                    elapsed: 0,
+                },
+            );
+            return Some(data);
+        };
+        state.elapsed = state.elapsed.saturating_add(data.gap);
+        let changed = (data.x - state.last.x).abs() > self.threshold
+            || (data.y - state.last.y).abs() > self.threshold
+            || (data.z - state.last.z).abs() > self.threshold;
+        if changed || state.elapsed >= self.keepalive {
+            let out = AccelData {
+                gap: state.elapsed,
+                ..data
+            };
+            state.last = data;
+            state.elapsed = 0;
+            Some(out)
+        } else {
+            None
+        }
+    }
+}
+
+/// Exponential moving average, applied per axis and per sensor:
+/// `y[n] = y[n-1] + alpha * (x[n] - y[n-1])`.
+///
+/// The filter is a first order low pass. At an output data rate `fs`, its
+/// time constant is `-1 / (fs * ln(1 - alpha))` and its -3 dB cutoff is
+/// `fs / (2π) * acos(1 - alpha² / (2 * (1 - alpha)))`, about
+/// `alpha * fs / (2π)` for small `alpha`. For example `alpha = 0.01` at
+/// 1000 Hz cuts off near 1.6 Hz. The cutoff scales with the ODR, so `alpha`
+/// must be chosen for the rate the sensors run at.
+///
+/// ```
+/// use accel_data::{AccelData, Ema};
+///
+/// let mut ema = Ema::new(0.5);
+/// let sample = |x| AccelData { idx: 0, gap: 1000, x, z: 1.0, ..Default::default() };
+/// // the first sample initializes the average
+/// assert_eq!({ ema.filter(sample(0.0)).x }, 0.0);
+/// // a unit step is approached geometrically
+/// let steps: Vec<f32> = (0..4).map(|_| { ema.filter(sample(1.0)).x }).collect();
+/// assert_eq!(steps, [0.5, 0.75, 0.875, 0.9375]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Ema {
+    alpha: f32,
+    state: HashMap<u32, AccelData>,
+}
+
+impl Ema {
+    /// Create a moving average with smoothing factor `alpha`, clamped to
+    /// `(0, 1]`; 1 disables smoothing
+    pub fn new(alpha: f32) -> Self {
+        Self {
+            alpha: alpha.clamp(f32::MIN_POSITIVE, 1.0),
+            state: HashMap::new(),
+        }
+    }
+
+    /// Smooth a sample
+    pub fn filter(&mut self, data: AccelData) -> AccelData {
+        let alpha = self.alpha;
+        let avg = self.state.entry(data.idx).or_insert(data);
+        let step = |avg: f32, x: f32| avg + alpha * (x - avg);
+        *avg = AccelData {
+            x: step(avg.x, data.x),
+            y: step(avg.y, data.y),
+            z: step(avg.z, data.z),
+            ..data
+        };
+        *avg
+    }
+}
+
+/// Gravity-subtracted magnitude: reduces each sensor to the scalar
+/// `sqrt(x² + y² + z²) - 1`, emitted at a fixed rate.
+///
+/// The subtraction assumes the sensor measures a roughly 1 g gravity
+/// baseline (samples in g, sensor at rest apart from vibration), so the
+/// result is close to zero for a still sensor regardless of its orientation.
+/// Within each interval the value of largest absolute size is kept, so short
+/// shocks are not lost to the lower rate.
+///
+/// Output samples carry the magnitude in `x`, with `y` and `z` zero, `gap`
+/// the time since the previous output sample of the same sensor, the `time` of
+/// the sample closing the interval and their own `seq` count.
+///
+/// ```
+/// use accel_data::{AccelData, Magnitude};
+/// use std::time::Duration;
+///
+/// let mut mag = Magnitude::new(Duration::from_millis(10));
+/// let sample = |z| AccelData { idx: 0, gap: 1000, z, ..Default::default() };
+/// let out: Vec<_> = [1.0, 1.5, 0.75, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0]
+///     .into_iter()
+///     .filter_map(|z| mag.filter(sample(z)))
+///     .collect();
+/// assert_eq!(out.len(), 1);
+/// assert_eq!(({ out[0].x }, { out[0].gap }), (0.5, 10_000));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Magnitude {
+    interval: u32,
+    state: HashMap<u32, MagnitudeState>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct MagnitudeState {
+    /// Microseconds since the last output sample
+    elapsed: u32,
+    /// Largest magnitude in the current interval
+    peak: f32,
+    /// Sequence number of the next output sample
+    seq: u32,
+}
+
+impl Magnitude {
+    /// Create a magnitude channel emitting one sample per sensor every
+    /// `interval`
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval: interval.as_micros().min(u32::MAX as u128) as u32,
+            state: HashMap::new(),
+        }
+    }
+
+    /// Accumulate a sample, returning the magnitude at the end of an interval
+    pub fn filter(&mut self, data: AccelData) -> Option<AccelData> {
+        let state = self.state.entry(data.idx).or_default();
+        let mag = (data.x * data.x + data.y * data.y + data.z * data.z).sqrt() - 1.0;
+        if mag.abs() > state.peak.abs() {
+            state.peak = mag;
+        }
+        state.elapsed = state.elapsed.saturating_add(data.gap);
+        if state.elapsed < self.interval {
+            return None;
+        }
+        let out = AccelData {
+            idx: data.idx,
+            seq: state.seq,
+            gap: state.elapsed,
+            time: data.time,
+            x: state.peak,
+            y: 0.0,
+            z: 0.0,
+            temp: data.temp,
+            fresh: data.fresh,
+        };
+        *state = MagnitudeState {
+            seq: state.seq.wrapping_add(1),
+            ..Default::default()
+        };
+        Some(out)
+    }
+}
+
+/// Anti-aliased decimation: low-pass filters each sensor's axes with a FIR
+/// filter and keeps every `factor`-th sample, converting e.g. a 4 kHz stream
+/// to a clean 100 Hz one without aliasing vibration above 50 Hz into it.
+///
+/// The factor is `input_hz / output_hz` rounded to an integer, at most
+/// [`MAX_DECIMATION_FACTOR`]. The filter is a
+/// Blackman-windowed sinc of `55 * factor + 1` taps with unity gain at DC,
+/// cutoff at 0.45 × the output rate and a transition band from 0.4 to 0.5 ×
+/// the output rate, beyond which it attenuates by at least 74 dB. Only the
+/// kept outputs are computed, which is what a polyphase decimator does too.
+/// Outputs are delayed by the group delay of `(taps - 1) / 2` input samples;
+/// the first outputs include the filter start-up transient.
+///
+/// Output samples carry `gap` summed over the input samples they replace, the
+/// `time` of the newest input sample (not corrected for the group delay),
+/// their own `seq` count and the latest temperature.
+///
+/// ```
+/// use accel_data::{AccelData, Decimator};
+///
+/// // chirp from 0 to 200 Hz over 4 s at 4 kHz, decimated to 100 Hz
+/// let fs = 4000.0;
+/// let mut dec = Decimator::new(fs, 100.0).unwrap();
+/// assert_eq!(dec.factor(), 40);
+/// let input: Vec<f32> = (0..16_000)
+///     .map(|n| {
+///         let t = n as f64 / fs as f64;
+///         (std::f64::consts::PI * 50.0 * t * t).sin() as f32
+///     })
+///     .collect();
+/// let out: Vec<f32> = input
+///     .iter()
+///     .filter_map(|&x| dec.filter(AccelData { gap: 250, x, ..Default::default() }))
+///     .map(|d| d.x)
+///     .collect();
+/// assert_eq!(out.len(), 400);
+///
+/// // reference: direct convolution over the whole input, then every 40th sample
+/// let taps = dec.taps();
+/// let reference = (0..input.len()).filter(|n| (n + 1) % 40 == 0).map(|n| {
+///     (0..taps.len().min(n + 1)).map(|k| taps[k] * input[n - k]).sum::<f32>()
+/// });
+/// assert!(out.iter().zip(reference).all(|(a, b)| (a - b).abs() < 1e-4));
+///
+/// // the chirp passes below 40 Hz (0.5 to 0.8 s, after the group delay) and
+/// // is suppressed by more than 80 dB once it is past 60 Hz (from 1.35 s,
+/// // the sweep smears across the 0.55 s filter span)
+/// let delay = 27; // (taps - 1) / 2 input samples, in output samples
+/// let peak = |range: std::ops::Range<usize>| out[range].iter().fold(0.0f32, |m, x| m.max(x.abs()));
+/// assert!(peak(50 + delay..80 + delay) > 0.95);
+/// assert!(peak(160..400) < 1e-4);
+///
+/// // the filter grows with the factor, which is bounded
+/// assert!(Decimator::new(fs, 0.001).is_err());
+/// assert!(Decimator::new(fs, 0.0).is_err());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Decimator {
+    factor: usize,
+    taps: Vec<f32>,
+    state: HashMap<u32, DecimatorState>,
+}
+
+#[derive(Debug, Clone)]
+struct DecimatorState {
+    /// Latest input samples per axis, a ring buffer of `taps.len()` entries
+    history: Vec<[f32; 3]>,
+    /// Position of the next write in `history`
+    pos: usize,
+    /// Input samples since the last output
+    count: usize,
+    /// Microseconds since the last output
+    elapsed: u32,
+    /// Sequence number of the next output sample
+    seq: u32,
+}
+
+/// Largest [`Decimator::factor`], e.g. from 4 kHz to 1 Hz. The filter of a
+/// decimator holds `55 * factor + 1` taps, and as many samples per sensor
+pub const MAX_DECIMATION_FACTOR: usize = 4000;
+
+/// Invalid rates of a [`Decimator`] or [`DecimationPlan`]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DecimationError {
+    /// A rate is not a positive number of Hz
+    InvalidRate(f32),
+    /// The ratio of the rates is above [`MAX_DECIMATION_FACTOR`]
+    FactorTooLarge(f32),
+}
+
+impl fmt::Display for DecimationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecimationError::InvalidRate(hz) => write!(f, "invalid rate {hz} Hz"),
+            DecimationError::FactorTooLarge(ratio) => write!(
+                f,
+                "decimation by {ratio} is above the maximum of {MAX_DECIMATION_FACTOR}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DecimationError {}
+
+/// Integer decimation factor from `input_hz` to about `output_hz`
+fn decimation_factor(input_hz: f32, output_hz: f32) -> Result<usize, DecimationError> {
+    for hz in [input_hz, output_hz] {
+        if !(hz.is_finite() && hz > 0.0) {
+            return Err(DecimationError::InvalidRate(hz));
+        }
+    }
+    let ratio = (input_hz / output_hz).round().max(1.0);
+    if ratio > MAX_DECIMATION_FACTOR as f32 {
+        return Err(DecimationError::FactorTooLarge(ratio));
+    }
+    Ok(ratio as usize)
+}
+
+impl Decimator {
+    /// Create a decimator from `input_hz` to about `output_hz`, see
+    /// [`Decimator::factor`]
+    pub fn new(input_hz: f32, output_hz: f32) -> Result<Self, DecimationError> {
+        decimation_factor(input_hz, output_hz).map(Self::with_factor)
+    }
+
+    fn with_factor(factor: usize) -> Self {
+        Self {
+            factor,
+            taps: lowpass_taps(factor),
+            state: HashMap::new(),
+        }
+    }
+
+    /// Number of input samples per output sample
+    pub fn factor(&self) -> usize {
+        self.factor
+    }
+
+    /// Filter coefficients, applied to the newest input sample first
+    pub fn taps(&self) -> &[f32] {
+        &self.taps
+    }
+
+    /// Accumulate a sample, returning the filtered sample every `factor` inputs
+    pub fn filter(&mut self, data: AccelData) -> Option<AccelData> {
+        let len = self.taps.len();
+        let state = self
+            .state
+            .entry(data.idx)
+            .or_insert_with(|| DecimatorState {
+                history: vec![[0.0; 3]; len],
+                pos: 0,
+                count: 0,
+                elapsed: 0,
+                seq: 0,
+            });
+        state.history[state.pos] = [data.x, data.y, data.z];
+        state.pos = (state.pos + 1) % len;
+        state.count += 1;
+        state.elapsed = state.elapsed.saturating_add(data.gap);
+        if state.count < self.factor {
+            return None;
+        }
+        let mut acc = [0.0f32; 3];
+        // newest sample first: history[pos - 1], history[pos - 2], ...
+        let (newer, older) = state.history.split_at(state.pos);
+        for (tap, sample) in self
+            .taps
+            .iter()
+            .zip(newer.iter().rev().chain(older.iter().rev()))
+        {
+            for (acc, value) in acc.iter_mut().zip(sample) {
+                *acc += tap * value;
+            }
+        }
+        let out = AccelData {
+            seq: state.seq,
+            gap: state.elapsed,
+            x: acc[0],
+            y: acc[1],
+            z: acc[2],
+            ..data
+        };
+        state.count = 0;
+        state.elapsed = 0;
+        state.seq = state.seq.wrapping_add(1);
+        Some(out)
+    }
+}
+
+/// Rates of a [`Decimator`] from one sensor, to validate a configuration
+/// before any sample arrives.
+///
+/// A stream at `f` Hz only represents frequencies up to its Nyquist
+/// frequency `f / 2`: content above it folds back onto lower frequencies,
+/// which is why dropping samples aliases. The decimator's filter passes up to
+/// 0.4 × and stops from 0.5 × the output rate, so nothing folds into the
+/// passband, and the sensor's own low pass (a quarter of its output data
+/// rate on the ADXL355) caps the passband further. Decimation does not
+/// raise a rate: a requested rate at or above the input rate passes the
+/// samples through unfiltered.
+///
+/// ```
+/// use accel_data::DecimationPlan;
+///
+/// // 1 kHz with the hardware low pass at 250 Hz
+/// let plan = DecimationPlan::new(1000.0, 250.0, 100.0).unwrap();
+/// assert_eq!((plan.factor, plan.output_hz, plan.passband_hz), (10, 100.0, 40.0));
+/// assert!(plan.is_exact() && !plan.exceeds_input());
+/// // integer factors only
+/// let plan = DecimationPlan::new(1000.0, 250.0, 300.0).unwrap();
+/// assert_eq!(plan.factor, 3);
+/// assert!(!plan.is_exact());
+/// // the hardware low pass limits a slow sensor
+/// let plan = DecimationPlan::new(62.5, 15.625, 62.5).unwrap();
+/// assert_eq!((plan.factor, plan.passband_hz), (1, 15.625));
+/// assert!(DecimationPlan::new(62.5, 15.625, 100.0).unwrap().exceeds_input());
+/// // too slow a rate for the filter
+/// assert!(DecimationPlan::new(4000.0, 1000.0, 0.5).is_err());
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct DecimationPlan {
+    /// Input rate in Hz
+    pub input_hz: f32,
+    /// Requested output rate in Hz
+    pub requested_hz: f32,
+    /// Input samples per output sample, see [`Decimator::factor`]
+    pub factor: usize,
+    /// Actual output rate in Hz, `input_hz / factor`
+    pub output_hz: f32,
+    /// Highest frequency passed to the output in Hz: the smaller of the
+    /// hardware bandwidth and the passband of the filter
+    pub passband_hz: f32,
+}
+
+impl DecimationPlan {
+    /// Decimation of a sensor sampling at `input_hz` behind a hardware low
+    /// pass at `bandwidth_hz` to about `requested_hz`
+    pub fn new(
+        input_hz: f32,
+        bandwidth_hz: f32,
+        requested_hz: f32,
+    ) -> Result<Self, DecimationError> {
+        let factor = decimation_factor(input_hz, requested_hz)?;
+        let output_hz = input_hz / factor as f32;
+        let passband_hz = if factor == 1 {
+            bandwidth_hz
+        } else {
+            bandwidth_hz.min(0.4 * output_hz)
+        };
+        Ok(Self {
+            input_hz,
+            requested_hz,
+            factor,
+            output_hz,
+            passband_hz,
+        })
+    }
+
+    /// The requested rate is above the input rate, the output is at the
+    /// input rate instead
+    pub fn exceeds_input(&self) -> bool {
+        self.requested_hz > self.input_hz
+    }
+
+    /// The output rate is within 1% of the requested rate
+    pub fn is_exact(&self) -> bool {
+        (self.output_hz - self.requested_hz).abs() <= 0.01 * self.requested_hz
+    }
+
+    /// Decimator of this plan
+    pub fn decimator(&self) -> Decimator {
+        Decimator::with_factor(self.factor)
+    }
+}
+
+/// Blackman-windowed sinc low pass for decimation by `factor`, normalized to
+/// unity gain at DC
+fn lowpass_taps(factor: usize) -> Vec<f32> {
+    if factor == 1 {
+        return vec![1.0];
+    }
+    let len = 55 * factor + 1;
+    // cutoff in cycles per input sample
+    let fc = 0.45 / factor as f64;
+    let center = (len - 1) as f64 / 2.0;
+    let taps: Vec<f64> = (0..len)
+        .map(|n| {
+            let t = n as f64 - center;
+            let sinc = if t == 0.0 {
+                2.0 * fc
+            } else {
+                (2.0 * std::f64::consts::PI * fc * t).sin() / (std::f64::consts::PI * t)
+            };
+            let phase = 2.0 * std::f64::consts::PI * n as f64 / (len - 1) as f64;
+            let window = 0.42 - 0.5 * phase.cos() + 0.08 * (2.0 * phase).cos();
+            sinc * window
+        })
+        .collect();
+    let sum: f64 = taps.iter().sum();
+    taps.iter().map(|tap| (tap / sum) as f32).collect()
+}
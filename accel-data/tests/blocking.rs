@@ -0,0 +1,89 @@
+//! End-to-end tests of the blocking TCP server
+mod common;
+
+use accel_data::{
+    AccelData, Frame, Handshake, SensorInfo,
+    blocking::{ServerConfig, tcp_server},
+    frame::{FRAME_HEADER_LEN, STREAM_HEADER_LEN, check_header},
+};
+use common::sample_of;
+use std::{
+    io::Read,
+    net::TcpStream,
+    sync::{Arc, atomic::AtomicBool, mpsc},
+    thread,
+    time::Duration,
+};
+
+const SAMPLES_PER_FRAME: usize = 4;
+
+/// Connect to the server, retrying until it is listening, and check the
+/// stream header
+fn connect(port: u16) -> TcpStream {
+    for _ in 0..100 {
+        if let Ok(mut stream) = TcpStream::connect(("127.0.0.1", port)) {
+            let mut header = [0u8; STREAM_HEADER_LEN];
+            stream.read_exact(&mut header).unwrap();
+            assert_eq!(check_header(&header), Ok(STREAM_HEADER_LEN));
+            return stream;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    panic!("server not listening on port {port}");
+}
+
+/// Read one frame, header included
+fn read_raw_frame(stream: &mut TcpStream) -> Vec<u8> {
+    let mut frame = vec![0u8; FRAME_HEADER_LEN];
+    stream.read_exact(&mut frame).unwrap();
+    let len = u16::from_le_bytes([frame[1], frame[2]]) as usize;
+    frame.resize(FRAME_HEADER_LEN + len, 0);
+    stream.read_exact(&mut frame[FRAME_HEADER_LEN..]).unwrap();
+    frame
+}
+
+#[test]
+fn handshake_then_full_and_partial_frames() {
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .expect("no free port")
+        .port();
+    let info = SensorInfo {
+        idx: 0,
+        odr: 1000.0,
+        range: 2.048,
+        hpf: 0,
+        scale: 1.0 / 256_000.0,
+    };
+    let mut config = ServerConfig::default();
+    config
+        .handshake(Handshake::new(vec![info]))
+        .frame_size(FRAME_HEADER_LEN + 1 + SAMPLES_PER_FRAME * AccelData::SIZE)
+        .max_latency(Duration::from_millis(20));
+    let (sink, source) = mpsc::channel();
+    thread::spawn(move || tcp_server(port, Arc::new(AtomicBool::new(true)), source, config));
+
+    let mut stream = connect(port);
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    // The client is registered before its handshake is sent
+    let frame = read_raw_frame(&mut stream);
+    let (frame, _) = Frame::parse(&frame).unwrap();
+    assert_eq!(frame.handshake(), Some(Handshake::new(vec![info])));
+
+    let count = SAMPLES_PER_FRAME as u32 + 2;
+    for i in 0..count {
+        sink.send(sample_of(0, i)).unwrap();
+    }
+    let mut received = Vec::new();
+    for expected in [SAMPLES_PER_FRAME, 2] {
+        let frame = read_raw_frame(&mut stream);
+        let samples = Frame::parse(&frame).unwrap().0.samples().unwrap();
+        assert_eq!(samples.len(), expected);
+        received.extend(samples);
+    }
+    for (i, data) in received.iter().enumerate() {
+        assert_eq!(data.as_bytes(), sample_of(0, i as u32).as_bytes());
+    }
+}
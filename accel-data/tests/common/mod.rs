@@ -0,0 +1,47 @@
+//! Fixtures shared by the integration tests
+// every test binary uses a different subset
+#![allow(dead_code)]
+use accel_data::AccelData;
+
+/// The `i`-th of the samples of two interleaved sensors at 1 kHz, with
+/// distinct gaps and axes
+pub fn sample(i: u32) -> AccelData {
+    AccelData {
+        idx: i % 2,
+        seq: i / 2,
+        gap: 1000 + i,
+        time: 1000 * u64::from(i / 2),
+        x: i as f32,
+        y: -(i as f32),
+        z: 0.5 * i as f32,
+        temp: None,
+        fresh: None,
+    }
+}
+
+/// Sample `seq` of sensor `idx` alone at 1 kHz
+pub fn sample_of(idx: u32, seq: u32) -> AccelData {
+    AccelData {
+        idx,
+        seq,
+        gap: 1000,
+        time: 1000 * u64::from(seq),
+        x: seq as f32,
+        y: -(seq as f32),
+        z: 1.0,
+        temp: None,
+        fresh: None,
+    }
+}
+
+/// Wait until the number of clients subscribed to the sink is `count`
+#[cfg(feature = "async")]
+pub async fn wait_for_clients(sink: &tokio::sync::broadcast::Sender<AccelData>, count: usize) {
+    for _ in 0..200 {
+        if sink.receiver_count() == count {
+            return;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    panic!("expected {count} clients, have {}", sink.receiver_count());
+}
@@ -0,0 +1,114 @@
+//! Golden-file tests of the wire format decoder
+use accel_data::{
+    AccelData, AxisMask, DataFrame, Frame, FrameKind, Handshake, SensorInfo, Units,
+    frame::{ByteOrder, RecordLayout, check_header, decode_records},
+};
+
+/// Stream header, a handshake, two data frames and a frame of an unknown
+/// kind, as a version 1 server sends them
+const GOLDEN: &[u8] = include_bytes!("data/golden-v1.bin");
+
+/// Fields of a sample, for comparing packed records
+fn fields(data: &AccelData) -> (u32, u32, u32, u64, f32, f32, f32, Option<f32>) {
+    (
+        data.idx, data.seq, data.gap, data.time, data.x, data.y, data.z, data.temp,
+    )
+}
+
+#[test]
+fn golden_stream_decodes_to_known_values() {
+    let mut bytes = &GOLDEN[check_header(GOLDEN).unwrap()..];
+    let mut frames = Vec::new();
+    while let Some((frame, len)) = Frame::parse(bytes) {
+        frames.push(frame);
+        bytes = &bytes[len..];
+    }
+    assert!(bytes.is_empty());
+    assert_eq!(frames.len(), 4);
+
+    let handshake = frames[0].handshake().unwrap();
+    let info = SensorInfo {
+        idx: 1,
+        odr: 4000.0,
+        range: 2.048,
+        hpf: 0,
+        scale: 0.0000039,
+    };
+    assert_eq!(handshake, Handshake::new(vec![info]));
+    assert_eq!(handshake.units, Units::G);
+
+    let samples = frames[1].samples().unwrap();
+    let samples: Vec<_> = samples.iter().map(fields).collect();
+    assert_eq!(
+        samples,
+        [
+            (1, 7, 1000, 123_456_789, 0.5, -0.25, 1.0, Some(25.5)),
+            // a temperature that was not sampled is NaN on the wire
+            (0, u32::MAX, 250, 1 << 40, -1.5, 0.125, 0.0, None),
+        ]
+    );
+    // disabled axes are zero
+    let samples = frames[2].samples().unwrap();
+    assert_eq!(fields(&samples[0]), (2, 3, 500, 1500, 2.0, 0.0, -2.0, None));
+
+    // frames of unknown kinds are skipped
+    assert_eq!(frames[3].kind, 9);
+    assert_eq!(FrameKind::from_u8(frames[3].kind), None);
+    assert!(frames[3].samples().is_none());
+}
+
+#[test]
+fn layouts_without_optional_fields_and_big_endian() {
+    let layout = RecordLayout {
+        byte_order: ByteOrder::Big,
+        seq: false,
+        time: false,
+        axes: AxisMask::X | AxisMask::TEMP,
+    };
+    assert_eq!(layout.record_len(), 16);
+    let record = [
+        0x00, 0x00, 0x00, 0x05, // idx
+        0x00, 0x00, 0x03, 0xe8, // gap
+        0x3f, 0xc0, 0x00, 0x00, // x = 1.5
+        0x41, 0xcc, 0x00, 0x00, // temperature = 25.5
+    ];
+    let samples = decode_records(&layout, &record).unwrap();
+    assert_eq!(
+        fields(&samples[0]),
+        (5, 0, 1000, 0, 1.5, 0.0, 0.0, Some(25.5))
+    );
+    // partial records are rejected
+    assert!(decode_records(&layout, &record[..15]).is_none());
+}
+
+#[test]
+fn unknown_versions_have_no_layout() {
+    let layout = RecordLayout::for_version(1, AxisMask::ALL).unwrap();
+    assert_eq!(layout.record_len(), AccelData::SIZE);
+    assert_eq!(RecordLayout::for_version(0, AxisMask::ALL), None);
+    assert_eq!(RecordLayout::for_version(3, AxisMask::ALL), None);
+}
+
+#[test]
+fn freshness_is_decoded_from_version_2() {
+    let axes = AxisMask::ALL | AxisMask::FRESH;
+    let mut frame = DataFrame::new(axes, 1024);
+    for fresh in [Some(true), Some(false), None] {
+        frame.push(&AccelData {
+            idx: 1,
+            fresh,
+            ..Default::default()
+        });
+    }
+    let bytes = frame.as_bytes();
+    let (frame, _) = Frame::parse(bytes).unwrap();
+    let samples = frame.samples().unwrap();
+    let fresh: Vec<_> = samples.iter().map(|data| data.fresh).collect();
+    assert_eq!(fresh, [Some(true), Some(false), None]);
+
+    // version 1 had no freshness field
+    let layout = RecordLayout::for_version(1, axes).unwrap();
+    assert_eq!(layout.axes, AxisMask::ALL);
+    let layout = RecordLayout::for_version(2, axes).unwrap();
+    assert_eq!(layout.record_len(), AccelData::SIZE + 4);
+}
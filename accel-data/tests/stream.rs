@@ -0,0 +1,68 @@
+//! In-process sample streams
+mod common;
+
+use accel_data::{StreamError, sample_stream};
+use common::sample_of;
+use futures_util::StreamExt;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+#[tokio::test]
+async fn lag_is_reported_as_one_gap_before_the_next_sample() {
+    let (sink, _) = broadcast::channel(4);
+    let mut samples = Box::pin(sample_stream(&sink));
+    // overrun the channel before the consumer runs
+    for seq in 0..10 {
+        sink.send(sample_of(0, seq)).unwrap();
+    }
+    assert_eq!(
+        samples.next().await.unwrap().unwrap_err(),
+        StreamError::Lagged(6)
+    );
+    for seq in 6..10 {
+        let data = samples.next().await.unwrap().unwrap();
+        assert_eq!({ data.seq }, seq);
+    }
+    // the stream keeps going after a gap
+    sink.send(sample_of(0, 10)).unwrap();
+    assert_eq!({ samples.next().await.unwrap().unwrap().seq }, 10);
+    drop(sink);
+    assert!(samples.next().await.is_none());
+}
+
+#[tokio::test]
+async fn slow_consumer_sees_every_sample_or_a_gap() {
+    let (sink, _) = broadcast::channel(8);
+    let samples = sample_stream(&sink);
+    let producer = tokio::spawn(async move {
+        for seq in 0..200 {
+            sink.send(sample_of(0, seq)).unwrap();
+            if seq % 50 == 0 {
+                tokio::task::yield_now().await;
+            }
+        }
+    });
+    let consumer = samples.then(|item| async move {
+        tokio::time::sleep(Duration::from_micros(10)).await;
+        item
+    });
+    let items: Vec<_> = consumer.collect().await;
+    producer.await.unwrap();
+    // every sample is either received or counted in a gap
+    let mut expected = 0;
+    let mut gaps = 0;
+    for item in items {
+        match item {
+            Ok(data) => {
+                assert_eq!({ data.seq }, expected);
+                expected += 1;
+            }
+            Err(StreamError::Lagged(n)) => {
+                expected += n as u32;
+                gaps += 1;
+            }
+        }
+    }
+    assert_eq!(expected, 200);
+    assert!(gaps > 0);
+}
@@ -0,0 +1,603 @@
+//! End-to-end tests of the TCP server wire format
+mod common;
+
+use accel_data::{
+    AccelData, AxisMask, COMPACT_REQUEST, Coalescer, CompactAccelData, DataFrame, ErrorKind, Frame,
+    FrameKind, Handshake, SampleSource, SeededSource, SensorInfo, ServerConfig, Status,
+    SyntheticSource, UDP_HELLO,
+    frame::{FRAME_HEADER_LEN, STREAM_HEADER_LEN, check_header},
+    relay, tcp_server, udp_server, uds_server,
+};
+use common::{sample, wait_for_clients};
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+    sync::broadcast,
+};
+
+/// Samples per data frame with the batch size used below
+const SAMPLES_PER_FRAME: usize = 6;
+
+/// Start a TCP server with fixed size frames on a free port
+async fn start_server() -> (u16, broadcast::Sender<AccelData>) {
+    start_server_with(ServerConfig::default()).await
+}
+
+async fn start_server_with(mut config: ServerConfig) -> (u16, broadcast::Sender<AccelData>) {
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .expect("no free port")
+        .port();
+    let (sink, _) = broadcast::channel(1024);
+    let frame_len = FRAME_HEADER_LEN + 1 + SAMPLES_PER_FRAME * AccelData::SIZE;
+    config.batch_size(frame_len, frame_len);
+    tokio::spawn(tcp_server(
+        port,
+        Arc::new(AtomicBool::new(true)),
+        sink.clone(),
+        config,
+    ));
+    (port, sink)
+}
+
+/// Connect to the server, retrying until it is listening, and check the
+/// stream header
+async fn connect(port: u16) -> TcpStream {
+    let mut stream = connect_raw(port).await;
+    let mut header = [0u8; STREAM_HEADER_LEN];
+    stream.read_exact(&mut header).await.unwrap();
+    assert_eq!(check_header(&header), Ok(STREAM_HEADER_LEN));
+    stream
+}
+
+/// Connect to the server, retrying until it is listening
+async fn connect_raw(port: u16) -> TcpStream {
+    for _ in 0..100 {
+        if let Ok(stream) = TcpStream::connect(("127.0.0.1", port)).await {
+            return stream;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("server not listening on port {port}");
+}
+
+/// Read one frame, header included
+async fn read_raw_frame(stream: &mut (impl AsyncRead + Unpin)) -> Vec<u8> {
+    let mut frame = vec![0u8; FRAME_HEADER_LEN];
+    stream.read_exact(&mut frame).await.unwrap();
+    let len = u16::from_le_bytes([frame[1], frame[2]]) as usize;
+    frame.resize(FRAME_HEADER_LEN + len, 0);
+    stream
+        .read_exact(&mut frame[FRAME_HEADER_LEN..])
+        .await
+        .unwrap();
+    frame
+}
+
+/// Read one data frame and decode its records
+async fn read_frame(stream: &mut (impl AsyncRead + Unpin)) -> Vec<AccelData> {
+    let frame = read_raw_frame(stream).await;
+    assert_eq!(frame[0], FrameKind::Data as u8);
+    let payload = &frame[FRAME_HEADER_LEN..];
+    let (&mask, records) = payload.split_first().unwrap();
+    assert_eq!(mask, 0b111, "all axes are enabled by default");
+    assert_eq!(records.len() % AccelData::SIZE, 0);
+    records
+        .chunks_exact(AccelData::SIZE)
+        .map(|rec| AccelData::from_bytes(rec).unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn samples_round_trip_across_frames() {
+    let (port, sink) = start_server().await;
+    let mut stream = connect(port).await;
+    wait_for_clients(&sink, 1).await;
+
+    // Frames are sent once full, so the last partial frame is held back
+    let frames = 5;
+    let count = frames * SAMPLES_PER_FRAME + 1;
+    for i in 0..count as u32 {
+        sink.send(sample(i)).unwrap();
+    }
+    let mut received = Vec::new();
+    for _ in 0..frames {
+        let frame = read_frame(&mut stream).await;
+        assert_eq!(frame.len(), SAMPLES_PER_FRAME);
+        received.extend(frame);
+    }
+    for (i, data) in received.iter().enumerate() {
+        assert_eq!(data.as_bytes(), sample(i as u32).as_bytes());
+    }
+}
+
+#[tokio::test]
+async fn partial_frame_is_sent_after_max_latency() {
+    let mut config = ServerConfig::default();
+    config.max_latency(Duration::from_millis(20));
+    let (port, sink) = start_server_with(config).await;
+    let mut stream = connect(port).await;
+    wait_for_clients(&sink, 1).await;
+
+    // A full frame is sent right away, the remainder once its deadline passes
+    let count = SAMPLES_PER_FRAME + 2;
+    for i in 0..count as u32 {
+        sink.send(sample(i)).unwrap();
+    }
+    let start = std::time::Instant::now();
+    assert_eq!(read_frame(&mut stream).await.len(), SAMPLES_PER_FRAME);
+    let rest = tokio::time::timeout(Duration::from_secs(1), read_frame(&mut stream))
+        .await
+        .expect("partial frame held back");
+    assert!(start.elapsed() >= Duration::from_millis(15));
+    let expected: Vec<_> = (SAMPLES_PER_FRAME as u32..count as u32)
+        .map(|i| sample(i).as_bytes())
+        .collect();
+    assert_eq!(
+        rest.iter().map(|d| d.as_bytes()).collect::<Vec<_>>(),
+        expected
+    );
+}
+
+#[tokio::test]
+async fn disconnecting_client_is_dropped() {
+    let (port, sink) = start_server().await;
+    let stream = connect(port).await;
+    wait_for_clients(&sink, 1).await;
+    drop(stream);
+    // The handler notices the closed connection without any data flowing
+    wait_for_clients(&sink, 0).await;
+
+    // and the server keeps serving new clients
+    let mut stream = connect(port).await;
+    wait_for_clients(&sink, 1).await;
+    for i in 0..=SAMPLES_PER_FRAME as u32 {
+        sink.send(sample(i)).unwrap();
+    }
+    let frame = read_frame(&mut stream).await;
+    assert_eq!(frame[0].as_bytes(), sample(0).as_bytes());
+}
+
+#[tokio::test]
+async fn client_closing_right_after_connecting_is_reaped() {
+    let clients = Arc::new(AtomicUsize::new(0));
+    let mut config = ServerConfig::default();
+    config.clients(clients.clone());
+    let (port, sink) = start_server_with(config).await;
+    // a client that closes its side before reading anything
+    let mut stream = connect_raw(port).await;
+    stream.shutdown().await.unwrap();
+    // The handler reads EOF and closes the connection, no write has to fail
+    let mut received = Vec::new();
+    tokio::time::timeout(Duration::from_secs(2), stream.read_to_end(&mut received))
+        .await
+        .expect("connection not closed by the server")
+        .unwrap();
+    assert_eq!(check_header(&received), Ok(STREAM_HEADER_LEN));
+    assert_eq!(received.len(), STREAM_HEADER_LEN);
+    wait_for_clients(&sink, 0).await;
+    for _ in 0..200 {
+        if clients.load(Ordering::Relaxed) == 0 {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("client still counted after closing");
+}
+
+#[tokio::test]
+async fn handshake_precedes_data() {
+    let info = SensorInfo {
+        idx: 0,
+        odr: 1000.0,
+        range: 2.048,
+        hpf: 6,
+        scale: 2.048 / 524_287.0,
+    };
+    let mut config = ServerConfig::default();
+    config.handshake(Handshake::new(vec![info]));
+    let (port, sink) = start_server_with(config).await;
+    let mut stream = connect(port).await;
+    let frame = read_raw_frame(&mut stream).await;
+    let (frame, _) = Frame::parse(&frame).unwrap();
+    assert_eq!(frame.handshake(), Some(Handshake::new(vec![info])));
+
+    wait_for_clients(&sink, 1).await;
+    for i in 0..=SAMPLES_PER_FRAME as u32 {
+        sink.send(sample(i)).unwrap();
+    }
+    assert_eq!(read_frame(&mut stream).await.len(), SAMPLES_PER_FRAME);
+}
+
+#[tokio::test]
+async fn udp_datagrams_start_with_the_stream_header() {
+    let port = std::net::UdpSocket::bind("127.0.0.1:0")
+        .and_then(|socket| socket.local_addr())
+        .expect("no free port")
+        .port();
+    let (sink, _) = broadcast::channel(1024);
+    let mut config = ServerConfig::default();
+    config.handshake(Handshake::new(Vec::new()));
+    tokio::spawn(udp_server(
+        port,
+        Arc::new(AtomicBool::new(true)),
+        sink.clone(),
+        config,
+    ));
+    let client = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let mut buf = [0u8; 2048];
+    // subscribe, retrying until the server is listening
+    let mut len = None;
+    for _ in 0..100 {
+        let _ = client.send_to(UDP_HELLO, ("127.0.0.1", port)).await;
+        let recv = tokio::time::timeout(Duration::from_millis(20), client.recv_from(&mut buf));
+        if let Ok(Ok((n, _))) = recv.await {
+            len = Some(n);
+            break;
+        }
+    }
+    let datagram = &buf[..len.expect("no handshake from the UDP server")];
+    let offset = check_header(datagram).unwrap();
+    let (frame, _) = Frame::parse(&datagram[offset..]).unwrap();
+    assert_eq!(frame.handshake(), Some(Handshake::new(Vec::new())));
+}
+
+#[tokio::test]
+async fn stalled_client_is_dropped() {
+    let mut config = ServerConfig::default();
+    config
+        .send_buffer_size(4096)
+        .write_timeout(Duration::from_millis(100));
+    let (port, sink) = start_server_with(config).await;
+    // A client that never reads, with a small receive buffer to fill quickly
+    let mut stream = None;
+    for _ in 0..100 {
+        let socket = tokio::net::TcpSocket::new_v4().unwrap();
+        socket.set_recv_buffer_size(4096).unwrap();
+        if let Ok(s) = socket.connect(([127, 0, 0, 1], port).into()).await {
+            stream = Some(s);
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    let _stream = stream.expect("server not listening");
+    wait_for_clients(&sink, 1).await;
+
+    let start = std::time::Instant::now();
+    let mut i = 0;
+    while sink.receiver_count() > 0 {
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "stalled client not dropped"
+        );
+        for _ in 0..64 {
+            sink.send(sample(i)).unwrap();
+            i += 1;
+        }
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+}
+
+#[tokio::test]
+async fn synthetic_source_is_served_with_nominal_timing() {
+    let (port, sink) = start_server().await;
+    let mut stream = connect(port).await;
+    wait_for_clients(&sink, 1).await;
+    let source = SyntheticSource::new(3, Duration::from_millis(1), |t| [t, -t, 1.0]);
+    let running = Arc::new(AtomicBool::new(true));
+    tokio::spawn(source.run(sink.clone(), running.clone()));
+
+    let received = read_frame(&mut stream).await;
+    running.store(false, std::sync::atomic::Ordering::Relaxed);
+    assert_eq!(received.len(), SAMPLES_PER_FRAME);
+    for (i, data) in received.iter().enumerate() {
+        let (idx, seq, gap, time) = ({ data.idx }, { data.seq }, { data.gap }, { data.time });
+        assert_eq!((idx, seq, gap, time), (3, i as u32, 1000, 1000 * i as u64));
+        assert_eq!(
+            ({ data.x }, { data.y }, { data.z }),
+            (i as f32 / 1000.0, -(i as f32) / 1000.0, 1.0)
+        );
+    }
+}
+
+#[tokio::test]
+async fn seeded_source_is_served_byte_for_byte() {
+    let (port, sink) = start_server().await;
+    // a full frame is sent when the next sample arrives
+    let count = 2 * SAMPLES_PER_FRAME;
+    let source = SeededSource::new(2, 7, count as u64 + 1, Duration::from_millis(1));
+    let samples: Vec<_> = source.samples().take(count).collect();
+    let running = Arc::new(AtomicBool::new(true));
+    // the source waits for the client, so no sample is dropped
+    tokio::spawn(source.run(sink.clone(), running.clone()));
+    let mut stream = connect(port).await;
+
+    for chunk in samples.chunks(SAMPLES_PER_FRAME) {
+        let mut expected = DataFrame::new(AxisMask::ALL, chunk.len());
+        for data in chunk {
+            expected.push(data);
+        }
+        assert_eq!(read_raw_frame(&mut stream).await, expected.as_bytes());
+    }
+    running.store(false, Ordering::Relaxed);
+}
+
+#[tokio::test]
+async fn coalesced_samples_are_served_as_tuples() {
+    let (port, sink) = start_server().await;
+    let mut stream = connect(port).await;
+    wait_for_clients(&sink, 1).await;
+    let (raw, _) = broadcast::channel(64);
+    let mut coalescer = Coalescer::new([0, 1], Duration::from_millis(1));
+    let running = Arc::new(AtomicBool::new(true));
+    tokio::spawn(relay(
+        raw.clone(),
+        sink.clone(),
+        running.clone(),
+        move |data| coalescer.push(data),
+    ));
+    while raw.receiver_count() < 1 {
+        tokio::task::yield_now().await;
+    }
+
+    // sensor 1 samples late and drops its sample of tick 1
+    for tick in 0..5u32 {
+        let time = 1000 * u64::from(tick);
+        raw.send(AccelData {
+            idx: 0,
+            seq: tick,
+            time,
+            x: 1.0,
+            ..Default::default()
+        })
+        .unwrap();
+        if tick != 1 {
+            let time = time + 30;
+            raw.send(AccelData {
+                idx: 1,
+                seq: tick,
+                time,
+                x: 2.0,
+                ..Default::default()
+            })
+            .unwrap();
+        }
+    }
+    let received = read_frame(&mut stream).await;
+    running.store(false, Ordering::Relaxed);
+    let tuples: Vec<_> = received
+        .iter()
+        .map(|data| ({ data.idx }, { data.time }, data.is_missing()))
+        .collect();
+    assert_eq!(
+        tuples,
+        [
+            (0, 0, false),
+            (1, 0, false),
+            (0, 1000, false),
+            (1, 1000, true),
+            (0, 2000, false),
+            (1, 2000, false),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn unix_socket_serves_the_tcp_stream() {
+    let path = std::env::temp_dir().join(format!("accel-data-test-{}.sock", std::process::id()));
+    // a stale socket of an earlier run is replaced
+    drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+    let (sink, _) = broadcast::channel(1024);
+    let mut config = ServerConfig::default();
+    let frame_len = FRAME_HEADER_LEN + 1 + SAMPLES_PER_FRAME * AccelData::SIZE;
+    config.batch_size(frame_len, frame_len);
+    let server = tokio::spawn(uds_server(
+        path.clone(),
+        Arc::new(AtomicBool::new(true)),
+        sink.clone(),
+        config,
+    ));
+    let mut stream = None;
+    for _ in 0..100 {
+        if let Ok(s) = tokio::net::UnixStream::connect(&path).await {
+            stream = Some(s);
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    let mut stream = stream.expect("server not listening");
+    let mut header = [0u8; STREAM_HEADER_LEN];
+    stream.read_exact(&mut header).await.unwrap();
+    assert_eq!(check_header(&header), Ok(STREAM_HEADER_LEN));
+    wait_for_clients(&sink, 1).await;
+    for i in 0..=SAMPLES_PER_FRAME as u32 {
+        sink.send(sample(i)).unwrap();
+    }
+    let frame = read_frame(&mut stream).await;
+    assert_eq!(frame.len(), SAMPLES_PER_FRAME);
+    assert_eq!(frame[0].as_bytes(), sample(0).as_bytes());
+
+    // the socket file is removed when the server is stopped
+    server.abort();
+    let _ = server.await;
+    assert!(!path.exists());
+}
+
+#[tokio::test]
+async fn unix_socket_bind_failures_are_returned() {
+    let path = std::env::temp_dir().join("accel-data-missing-dir/accel.sock");
+    let (sink, _) = broadcast::channel(16);
+    let server = uds_server(
+        path,
+        Arc::new(AtomicBool::new(true)),
+        sink,
+        ServerConfig::default(),
+    );
+    let e = tokio::time::timeout(Duration::from_secs(2), server)
+        .await
+        .expect("server kept running")
+        .unwrap_err();
+    assert_eq!(e.kind(), std::io::ErrorKind::NotFound);
+}
+
+/// Send `hello` to the UDP server until it answers, giving up after 20 tries
+async fn udp_answered(client: &tokio::net::UdpSocket, port: u16, hello: &[u8]) -> bool {
+    let mut buf = [0u8; 2048];
+    for _ in 0..20 {
+        let _ = client.send_to(hello, ("127.0.0.1", port)).await;
+        let recv = tokio::time::timeout(Duration::from_millis(20), client.recv_from(&mut buf));
+        if let Ok(Ok(_)) = recv.await {
+            return true;
+        }
+    }
+    false
+}
+
+#[tokio::test]
+async fn clients_must_present_the_auth_token() {
+    let mut config = ServerConfig::default();
+    config.auth_token("s3cret");
+    let (port, sink) = start_server_with(config).await;
+
+    // a wrong token is disconnected before any byte of the stream
+    let mut stream = connect_raw(port).await;
+    stream.write_all(b"secret\n").await.unwrap();
+    let mut buf = [0u8; STREAM_HEADER_LEN];
+    assert_eq!(stream.read(&mut buf).await.unwrap_or(0), 0);
+
+    let mut stream = connect_raw(port).await;
+    stream.write_all(b"s3cret\n").await.unwrap();
+    stream.read_exact(&mut buf).await.unwrap();
+    assert_eq!(check_header(&buf), Ok(STREAM_HEADER_LEN));
+    wait_for_clients(&sink, 1).await;
+    for i in 0..=SAMPLES_PER_FRAME as u32 {
+        sink.send(sample(i)).unwrap();
+    }
+    assert_eq!(read_frame(&mut stream).await.len(), SAMPLES_PER_FRAME);
+}
+
+#[tokio::test]
+async fn udp_hellos_must_carry_the_auth_token() {
+    let port = std::net::UdpSocket::bind("127.0.0.1:0")
+        .and_then(|socket| socket.local_addr())
+        .expect("no free port")
+        .port();
+    let (sink, _) = broadcast::channel(1024);
+    let mut config = ServerConfig::default();
+    config
+        .handshake(Handshake::new(Vec::new()))
+        .auth_token("s3cret");
+    tokio::spawn(udp_server(
+        port,
+        Arc::new(AtomicBool::new(true)),
+        sink.clone(),
+        config,
+    ));
+    let client = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    assert!(!udp_answered(&client, port, UDP_HELLO).await);
+    assert!(!udp_answered(&client, port, b"hello secret").await);
+    assert!(udp_answered(&client, port, b"hello s3cret").await);
+}
+
+#[tokio::test]
+async fn clients_may_request_compact_frames() {
+    let (port, sink) = start_server().await;
+    let mut stream = connect(port).await;
+    stream
+        .write_all(format!("{COMPACT_REQUEST}\n").as_bytes())
+        .await
+        .unwrap();
+    wait_for_clients(&sink, 1).await;
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    // 26-byte records fit one more sample in the same frame size
+    let per_frame = SAMPLES_PER_FRAME + 1;
+    for i in 0..=per_frame as u32 {
+        sink.send(sample(i)).unwrap();
+    }
+    let bytes = read_raw_frame(&mut stream).await;
+    let (frame, _) = Frame::parse(&bytes).unwrap();
+    assert_eq!(FrameKind::from_u8(frame.kind), Some(FrameKind::Compact));
+    let samples = frame.samples().unwrap();
+    assert_eq!(samples.len(), per_frame);
+    // the largest axis value sets the scale of the batch
+    let scale = CompactAccelData::scale_for(&samples);
+    for (i, data) in samples.iter().enumerate() {
+        let expected = sample(i as u32);
+        assert_eq!(
+            ({ data.idx }, { data.seq }, { data.gap }, { data.time }),
+            ({ expected.idx }, { expected.seq }, { expected.gap }, {
+                expected.time
+            })
+        );
+        for (value, expected) in [
+            (data.x, expected.x),
+            (data.y, expected.y),
+            (data.z, expected.z),
+        ] {
+            assert!((value - expected).abs() <= scale, "{value} != {expected}");
+        }
+    }
+}
+
+#[tokio::test]
+async fn connected_clients_are_counted() {
+    let clients = Arc::new(AtomicUsize::new(0));
+    let mut config = ServerConfig::default();
+    config.clients(clients.clone());
+    let (port, sink) = start_server_with(config).await;
+    let wait_for_count = async |count: usize| {
+        for _ in 0..200 {
+            if clients.load(Ordering::Relaxed) == count {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("expected {count} clients");
+    };
+
+    let first = connect(port).await;
+    let second = connect(port).await;
+    wait_for_clients(&sink, 2).await;
+    wait_for_count(2).await;
+    drop(first);
+    // noticed by the read of the disconnected stream
+    wait_for_count(1).await;
+    drop(second);
+    wait_for_count(0).await;
+}
+
+#[tokio::test]
+async fn lagging_clients_are_told_of_skipped_samples() {
+    let mut config = ServerConfig::default();
+    config.errors(true);
+    let (port, sink) = start_server_with(config).await;
+    let mut stream = connect(port).await;
+    wait_for_clients(&sink, 1).await;
+
+    // the server task does not run while the channel of 1024 overflows
+    for i in 0..1100 {
+        sink.send(sample(i)).unwrap();
+    }
+    let status = loop {
+        let bytes = read_raw_frame(&mut stream).await;
+        let (frame, _) = Frame::parse(&bytes).unwrap();
+        if let Some(status) = frame.status() {
+            break status;
+        }
+    };
+    let Status::Error {
+        idx, kind, count, ..
+    } = status
+    else {
+        panic!("unexpected {status:?}");
+    };
+    assert_eq!((idx, kind, count), (None, ErrorKind::Overrun, 1100 - 1024));
+}
@@ -0,0 +1,254 @@
+//! End-to-end tests of the WebSocket server
+mod common;
+
+use accel_data::{
+    AccelData, CommandRequest, ErrorKind, History, ServerConfig, Status, wsock_server,
+};
+use common::{sample, wait_for_clients};
+use futures_util::{SinkExt, StreamExt};
+use std::{
+    sync::{Arc, atomic::AtomicBool},
+    time::Duration,
+};
+use tokio::{
+    net::TcpStream,
+    sync::{broadcast, mpsc},
+};
+use tokio_tungstenite::{
+    MaybeTlsStream, WebSocketStream,
+    tungstenite::{Error, Message, client::IntoClientRequest, protocol::frame::coding::CloseCode},
+};
+
+/// Start a WebSocket server on a free port
+async fn start_server(config: ServerConfig) -> (u16, broadcast::Sender<AccelData>) {
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .expect("no free port")
+        .port();
+    let (sink, _) = broadcast::channel(1024);
+    tokio::spawn(wsock_server(
+        port,
+        Arc::new(AtomicBool::new(true)),
+        sink.clone(),
+        config,
+    ));
+    (port, sink)
+}
+
+/// Connect to the server, retrying until it is listening
+async fn connect(port: u16, query: &str) -> WebSocketStream<MaybeTlsStream<TcpStream>> {
+    let url = format!("ws://127.0.0.1:{port}/{query}");
+    for _ in 0..100 {
+        if let Ok((stream, _)) = tokio_tungstenite::connect_async(url.as_str()).await {
+            return stream;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("server not listening on port {port}");
+}
+
+/// Read messages until the server closes the connection, returning the close
+/// code if it sent one
+async fn wait_for_close(ws: &mut WebSocketStream<MaybeTlsStream<TcpStream>>) -> Option<CloseCode> {
+    loop {
+        match ws.next().await {
+            Some(Ok(Message::Close(frame))) => return frame.map(|frame| frame.code),
+            Some(Ok(_)) => {}
+            Some(Err(_)) | None => return None,
+        }
+    }
+}
+
+#[tokio::test]
+async fn history_is_replayed_before_new_samples() {
+    let history = History::new(4);
+    for i in 0..10 {
+        history.push(sample(i));
+    }
+    let mut config = ServerConfig::default();
+    config.history(history);
+    let (port, sink) = start_server(config).await;
+    let mut ws = connect(port, "?replay=3").await;
+    let mut next = async || match ws.next().await {
+        Some(Ok(Message::Text(text))) => serde_json::from_str::<Vec<AccelData>>(&text).unwrap(),
+        other => panic!("unexpected message {other:?}"),
+    };
+
+    // the last 3 samples of each sensor, with their original seq and time
+    let replayed = next().await;
+    let expected: Vec<_> = (4..10).map(sample).collect();
+    assert_eq!(replayed.len(), expected.len());
+    for (data, expected) in replayed.iter().zip(&expected) {
+        assert_eq!(data.as_bytes(), expected.as_bytes());
+    }
+
+    // samples already replayed are not sent again
+    wait_for_clients(&sink, 1).await;
+    for i in 6..400 {
+        sink.send(sample(i)).unwrap();
+    }
+    let live = next().await;
+    assert_eq!(live[0].as_bytes(), sample(10).as_bytes());
+}
+
+#[tokio::test]
+async fn malformed_messages_close_the_connection() {
+    let mut config = ServerConfig::default();
+    config.max_message_size(1024);
+    let (port, _sink) = start_server(config).await;
+
+    let mut ws = connect(port, "").await;
+    ws.send(Message::from(r#"{"type":"subscr"#)).await.unwrap();
+    assert_eq!(wait_for_close(&mut ws).await, Some(CloseCode::Invalid));
+
+    let mut ws = connect(port, "").await;
+    let long = format!(r#"{{"type":"subscribe","channel":"{}"}}"#, "x".repeat(2048));
+    // the server may reset the connection before the whole message is sent
+    let _ = ws.send(Message::from(long)).await;
+    let code = wait_for_close(&mut ws).await;
+    assert!(matches!(code, Some(CloseCode::Size) | None), "{code:?}");
+
+    // the server keeps serving
+    let mut ws = connect(port, "").await;
+    ws.send(Message::from(r#"{"type":"subscribe","channel":"raw"}"#))
+        .await
+        .unwrap();
+    ws.close(None).await.unwrap();
+    assert_eq!(wait_for_close(&mut ws).await, None);
+}
+
+#[tokio::test]
+async fn lagging_clients_skip_samples_and_stay_connected() {
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .expect("no free port")
+        .port();
+    let (sink, _) = broadcast::channel(16);
+    let mut config = ServerConfig::default();
+    config.client_buffer(16);
+    tokio::spawn(wsock_server(
+        port,
+        Arc::new(AtomicBool::new(true)),
+        sink.clone(),
+        config,
+    ));
+    let mut ws = connect(port, "").await;
+    wait_for_clients(&sink, 1).await;
+
+    // more samples at once than the channel and the client buffer hold
+    for i in 0..1000 {
+        sink.send(sample(2 * i)).unwrap();
+    }
+    // then enough for the server to send a full message
+    for i in 1000..1200 {
+        sink.send(sample(2 * i)).unwrap();
+        tokio::task::yield_now().await;
+    }
+    let mut seqs = Vec::new();
+    while seqs.len() < 100 {
+        match ws.next().await {
+            Some(Ok(Message::Text(text))) => {
+                let data = serde_json::from_str::<Vec<AccelData>>(&text).unwrap();
+                seqs.extend(data.iter().map(|data| data.seq));
+            }
+            other => panic!("unexpected message {other:?}"),
+        }
+    }
+    // the oldest samples were skipped, the newest ones still arrive in order
+    assert!(seqs[0] >= 32, "{seqs:?}");
+    assert!(seqs.windows(2).all(|pair| pair[0] < pair[1]));
+}
+
+#[tokio::test]
+async fn upgrades_without_the_auth_token_are_refused() {
+    let mut config = ServerConfig::default();
+    config.auth_token("s3cret");
+    let (port, sink) = start_server(config).await;
+
+    // the query parameter or a bearer header
+    let mut ws = connect(port, "?token=s3cret").await;
+    wait_for_clients(&sink, 1).await;
+    let mut request = format!("ws://127.0.0.1:{port}/")
+        .into_client_request()
+        .unwrap();
+    request
+        .headers_mut()
+        .insert("Authorization", "Bearer s3cret".parse().unwrap());
+    let (mut bearer, _) = tokio_tungstenite::connect_async(request).await.unwrap();
+    wait_for_clients(&sink, 2).await;
+
+    for query in ["", "?token=secret", "?replay=10&token="] {
+        let url = format!("ws://127.0.0.1:{port}/{query}");
+        match tokio_tungstenite::connect_async(url).await {
+            Err(Error::Http(response)) => assert_eq!(response.status(), 401),
+            other => panic!("expected a refusal for `{query}`, got {other:?}"),
+        }
+    }
+
+    ws.close(None).await.unwrap();
+    bearer.close(None).await.unwrap();
+}
+
+#[tokio::test]
+async fn partial_messages_are_sent_on_the_latency_deadline() {
+    let mut config = ServerConfig::default();
+    config.max_latency(Duration::from_millis(50));
+    let (port, sink) = start_server(config).await;
+    let mut ws = connect(port, "").await;
+    wait_for_clients(&sink, 1).await;
+
+    // far fewer samples than fill a message
+    for i in 0..3 {
+        sink.send(sample(i)).unwrap();
+    }
+    let msg = tokio::time::timeout(Duration::from_secs(2), ws.next())
+        .await
+        .expect("partial message not flushed");
+    let samples = match msg {
+        Some(Ok(Message::Text(text))) => serde_json::from_str::<Vec<AccelData>>(&text).unwrap(),
+        other => panic!("unexpected message {other:?}"),
+    };
+    assert_eq!(samples.len(), 3);
+}
+
+#[tokio::test]
+async fn failed_commands_are_reported_to_their_client() {
+    let (tx, mut rx) = mpsc::channel::<CommandRequest>(8);
+    // reject every command, as the daemon does an invalid one
+    tokio::spawn(async move {
+        while let Some(request) = rx.recv().await {
+            request.reject("invalid range: 3 g");
+        }
+    });
+    let mut config = ServerConfig::default();
+    config.commands(tx);
+    let (port, _sink) = start_server(config).await;
+    let mut ws = connect(port, "").await;
+    let next_status = async |ws: &mut WebSocketStream<_>| match ws.next().await {
+        Some(Ok(Message::Text(text))) => serde_json::from_str::<Status>(&text).unwrap(),
+        other => panic!("unexpected message {other:?}"),
+    };
+    let command = Message::from(r#"{"type":"command","cmd":"set_range","idx":1,"g":3}"#);
+
+    // not an admin yet
+    ws.send(command.clone()).await.unwrap();
+    let Status::Error {
+        idx, kind, message, ..
+    } = next_status(&mut ws).await
+    else {
+        panic!("expected an error record");
+    };
+    assert_eq!((idx, kind), (Some(1), ErrorKind::CommandFailed));
+    assert_eq!(message, "not an admin client");
+
+    ws.send(Message::from(r#"{"type":"admin"}"#)).await.unwrap();
+    ws.send(command).await.unwrap();
+    let Status::Error {
+        idx, kind, message, ..
+    } = next_status(&mut ws).await
+    else {
+        panic!("expected an error record");
+    };
+    assert_eq!((idx, kind), (Some(1), ErrorKind::CommandFailed));
+    assert_eq!(message, "invalid range: 3 g");
+}
@@ -0,0 +1,81 @@
+//! Collects samples through the FIFO at 4 kHz, draining it whenever the
+//! watermark is reached, against `MockSpi` on the host
+//!
+//! ```bash
+//! cargo run -p adxl355 --example fifo_logger --features mock
+//! ```
+//!
+//! On hardware the FIFO_FULL flag would be routed to INT1 and the drain loop
+//! run from its interrupt. Here every STATUS read advances the mock by a
+//! few output periods instead, and the loop polls STATUS.
+
+use std::convert::TryInto;
+
+use adxl355::{Adxl355, Config, FifoEntry, I32x3, MockSpi, Register, FIFO_SET_LEN, ODR_LPF};
+
+/// FIFO_SAMPLES watermark in axis words: 16 sample sets of the 32 the FIFO
+/// holds, leaving room for the samples arriving while it is drained
+const WATERMARK: u8 = 48;
+/// INT_MAP: FIFO_FULL on INT1
+const FULL_EN1: u8 = 0x02;
+/// STATUS: the watermark is reached
+const FIFO_FULL: u8 = 0x02;
+/// STATUS: samples were lost because the FIFO was full
+const FIFO_OVR: u8 = 0x04;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // a ramp on x with 1g on z, in counts at the default ±2g range
+    let samples = (0..64).map(|i| I32x3::new(i * 1000, 0, 256_000));
+    let mut spi = MockSpi::new(samples);
+    // samples produced between two STATUS polls
+    spi.fifo_rate(4);
+
+    let mut accel = Adxl355::new(spi, Config::default().odr(ODR_LPF::ODR_4000_Hz))?;
+    // the watermark is configured in standby, before starting
+    accel.write_register(Register::FIFO_SAMPLES, WATERMARK)?;
+    accel.write_register(Register::INT_MAP, FULL_EN1)?;
+    accel.start()?;
+
+    let scale = accel.scale();
+    let mut buf = [0u8; 32 * FIFO_SET_LEN];
+    let mut collected = 0;
+    while collected < 200 {
+        // stands in for waiting on the watermark interrupt
+        let status = accel.read_register(Register::STATUS)?;
+        if status & FIFO_OVR != 0 {
+            println!("FIFO overrun, samples were lost");
+        }
+        if status & FIFO_FULL == 0 {
+            continue;
+        }
+        // only read whole sample sets, the rest stays for the next drain
+        let sets = accel.fifo_entries()? as usize / 3;
+        if sets == 0 {
+            continue;
+        }
+        let len = accel.read_fifo(&mut buf[..sets * FIFO_SET_LEN])?;
+        for set in buf[..len].chunks_exact(FIFO_SET_LEN) {
+            match FifoEntry::decode(set.try_into()?) {
+                FifoEntry::Sample(v) => {
+                    collected += 1;
+                    let (x, y, z) = (v.x as f32 * scale, v.y as f32 * scale, v.z as f32 * scale);
+                    println!("{collected:4}: {x:+.4} {y:+.4} {z:+.4}");
+                }
+                // drained faster than the device filled it
+                FifoEntry::Empty => break,
+                FifoEntry::Error => {
+                    // lost alignment with the sample sets: discard what is
+                    // queued and start again from a fresh set
+                    println!("misaligned FIFO read, draining");
+                    while accel.fifo_entries()? > 0 {
+                        accel.read_fifo_entry()?;
+                    }
+                    break;
+                }
+            }
+        }
+    }
+    accel.stop()?;
+    println!("samples produced: {}", accel.release().samples_read());
+    Ok(())
+}
@@ -0,0 +1,38 @@
+//! Drives the driver against `MockSpi` on the host
+//!
+//! ```bash
+//! cargo run -p adxl355 --example mock --features mock
+//! ```
+
+use adxl355::{Accelerometer, Adxl355, Config, I32x3, MockSpi, Range, ODR_LPF};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // one period of a ±0.5g sine on x with 1g on z, in counts at ±4g
+    let one_g = 524_287.0 / 4.096;
+    let samples = (0..8).map(|i| {
+        let phase = i as f32 * std::f32::consts::PI / 4.0;
+        I32x3::new((0.5 * phase.sin() * one_g) as i32, 0, one_g as i32)
+    });
+    let mut spi = MockSpi::new(samples);
+    // a warmer die: 1885 - 9.05 * 10 LSB
+    spi.set_register(0x06, 0x07).set_register(0x07, 0x02);
+
+    let mut accel = Adxl355::new(
+        spi,
+        Config::default()
+            .range(Range::_4G)
+            .odr(ODR_LPF::ODR_125_Hz)
+            .verify(true),
+    )?;
+    accel.start()?;
+    println!("ids: {:?}", accel.read_ids()?);
+    println!("config: {:?}", accel.dump_config()?);
+    println!("temperature: {:.1} C", accel.read_temp_celsius()?);
+    for _ in 0..8 {
+        let v = accel.accel_norm().map_err(|e| format!("{e:?}"))?;
+        println!("{:+.3} {:+.3} {:+.3}", v.x, v.y, v.z);
+    }
+    let spi = accel.release();
+    println!("samples read: {}", spi.samples_read());
+    Ok(())
+}
@@ -0,0 +1,100 @@
+//! Temperature compensation of the sensitivity
+
+/// Largest number of points in a [`SensitivityTable`]
+pub const MAX_TABLE_POINTS: usize = 8;
+
+/// Sensitivity of the sensor against die temperature, as a piecewise linear
+/// curve through up to [`MAX_TABLE_POINTS`] points, e.g. read off the
+/// typical sensitivity change curves of the datasheet or measured on a
+/// calibration run.
+///
+/// Each point is a temperature in °C and the gain at that temperature
+/// relative to the nominal sensitivity: `1.002` for a sensor reading 0.2%
+/// high. Gains between points are interpolated linearly, gains outside the
+/// points are those of the first and last point. The empty table, the
+/// default, is a gain of 1 at every temperature.
+///
+/// ```
+/// use adxl355::SensitivityTable;
+///
+/// // reads 0.1% low at -40°C and 0.2% high at 85°C
+/// let table = SensitivityTable::new(&[(-40.0, 0.999), (25.0, 1.0), (85.0, 1.002)]).unwrap();
+/// assert_eq!(table.gain(25.0), 1.0);
+/// assert!((table.gain(55.0) - 1.001).abs() < 1e-6);
+/// assert_eq!(table.gain(-60.0), 0.999);
+/// assert_eq!(table.gain(100.0), 1.002);
+/// assert_eq!(SensitivityTable::IDENTITY.gain(100.0), 1.0);
+///
+/// // temperatures must be strictly ascending and gains positive
+/// assert!(SensitivityTable::new(&[(25.0, 1.0), (25.0, 1.001)]).is_none());
+/// assert!(SensitivityTable::new(&[(25.0, 0.0)]).is_none());
+/// assert!(SensitivityTable::new(&[(0.0, 1.0); 9]).is_none());
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SensitivityTable {
+    points: [(f32, f32); MAX_TABLE_POINTS],
+    len: usize,
+}
+
+impl SensitivityTable {
+    /// Gain of 1 at every temperature
+    pub const IDENTITY: SensitivityTable = SensitivityTable {
+        points: [(0.0, 1.0); MAX_TABLE_POINTS],
+        len: 0,
+    };
+
+    /// Table through `points` of (temperature in °C, relative gain). Returns
+    /// `None` for more than [`MAX_TABLE_POINTS`] points, temperatures that
+    /// are not strictly ascending or gains that are not positive and finite
+    pub fn new(points: &[(f32, f32)]) -> Option<SensitivityTable> {
+        if points.len() > MAX_TABLE_POINTS {
+            return None;
+        }
+        let ascending = points.windows(2).all(|pair| pair[0].0 < pair[1].0);
+        let valid = points
+            .iter()
+            .all(|&(temp, gain)| temp.is_finite() && gain.is_finite() && gain > 0.0);
+        if !ascending || !valid {
+            return None;
+        }
+        let mut table = SensitivityTable::IDENTITY;
+        table.points[..points.len()].copy_from_slice(points);
+        table.len = points.len();
+        Some(table)
+    }
+
+    /// Points of the table
+    pub fn points(&self) -> &[(f32, f32)] {
+        &self.points[..self.len]
+    }
+
+    /// Relative gain at `temp` °C
+    pub fn gain(&self, temp: f32) -> f32 {
+        let points = self.points();
+        let (first, last) = match (points.first(), points.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return 1.0,
+        };
+        if temp <= first.0 {
+            return first.1;
+        }
+        if temp >= last.0 {
+            return last.1;
+        }
+        points
+            .windows(2)
+            .find(|pair| temp <= pair[1].0)
+            .map(|pair| {
+                let ((t0, g0), (t1, g1)) = (pair[0], pair[1]);
+                g0 + (g1 - g0) * (temp - t0) / (t1 - t0)
+            })
+            .unwrap_or(last.1)
+    }
+}
+
+impl Default for SensitivityTable {
+    fn default() -> Self {
+        SensitivityTable::IDENTITY
+    }
+}
@@ -1,6 +1,53 @@
 #![allow(non_camel_case_types)]
 
-#[derive(Copy, Clone, Debug, Default)]
+use core::convert::TryFrom;
+use core::fmt;
+use core::str::FromStr;
+
+use crate::hal::{delay::DelayNs, spi::SpiBus};
+use crate::trace::Tracer;
+use crate::{Adxl355, Adxl355Error, DeviceConfig, SensitivityTable, TraceHook};
+
+/// Error parsing a configuration value from a string
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ParseConfigError;
+
+impl fmt::Display for ParseConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid configuration value")
+    }
+}
+
+impl core::error::Error for ParseConfigError {}
+
+/// Error decoding a configuration value from a register: the contained bit
+/// pattern is reserved or invalid
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct InvalidRegisterValue(pub u8);
+
+impl fmt::Display for InvalidRegisterValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid register value {:#04x}", self.0)
+    }
+}
+
+impl core::error::Error for InvalidRegisterValue {}
+
+/// Strips a case-insensitive unit suffix and surrounding whitespace
+fn strip_unit<'a>(s: &'a str, unit: &str) -> &'a str {
+    let s = s.trim();
+    let split = s.len().saturating_sub(unit.len());
+    match (s.get(..split), s.get(split..)) {
+        (Some(value), Some(suffix)) if suffix.eq_ignore_ascii_case(unit) => value.trim_end(),
+        _ => s,
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Range {
     #[default]
     _2G = 0b01,
@@ -12,8 +59,121 @@ impl Range {
     pub fn val(self) -> u8 {
         self as u8
     }
+
+    /// Every range, smallest first
+    ///
+    /// ```
+    /// use adxl355::Range;
+    ///
+    /// assert_eq!(Range::all().len(), 3);
+    /// assert!(Range::all().windows(2).all(|w| f32::from(w[0]) < f32::from(w[1])));
+    /// ```
+    pub const fn all() -> &'static [Range] {
+        &[Range::_2G, Range::_4G, Range::_8G]
+    }
+}
+
+/// Decodes the range bits of the RANGE register, the inverse of
+/// [`Range::val`]
+///
+/// ```
+/// use adxl355::Range;
+/// use core::convert::TryFrom;
+///
+/// for &range in Range::all() {
+///     assert_eq!(Range::try_from(range.val()), Ok(range));
+/// }
+/// assert!(Range::try_from(0b00).is_err());
+/// ```
+impl TryFrom<u8> for Range {
+    type Error = InvalidRegisterValue;
+
+    fn try_from(val: u8) -> Result<Self, Self::Error> {
+        match val {
+            0b01 => Ok(Range::_2G),
+            0b10 => Ok(Range::_4G),
+            0b11 => Ok(Range::_8G),
+            _ => Err(InvalidRegisterValue(val)),
+        }
+    }
 }
 
+impl Range {
+    /// Nominal full scale in g
+    fn nominal_g(self) -> u8 {
+        match self {
+            Range::_2G => 2,
+            Range::_4G => 4,
+            Range::_8G => 8,
+        }
+    }
+
+    /// Largest raw reading in counts, 2^19 - 1 for the 20-bit output. The
+    /// same for every range: the sensitivity scales with the range instead
+    pub const fn full_scale_counts(self) -> u32 {
+        crate::ACCEL_MAX_I20
+    }
+
+    /// Sensitivity in counts per g (datasheet typical values)
+    ///
+    /// ```
+    /// use adxl355::Range;
+    ///
+    /// for (range, lsb_per_g) in [(Range::_2G, 256_000), (Range::_4G, 128_000), (Range::_8G, 64_000)] {
+    ///     assert_eq!(range.sensitivity_lsb_per_g(), lsb_per_g);
+    ///     // a full scale reading is the actual full scale in g
+    ///     let full_scale = range.full_scale_counts() as f32 / lsb_per_g as f32;
+    ///     assert!((full_scale / f32::from(range) - 1.0).abs() < 1e-5);
+    /// }
+    /// ```
+    pub const fn sensitivity_lsb_per_g(self) -> u32 {
+        match self {
+            Range::_2G => 256_000,
+            Range::_4G => 128_000,
+            Range::_8G => 64_000,
+        }
+    }
+}
+
+/// Displays the nominal range, e.g. `±2 g`
+impl fmt::Display for Range {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "±{} g", self.nominal_g())
+    }
+}
+
+/// Parses a nominal range such as `±2 g`, `2g` or `2`
+///
+/// ```
+/// use adxl355::Range;
+///
+/// for &range in Range::all() {
+///     let parsed: Range = range.to_string().parse().unwrap();
+///     assert_eq!(parsed.val(), range.val());
+/// }
+/// assert_eq!("4g".parse::<Range>().unwrap().val(), Range::_4G.val());
+/// assert!("3 g".parse::<Range>().is_err());
+/// ```
+impl FromStr for Range {
+    type Err = ParseConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let s = s
+            .strip_prefix('±')
+            .or_else(|| s.strip_prefix("+-"))
+            .unwrap_or(s);
+        match strip_unit(s, "g") {
+            "2" => Ok(Range::_2G),
+            "4" => Ok(Range::_4G),
+            "8" => Ok(Range::_8G),
+            _ => Err(ParseConfigError),
+        }
+    }
+}
+
+/// Actual full scale in g, about [`Range::full_scale_counts`] divided by
+/// [`Range::sensitivity_lsb_per_g`]
 impl From<Range> for f32 {
     fn from(range: Range) -> f32 {
         match range {
@@ -24,7 +184,9 @@ impl From<Range> for f32 {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Output data rate (odr) and Low pass filter corner frequency (lpf)
 #[derive(Default)]
 pub enum ODR_LPF {
@@ -57,6 +219,107 @@ impl ODR_LPF {
     pub fn val(self) -> u8 {
         self as u8
     }
+
+    /// Every output data rate, fastest first, in the order of [`ODR_LPF::val`]
+    ///
+    /// ```
+    /// use adxl355::ODR_LPF;
+    ///
+    /// assert_eq!(ODR_LPF::all().len(), 11);
+    /// for (val, odr) in ODR_LPF::all().iter().enumerate() {
+    ///     assert_eq!(odr.val(), val as u8);
+    /// }
+    /// ```
+    pub const fn all() -> &'static [ODR_LPF] {
+        &[
+            ODR_LPF::ODR_4000_Hz,
+            ODR_LPF::ODR_2000_Hz,
+            ODR_LPF::ODR_1000_Hz,
+            ODR_LPF::ODR_500_Hz,
+            ODR_LPF::ODR_250_Hz,
+            ODR_LPF::ODR_125_Hz,
+            ODR_LPF::ODR_62_5_Hz,
+            ODR_LPF::ODR_31_25_Hz,
+            ODR_LPF::ODR_15_625_Hz,
+            ODR_LPF::ODR_7_813_Hz,
+            ODR_LPF::ODR_3_906_Hz,
+        ]
+    }
+
+    /// -3 dB corner of the low pass filter in Hz (datasheet), a quarter of
+    /// the output data rate. Signals above it are attenuated but not removed,
+    /// so content up to the Nyquist rate of half the output data rate still
+    /// reaches the samples.
+    ///
+    /// ```
+    /// use adxl355::ODR_LPF;
+    ///
+    /// let table = [
+    ///     (ODR_LPF::ODR_4000_Hz, 1000.0),
+    ///     (ODR_LPF::ODR_2000_Hz, 500.0),
+    ///     (ODR_LPF::ODR_1000_Hz, 250.0),
+    ///     (ODR_LPF::ODR_500_Hz, 125.0),
+    ///     (ODR_LPF::ODR_250_Hz, 62.5),
+    ///     (ODR_LPF::ODR_125_Hz, 31.25),
+    ///     (ODR_LPF::ODR_62_5_Hz, 15.625),
+    ///     (ODR_LPF::ODR_31_25_Hz, 7.813),
+    ///     (ODR_LPF::ODR_15_625_Hz, 3.906),
+    ///     (ODR_LPF::ODR_7_813_Hz, 1.953),
+    ///     (ODR_LPF::ODR_3_906_Hz, 0.977),
+    /// ];
+    /// for (odr, bandwidth) in table {
+    ///     assert_eq!(odr.bandwidth_hz(), bandwidth);
+    ///     assert!((f32::from(odr) / 4.0 - bandwidth).abs() < 1e-3);
+    /// }
+    /// ```
+    pub fn bandwidth_hz(self) -> f32 {
+        match self {
+            ODR_LPF::ODR_4000_Hz => 1000.0,
+            ODR_LPF::ODR_2000_Hz => 500.0,
+            ODR_LPF::ODR_1000_Hz => 250.0,
+            ODR_LPF::ODR_500_Hz => 125.0,
+            ODR_LPF::ODR_250_Hz => 62.5,
+            ODR_LPF::ODR_125_Hz => 31.25,
+            ODR_LPF::ODR_62_5_Hz => 15.625,
+            ODR_LPF::ODR_31_25_Hz => 7.813,
+            ODR_LPF::ODR_15_625_Hz => 3.906,
+            ODR_LPF::ODR_7_813_Hz => 1.953,
+            ODR_LPF::ODR_3_906_Hz => 0.977,
+        }
+    }
+}
+
+/// Decodes the ODR_LPF bits of the FILTER register, the inverse of
+/// [`ODR_LPF::val`]
+///
+/// ```
+/// use adxl355::ODR_LPF;
+/// use core::convert::TryFrom;
+///
+/// for val in 0..=10 {
+///     assert_eq!(ODR_LPF::try_from(val).map(ODR_LPF::val), Ok(val));
+/// }
+/// assert!(ODR_LPF::try_from(11).is_err());
+/// ```
+impl TryFrom<u8> for ODR_LPF {
+    type Error = InvalidRegisterValue;
+
+    fn try_from(val: u8) -> Result<Self, Self::Error> {
+        Ok(match val {
+            0 => ODR_LPF::ODR_4000_Hz,
+            1 => ODR_LPF::ODR_2000_Hz,
+            2 => ODR_LPF::ODR_1000_Hz,
+            3 => ODR_LPF::ODR_500_Hz,
+            4 => ODR_LPF::ODR_250_Hz,
+            5 => ODR_LPF::ODR_125_Hz,
+            6 => ODR_LPF::ODR_62_5_Hz,
+            7 => ODR_LPF::ODR_31_25_Hz,
+            8 => ODR_LPF::ODR_15_625_Hz,
+            9 => ODR_LPF::ODR_7_813_Hz,
+            10 => ODR_LPF::ODR_3_906_Hz,
+            _ => return Err(InvalidRegisterValue(val)),
+        })
+    }
 }
 
 impl From<ODR_LPF> for f32 {
@@ -77,7 +340,44 @@ impl From<ODR_LPF> for f32 {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+/// Displays the output data rate, e.g. `1000 Hz`
+impl fmt::Display for ODR_LPF {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} Hz", f32::from(*self))
+    }
+}
+
+/// Parses an output data rate such as `1000 Hz`, `62.5hz` or `31.25`
+///
+/// ```
+/// use adxl355::ODR_LPF;
+///
+/// for odr in [ODR_LPF::ODR_4000_Hz, ODR_LPF::ODR_62_5_Hz, ODR_LPF::ODR_3_906_Hz] {
+///     let parsed: ODR_LPF = odr.to_string().parse().unwrap();
+///     assert_eq!(parsed.val(), odr.val());
+/// }
+/// assert_eq!("1000hz".parse::<ODR_LPF>().unwrap().val(), ODR_LPF::ODR_1000_Hz.val());
+/// assert!("1200 Hz".parse::<ODR_LPF>().is_err());
+/// ```
+impl FromStr for ODR_LPF {
+    type Err = ParseConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hz: f32 = strip_unit(s, "Hz").parse().map_err(|_| ParseConfigError)?;
+        ODR_LPF::all()
+            .iter()
+            .copied()
+            .find(|odr| {
+                let nominal = f32::from(*odr);
+                (hz - nominal).abs() <= nominal * 1e-3
+            })
+            .ok_or(ParseConfigError)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// High pass corner frequency is proportional to the output data rate (ODR)
 #[derive(Default)]
 pub enum HPF_CORNER {
@@ -102,12 +402,63 @@ impl HPF_CORNER {
     pub fn val(self) -> u8 {
         self as u8
     }
+
+    /// -3dB corner frequency in Hz at the given output data rate, or `None`
+    /// if the high pass filter is disabled
+    pub fn corner_frequency(self, odr: ODR_LPF) -> Option<f32> {
+        let factor = match self {
+            HPF_CORNER::NONE => return None,
+            HPF_CORNER::_247_ODR => 247e-3,
+            HPF_CORNER::_62_084_ODR => 62.084e-3,
+            HPF_CORNER::_15_545_ODR => 15.545e-3,
+            HPF_CORNER::_3_862_ODR => 3.862e-3,
+            HPF_CORNER::_0_954_ODR => 0.954e-3,
+            HPF_CORNER::_0_238_ODR => 0.238e-3,
+        };
+        Some(factor * f32::from(odr))
+    }
+}
+
+/// Decodes the HPF_CORNER bits of the FILTER register, the inverse of
+/// [`HPF_CORNER::val`]
+///
+/// ```
+/// use adxl355::HPF_CORNER;
+/// use core::convert::TryFrom;
+///
+/// for val in 0..=6 {
+///     assert_eq!(HPF_CORNER::try_from(val).map(HPF_CORNER::val), Ok(val));
+/// }
+/// assert!(HPF_CORNER::try_from(7).is_err());
+/// ```
+impl TryFrom<u8> for HPF_CORNER {
+    type Error = InvalidRegisterValue;
+
+    fn try_from(val: u8) -> Result<Self, Self::Error> {
+        Ok(match val {
+            0 => HPF_CORNER::NONE,
+            1 => HPF_CORNER::_247_ODR,
+            2 => HPF_CORNER::_62_084_ODR,
+            3 => HPF_CORNER::_15_545_ODR,
+            4 => HPF_CORNER::_3_862_ODR,
+            5 => HPF_CORNER::_0_954_ODR,
+            6 => HPF_CORNER::_0_238_ODR,
+            _ => return Err(InvalidRegisterValue(val)),
+        })
+    }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Config {
     pub(crate) range: Option<Range>,
     pub(crate) odr: Option<ODR_LPF>,
     pub(crate) hpf: Option<HPF_CORNER>,
+    pub(crate) verify: bool,
+    pub(crate) sensitivity: SensitivityTable,
+    pub(crate) trace: Tracer,
+    pub(crate) id_attempts: u8,
+    pub(crate) id_interval_us: u32,
 }
 
 impl Default for Config {
@@ -116,6 +467,11 @@ impl Default for Config {
             range: Some(Range::default()),
             odr: Some(ODR_LPF::default()),
             hpf: Some(HPF_CORNER::default()),
+            verify: false,
+            sensitivity: SensitivityTable::IDENTITY,
+            trace: Tracer::default(),
+            id_attempts: 1,
+            id_interval_us: 0,
         }
     }
 }
@@ -144,4 +500,73 @@ impl Config {
         self.hpf = Some(hpf);
         self
     }
+
+    /// Reads the configuration registers back after writing them, failing
+    /// with [`crate::Adxl355Error::VerifyFailed`] on a mismatch
+    ///
+    /// Default is no verification
+    pub fn verify(&mut self, verify: bool) -> &mut Self {
+        self.verify = verify;
+        self
+    }
+
+    /// Sets the temperature dependence of the sensitivity corrected by
+    /// [`crate::Adxl355::accel_norm_table_compensated`]
+    ///
+    /// Default is [`SensitivityTable::IDENTITY`], no correction
+    pub fn sensitivity_table(&mut self, table: SensitivityTable) -> &mut Self {
+        self.sensitivity = table;
+        self
+    }
+
+    /// Traces every transfer of the driver, identification and configuration
+    /// at construction included, see [`crate::Adxl355::set_trace`]
+    ///
+    /// Default is no tracing
+    pub fn trace(&mut self, hook: TraceHook) -> &mut Self {
+        self.trace = Tracer(Some(hook));
+        self
+    }
+
+    /// Reads the identification registers up to `attempts` times at
+    /// construction, waiting `interval_us` between attempts with the delay
+    /// of [`crate::Adxl355::new_with_delay`], so a part still powering up
+    /// does not fail the construction. Without a delay the attempts are back
+    /// to back; 0 attempts count as 1. See [`crate::Adxl355::id_attempts`]
+    ///
+    /// Default is a single attempt
+    pub fn id_attempts(&mut self, attempts: u8, interval_us: u32) -> &mut Self {
+        self.id_attempts = attempts.max(1);
+        self.id_interval_us = interval_us;
+        self
+    }
+
+    /// Reads FILTER and RANGE of a running device into a configuration,
+    /// e.g. to hand a sensor configured by other firmware or surviving a warm
+    /// reset to a fresh driver. Verification is left disabled and the
+    /// sensitivity table, which is not stored on the device, is the identity.
+    pub fn from_registers<SPI, D, E>(
+        device: &mut Adxl355<SPI, D>,
+    ) -> Result<Config, Adxl355Error<E>>
+    where
+        SPI: SpiBus<u8, Error = E>,
+        D: DelayNs,
+    {
+        device.dump_config().map(Config::from)
+    }
+}
+
+impl From<DeviceConfig> for Config {
+    fn from(config: DeviceConfig) -> Self {
+        Self {
+            range: Some(config.range),
+            odr: Some(config.odr),
+            hpf: Some(config.hpf),
+            verify: false,
+            sensitivity: SensitivityTable::IDENTITY,
+            trace: Tracer::default(),
+            id_attempts: 1,
+            id_interval_us: 0,
+        }
+    }
 }
@@ -0,0 +1,84 @@
+//! ADXL355 driver errors
+
+use core::fmt::{self, Debug, Display};
+
+use crate::DeviceIds;
+
+/// Errors returned by the ADXL355 driver
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Adxl355Error<E> {
+    /// Error reported by the underlying SPI bus
+    Spi(E),
+    /// The output buffer cannot hold the requested read
+    BufferTooSmall {
+        /// Minimum number of bytes the read needs
+        required: usize,
+        /// Number of bytes provided by the caller
+        actual: usize,
+    },
+    /// A configuration register did not read back the value written to it
+    VerifyFailed {
+        /// Register address
+        register: u8,
+        /// Value written
+        written: u8,
+        /// Value read back
+        read: u8,
+    },
+    /// A configuration register holds a reserved bit pattern
+    InvalidRegister {
+        /// Register address
+        register: u8,
+        /// Value read
+        value: u8,
+    },
+    /// The identification registers do not read as an ADXL355, most likely
+    /// because of a wrong SPI mode (see [`crate::SPI_MODE`]) or wiring
+    UnexpectedIds(DeviceIds),
+}
+
+impl<E: Debug> Display for Adxl355Error<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Adxl355Error::Spi(e) => write!(f, "SPI bus error: {e:?}"),
+            Adxl355Error::BufferTooSmall { required, actual } => write!(
+                f,
+                "buffer too small: {required} bytes required, {actual} provided"
+            ),
+            Adxl355Error::VerifyFailed {
+                register,
+                written,
+                read,
+            } => write!(
+                f,
+                "register {register:#04x} verification failed: wrote {written:#04x}, read {read:#04x}"
+            ),
+            Adxl355Error::InvalidRegister { register, value } => {
+                write!(f, "register {register:#04x} holds invalid value {value:#04x}")
+            }
+            Adxl355Error::UnexpectedIds(ids) => write!(
+                f,
+                "unexpected device IDs {:#04x} {:#04x} {:#04x} (rev {}), expected 0xad 0x1d 0xed: check the SPI mode and wiring",
+                ids.devid_ad, ids.devid_mst, ids.partid, ids.revid
+            ),
+        }
+    }
+}
+
+/// `std::error::Error` is a re-export of this trait, so errors can be boxed as
+/// `Box<dyn std::error::Error>` with or without the `std` feature.
+impl<E: Debug> core::error::Error for Adxl355Error<E> {}
+
+impl<E: Debug> From<Adxl355Error<E>> for accelerometer::Error<E> {
+    fn from(err: Adxl355Error<E>) -> Self {
+        use accelerometer::error::ErrorKind;
+        match err {
+            Adxl355Error::Spi(e) => accelerometer::Error::new_with_cause(ErrorKind::Bus, e),
+            Adxl355Error::BufferTooSmall { .. } => accelerometer::Error::new(ErrorKind::Param),
+            Adxl355Error::VerifyFailed { .. }
+            | Adxl355Error::InvalidRegister { .. }
+            | Adxl355Error::UnexpectedIds(_) => accelerometer::Error::new(ErrorKind::Device),
+        }
+    }
+}
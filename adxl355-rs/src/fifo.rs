@@ -0,0 +1,73 @@
+//! FIFO sample decoding
+
+use crate::{I32x3, FIFO_SET_LEN};
+
+/// Set in the last byte of an axis word when it holds X-axis data, marking the
+/// start of a sample set
+const X_AXIS_MARKER: u8 = 0x01;
+/// Set in the last byte of an axis word read from an empty FIFO
+const EMPTY_MARKER: u8 = 0x02;
+
+/// Decoded FIFO sample set
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FifoEntry {
+    /// Valid x/y/z sample
+    Sample(I32x3),
+    /// The FIFO was empty when read
+    Empty,
+    /// The read was not aligned to a sample set (the X-axis marker is missing
+    /// or set on the Y/Z axis). The caller should discard the data and
+    /// resynchronize, e.g. by draining the FIFO.
+    Error,
+}
+
+impl FifoEntry {
+    /// Decode one sample set as read from FIFO_DATA
+    ///
+    /// ```
+    /// use adxl355::FifoEntry;
+    ///
+    /// // x = 1, y = -1, z = 0 with the X-axis marker on the first word
+    /// let set = [0x00, 0x00, 0x11, 0xFF, 0xFF, 0xF0, 0x00, 0x00, 0x00];
+    /// match FifoEntry::decode(&set) {
+    ///     FifoEntry::Sample(v) => assert_eq!((v.x, v.y, v.z), (1, -1, 0)),
+    ///     other => panic!("unexpected entry {other:?}"),
+    /// }
+    ///
+    /// // empty marker
+    /// assert_eq!(FifoEntry::decode(&[0, 0, 0x02, 0, 0, 0x02, 0, 0, 0x02]), FifoEntry::Empty);
+    ///
+    /// // misaligned read: the set starts with Y-axis data
+    /// assert_eq!(FifoEntry::decode(&[0; 9]), FifoEntry::Error);
+    /// assert_eq!(FifoEntry::decode(&[0, 0, 0x01, 0, 0, 0x01, 0, 0, 0]), FifoEntry::Error);
+    /// ```
+    pub fn decode(set: &[u8; FIFO_SET_LEN]) -> FifoEntry {
+        let markers = [set[2], set[5], set[8]];
+        if markers.iter().any(|m| m & EMPTY_MARKER != 0) {
+            return FifoEntry::Empty;
+        }
+        if markers[0] & X_AXIS_MARKER == 0
+            || markers[1] & X_AXIS_MARKER != 0
+            || markers[2] & X_AXIS_MARKER != 0
+        {
+            return FifoEntry::Error;
+        }
+        FifoEntry::Sample(decode_sample(set))
+    }
+}
+
+/// Combine 3 bytes into one i32 value,
+/// right-shift with sign-extend to 20-bit
+pub(crate) fn decode_axis(bytes: &[u8]) -> i32 {
+    (((bytes[0] as i32) << 24) | ((bytes[1] as i32) << 16) | (((bytes[2] & 0xF0) as i32) << 8))
+        >> 12
+}
+
+/// Decode a x/y/z sample from 9 consecutive data bytes
+pub(crate) fn decode_sample(bytes: &[u8]) -> I32x3 {
+    I32x3::new(
+        decode_axis(&bytes[0..3]),
+        decode_axis(&bytes[3..6]),
+        decode_axis(&bytes[6..9]),
+    )
+}
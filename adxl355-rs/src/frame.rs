@@ -0,0 +1,102 @@
+//! Data block decoding: STATUS, FIFO_ENTRIES, temperature and a sample read
+//! in one burst
+
+use crate::fifo::decode_sample;
+use crate::register::Register;
+use crate::{temp_celsius, F32x3, I32x3};
+
+/// Bytes in a data block, STATUS to ZDATA1
+pub const FRAME_LEN: usize = 13;
+
+// Offsets of the registers in a data block
+const STATUS: usize = 0;
+const FIFO_ENTRIES: usize = Register::FIFO_ENTRIES as usize - Register::STATUS as usize;
+const TEMP2: usize = Register::TEMP2 as usize - Register::STATUS as usize;
+const XDATA3: usize = Register::XDATA3 as usize - Register::STATUS as usize;
+
+// The block must span STATUS to ZDATA1 with the temperature registers
+// immediately followed by the sample
+const _: () = assert!(Register::ZDATA1 as usize - Register::STATUS as usize + 1 == FRAME_LEN);
+const _: () = assert!(Register::TEMP1 as usize - Register::STATUS as usize == TEMP2 + 1);
+const _: () = assert!(XDATA3 == TEMP2 + 2);
+
+/// STATUS register: a new sample is ready
+const DATA_RDY: u8 = 0x01;
+/// STATUS register: the FIFO is full
+const FIFO_FULL: u8 = 0x02;
+/// STATUS register: samples were lost because the FIFO was full
+const FIFO_OVR: u8 = 0x04;
+
+/// Fully annotated sample, decoded from a data block
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Frame {
+    /// Raw STATUS register
+    pub status: u8,
+    /// Number of entries (axis words, three per sample) in the FIFO
+    pub fifo_entries: u8,
+    /// Raw temperature, see [`crate::Adxl355::read_temp_celsius`]
+    pub temp_raw: u16,
+    /// Raw acceleration
+    pub sample: I32x3,
+}
+
+impl Frame {
+    /// Decode a data block read starting at STATUS
+    ///
+    /// ```
+    /// use adxl355::{Frame, FRAME_LEN};
+    ///
+    /// let mut block = [0u8; FRAME_LEN];
+    /// block[0] = 0x01; // STATUS: data ready
+    /// block[1] = 0x06; // FIFO_ENTRIES: two samples
+    /// block[2..4].copy_from_slice(&[0x07, 0x5D]); // TEMP2/TEMP1: 1885 LSB
+    /// block[4..7].copy_from_slice(&[0x00, 0x00, 0x10]); // x = 1
+    /// block[7..10].copy_from_slice(&[0xFF, 0xFF, 0xF0]); // y = -1
+    /// let frame = Frame::decode(&block);
+    /// assert!(frame.data_ready() && !frame.fifo_overrun());
+    /// assert_eq!(frame.fifo_entries, 6);
+    /// assert_eq!(frame.temperature_celsius(), 25.0);
+    /// assert_eq!((frame.sample.x, frame.sample.y, frame.sample.z), (1, -1, 0));
+    /// ```
+    pub fn decode(block: &[u8; FRAME_LEN]) -> Frame {
+        Frame {
+            status: block[STATUS],
+            fifo_entries: block[FIFO_ENTRIES] & 0x7F,
+            temp_raw: (((block[TEMP2] & 0x0F) as u16) << 8) | block[TEMP2 + 1] as u16,
+            sample: decode_sample(&block[XDATA3..]),
+        }
+    }
+
+    /// A new sample was ready when the block was read
+    pub fn data_ready(&self) -> bool {
+        self.status & DATA_RDY != 0
+    }
+
+    /// The FIFO is full
+    pub fn fifo_full(&self) -> bool {
+        self.status & FIFO_FULL != 0
+    }
+
+    /// Samples were lost because the FIFO was full
+    pub fn fifo_overrun(&self) -> bool {
+        self.status & FIFO_OVR != 0
+    }
+
+    /// Die temperature in degrees Celsius
+    pub fn temperature_celsius(&self) -> f32 {
+        temp_celsius(self.temp_raw)
+    }
+}
+
+/// Calibrated sample, see [`crate::Adxl355::reading`]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Reading {
+    /// Number of readings the driver took before this one
+    pub index: u64,
+    /// Acceleration in g, oriented and compensated
+    pub accel: F32x3,
+    /// Die temperature in degrees Celsius
+    pub temperature: f32,
+    /// A new sample was ready when it was read, see [`Frame::data_ready`]
+    pub fresh: bool,
+}
@@ -0,0 +1,65 @@
+//! Device identification and configuration read back from the registers
+
+use crate::{Range, HPF_CORNER, ODR_LPF};
+
+/// Contents of the identification registers
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceIds {
+    /// Analog Devices ID, `0xAD`
+    pub devid_ad: u8,
+    /// Analog Devices MEMS ID, `0x1D`
+    pub devid_mst: u8,
+    /// Device ID, `0xED` (355 octal)
+    pub partid: u8,
+    /// Mask revision
+    pub revid: u8,
+}
+
+impl DeviceIds {
+    /// Check the fixed IDs of an ADXL355. The mask revision is not checked.
+    pub fn is_adxl355(&self) -> bool {
+        self.devid_ad == 0xAD && self.devid_mst == 0x1D && self.partid == crate::EXPECTED_DEVICE_ID
+    }
+}
+
+/// Configuration currently held by the device registers
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceConfig {
+    /// Output data rate and low pass filter (FILTER register)
+    pub odr: ODR_LPF,
+    /// High pass filter corner (FILTER register)
+    pub hpf: HPF_CORNER,
+    /// Measurement range (RANGE register)
+    pub range: Range,
+}
+
+/// Snapshot of the device state for bug reports, see
+/// [`crate::Adxl355::diagnostics`]
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Diagnostics {
+    /// Identification registers
+    pub ids: DeviceIds,
+    /// Configuration held by the registers
+    pub config: DeviceConfig,
+    /// Raw POWER_CTL register, bit 0 set in standby
+    pub power_ctl: u8,
+    /// Raw STATUS register
+    pub status: u8,
+    /// Number of entries (axis words) in the FIFO
+    pub fifo_entries: u8,
+    /// Die temperature in degrees Celsius
+    pub temperature: f32,
+}
+
+impl Diagnostics {
+    /// The device is in measurement mode
+    pub fn measuring(&self) -> bool {
+        self.power_ctl & 0x01 == 0
+    }
+}
@@ -10,15 +10,14 @@
 //!
 //! # Usage
 //!
-//! Use embedded-hal implementation to get SPI and a GPIO OutputPin for the chip select,
+//! Use embedded-hal implementation to get a SPI bus (with hardware chip select),
 //! then create the accelerometer handle
 //!
-//! ```
-//!
+//! ```ignore
 //! use adxl355::{Adxl355, Config as ADXLConfig, ODR_LPF, Range, Accelerometer};
 //!
 //! // to create sensor with default configuration:
-//! let mut accelerometer = Adxl355::default(spi, cs)?;
+//! let mut accelerometer = Adxl355::default(spi)?;
 //!
 //! // start measurements
 //! accelerometer.start();
@@ -30,8 +29,8 @@
 //! // One can also use conf module to supply configuration:
 //!
 //! let mut accelerometer =
-//!     Adxl355::new(spi, cs,
-//!                     ADXLConfig::new()
+//!     Adxl355::new(spi,
+//!                     ADXLConfig::default()
 //!                     .odr(ODR_LPF::ODR_31_25_Hz)
 //!                     .range(Range::_2G))?;
 //! ```
@@ -48,15 +47,30 @@
 //!
 //!
 
-#![no_std]
+#![cfg_attr(not(feature = "std"), no_std)]
 
+mod compensation;
 mod conf;
+mod errors;
+mod fifo;
+mod frame;
+mod info;
+#[cfg(feature = "mock")]
+mod mock;
+mod orientation;
 mod register;
+#[cfg(feature = "std")]
+mod shared;
+mod trace;
+mod typed;
 
+use core::convert::TryFrom;
 use core::fmt::Debug;
+use core::time::Duration;
 
 use embedded_hal as hal;
 
+use hal::delay::DelayNs;
 use hal::spi::SpiBus;
 
 pub use accelerometer::{
@@ -65,19 +79,98 @@ pub use accelerometer::{
     Accelerometer, Error, RawAccelerometer,
 };
 
+pub use compensation::{SensitivityTable, MAX_TABLE_POINTS};
 pub use conf::*;
-use register::Register;
+pub use errors::Adxl355Error;
+pub use fifo::FifoEntry;
+pub use frame::{Frame, Reading, FRAME_LEN};
+pub use info::{DeviceConfig, DeviceIds, Diagnostics};
+#[cfg(feature = "mock")]
+pub use mock::{MockSpi, MockSpiError};
+pub use orientation::{Axis, AxisTransform};
+pub use register::Register;
+#[cfg(feature = "std")]
+pub use shared::SharedAdxl355;
+pub use trace::{Direction, TraceHook, Transfer};
+pub use typed::{Measuring, PowerState, Standby, Transition, TransitionError, TypedAdxl355};
 
 const SPI_READ: u8 = 0x01;
 const SPI_WRITE: u8 = 0x00;
 
 const EXPECTED_DEVICE_ID: u8 = 0xED;
 
+/// SPI mode of the ADXL355: clock idle low, data captured on the rising edge
+/// (CPOL = 0, CPHA = 0). The bus must be configured with this mode; in any
+/// other mode reads return shifted bits, which [`Adxl355::new`] and
+/// [`Adxl355::attach`] report as [`Adxl355Error::UnexpectedIds`].
+pub const SPI_MODE: hal::spi::Mode = hal::spi::MODE_0;
+
 const ACCEL_MAX_I20: u32 = 524_287; // = 2^(20-1)-1
 
+/// Standard gravity in m/s², as used by [`Adxl355::accel_mps2`]
+pub const STANDARD_GRAVITY: f32 = 9.806_65;
+
+/// Turn-on time from standby to measurement mode (datasheet)
+const TURN_ON_TIME: Duration = Duration::from_millis(10);
+
+/// Output periods needed to flush the decimation/low pass filter
+const LPF_SETTLING_PERIODS: f32 = 16.0;
+
+/// High pass filter time constants to settle within 1% of the final value
+const HPF_SETTLING_TAUS: f32 = 4.6;
+
+/// Convert a raw temperature reading to degrees Celsius
+fn temp_celsius(raw: u16) -> f32 {
+    TEMP_INTERCEPT_C + (raw as f32 - TEMP_INTERCEPT_LSB) / TEMP_SLOPE_LSB_PER_C
+}
+
+/// Nominal temperature sensor intercept (datasheet)
+const TEMP_INTERCEPT_LSB: f32 = 1885.0;
+const TEMP_INTERCEPT_C: f32 = 25.0;
+/// Nominal temperature sensor slope (datasheet)
+const TEMP_SLOPE_LSB_PER_C: f32 = -9.05;
+
+/// Bytes in one FIFO x/y/z sample set (3 bytes per axis)
+pub const FIFO_SET_LEN: usize = 9;
+
+/// Largest single burst transferred over the bus, chosen to hold a whole
+/// number of FIFO sample sets
+const BURST_LEN: usize = 7 * FIFO_SET_LEN;
+
+/// Default and largest transfer length in bytes, a burst and its command
+/// byte, see [`Adxl355::set_max_transfer`]
+pub const MAX_TRANSFER_LEN: usize = BURST_LEN + 1;
+
+/// Smallest transfer length the driver can work with: one FIFO sample set
+/// or a sample of the data registers, and the command byte
+pub const MIN_TRANSFER_LEN: usize = FIFO_SET_LEN + 1;
+
+/// [`DelayNs`] that returns immediately, the delay of a driver created
+/// without [`Adxl355::new_with_delay`]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct NoDelay;
+
+impl DelayNs for NoDelay {
+    fn delay_ns(&mut self, _ns: u32) {}
+}
+
 /// ADXL355 driver
-pub struct Adxl355<SPI> {
+pub struct Adxl355<SPI, D = NoDelay> {
     spi: SPI,
+    delay: D,
+    /// Wait before every transfer in ns, 0 for none
+    settle_ns: u32,
+    /// Longest transfer in bytes, command byte included
+    max_transfer: usize,
+    /// Readings taken by [`Adxl355::reading`]
+    readings: u64,
+    /// Applied to every sample read by accel_raw
+    orientation: AxisTransform,
+    /// Called with every transfer, see [`Adxl355::set_trace`]
+    trace: Option<TraceHook>,
+    sensitivity: SensitivityTable,
+    /// Identification attempts needed at construction
+    id_attempts: u8,
 
     // configuration
     odr: ODR_LPF,
@@ -91,24 +184,74 @@ where
 {
     /// Creates a new `adxl355` driver from a SPI peripheral with
     /// default configuration.
-    pub fn default(spi: SPI) -> Result<Self, E> {
+    pub fn default(spi: SPI) -> Result<Self, Adxl355Error<E>> {
         Adxl355::new(spi, &Config::default())
     }
 
     /// Takes a config object to initialize the adxl355 driver
-    pub fn new(spi: SPI, config: &Config) -> Result<Self, E> {
+    pub fn new(spi: SPI, config: &Config) -> Result<Self, Adxl355Error<E>> {
+        Adxl355::new_with_delay(spi, config, NoDelay, 0)
+    }
+
+    /// Wraps an already configured device, reading its configuration from
+    /// the registers instead of writing it
+    pub fn attach(spi: SPI) -> Result<Self, Adxl355Error<E>> {
         let mut adxl355 = Adxl355 {
             spi,
+            delay: NoDelay,
+            settle_ns: 0,
+            max_transfer: MAX_TRANSFER_LEN,
+            readings: 0,
+            orientation: AxisTransform::IDENTITY,
+            trace: None,
+            sensitivity: SensitivityTable::IDENTITY,
+            id_attempts: 0,
+            odr: ODR_LPF::default(),
+            hpf: HPF_CORNER::default(),
+            range: Range::default(),
+        };
+        adxl355.check_ids(1, 0)?;
+        adxl355.refresh_config()?;
+        Ok(adxl355)
+    }
+}
+
+impl<SPI, D, E> Adxl355<SPI, D>
+where
+    SPI: SpiBus<u8, Error = E>,
+    D: DelayNs,
+{
+    /// Like [`Adxl355::new`], waiting `settle_ns` with `delay` before every
+    /// transfer, the identification reads included.
+    ///
+    /// Only needed for marginal wiring, e.g. long or unterminated traces at
+    /// high SPI clocks where back-to-back transfers read corrupted data or
+    /// [`Adxl355Error::UnexpectedIds`]. The wait separates transfers: the
+    /// chip select setup time and gaps between bytes within a transfer are
+    /// up to the SPI controller, e.g. `word_delay_usecs` of a Linux spidev
+    /// transfer. A `settle_ns` of 0 never calls `delay`.
+    pub fn new_with_delay(
+        spi: SPI,
+        config: &Config,
+        delay: D,
+        settle_ns: u32,
+    ) -> Result<Self, Adxl355Error<E>> {
+        let mut adxl355 = Adxl355 {
+            spi,
+            delay,
+            settle_ns,
+            max_transfer: MAX_TRANSFER_LEN,
+            readings: 0,
+            orientation: AxisTransform::IDENTITY,
+            sensitivity: config.sensitivity,
+            trace: config.trace.0,
+            id_attempts: 0,
             odr: config.odr.unwrap_or_default(),
             hpf: config.hpf.unwrap_or_default(),
             range: config.range.unwrap_or_default(),
         };
 
-        let id = adxl355.get_device_id()?;
-
-        if id != EXPECTED_DEVICE_ID {
-            // error
-        }
+        adxl355.check_ids(config.id_attempts, config.id_interval_us)?;
 
         adxl355.write_reg(
             Register::FILTER.addr(),
@@ -116,54 +259,589 @@ where
         )?;
         adxl355.write_reg(Register::RANGE.addr(), adxl355.range.val())?;
 
+        if config.verify {
+            adxl355.verify_config()?;
+        }
+
         Ok(adxl355)
     }
 
+    /// Re-reads FILTER and RANGE into the cached configuration that
+    /// [`Accelerometer::accel_norm`], [`Adxl355::scale`] and
+    /// [`Adxl355::settling_time`] rely on, e.g. after
+    /// [`Adxl355::write_register`] or another driver changed them
+    pub fn refresh_config(&mut self) -> Result<(), Adxl355Error<E>> {
+        let config = self.dump_config()?;
+        self.odr = config.odr;
+        self.hpf = config.hpf;
+        self.range = config.range;
+        Ok(())
+    }
+
+    /// Reads the identification registers until they match an ADXL355, up
+    /// to `attempts` times `interval_us` apart
+    fn check_ids(&mut self, attempts: u8, interval_us: u32) -> Result<(), Adxl355Error<E>> {
+        let mut attempt = 1;
+        loop {
+            let checked = self.read_ids().and_then(|ids| {
+                if ids.is_adxl355() {
+                    Ok(())
+                } else {
+                    Err(Adxl355Error::UnexpectedIds(ids))
+                }
+            });
+            match checked {
+                Ok(()) => {
+                    self.id_attempts = attempt;
+                    return Ok(());
+                }
+                Err(e) if attempt >= attempts => return Err(e),
+                Err(_) => {
+                    #[cfg(feature = "defmt")]
+                    defmt::debug!("adxl355: identification attempt {=u8} failed", attempt);
+                    self.delay.delay_us(interval_us);
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    /// Identification attempts the construction needed, 1 unless retried
+    /// as set by [`Config::id_attempts`]
+    pub fn id_attempts(&self) -> u8 {
+        self.id_attempts
+    }
+
+    /// Reads the configuration held by the FILTER and RANGE registers
+    pub fn dump_config(&mut self) -> Result<DeviceConfig, Adxl355Error<E>> {
+        let invalid = |register: Register, value| Adxl355Error::InvalidRegister {
+            register: register.addr(),
+            value,
+        };
+        let mut filter = [0u8];
+        self.read_regs(Register::FILTER.addr(), &mut filter)?;
+        let filter = filter[0];
+        let mut range = [0u8];
+        self.read_regs(Register::RANGE.addr(), &mut range)?;
+        let range = range[0];
+        Ok(DeviceConfig {
+            odr: ODR_LPF::try_from(filter & 0x0F).map_err(|_| invalid(Register::FILTER, filter))?,
+            hpf: HPF_CORNER::try_from((filter >> 4) & 0x07)
+                .map_err(|_| invalid(Register::FILTER, filter))?,
+            range: Range::try_from(range & 0x03).map_err(|_| invalid(Register::RANGE, range))?,
+        })
+    }
+
+    /// Reads FILTER and RANGE back and checks they decode to the cached
+    /// configuration
+    fn verify_config(&mut self) -> Result<(), Adxl355Error<E>> {
+        let mut filter = [0u8];
+        self.read_regs(Register::FILTER.addr(), &mut filter)?;
+        let filter = filter[0];
+        let hpf = HPF_CORNER::try_from((filter >> 4) & 0x07);
+        let odr = ODR_LPF::try_from(filter & 0x0F);
+        if hpf != Ok(self.hpf) || odr != Ok(self.odr) {
+            return Err(Adxl355Error::VerifyFailed {
+                register: Register::FILTER.addr(),
+                written: (self.hpf.val() << 4) | self.odr.val(),
+                read: filter,
+            });
+        }
+
+        let mut range = [0u8];
+        self.read_regs(Register::RANGE.addr(), &mut range)?;
+        let range = range[0];
+        if Range::try_from(range & 0x03) != Ok(self.range) {
+            return Err(Adxl355Error::VerifyFailed {
+                register: Register::RANGE.addr(),
+                written: self.range.val(),
+                read: range,
+            });
+        }
+        Ok(())
+    }
+
+    /// Destroys the driver and returns the SPI bus, e.g. to hand it to a
+    /// fresh instance or another device.
+    ///
+    /// No registers are written: a started device keeps measuring. Call
+    /// [`Adxl355::stop`] first to leave it in standby.
+    pub fn release(self) -> SPI {
+        self.spi
+    }
+
     /// Puts the device in `Measurement mode`. The defaut after power up is `Standby mode`.
-    pub fn start(&mut self) -> Result<(), E> {
+    pub fn start(&mut self) -> Result<(), Adxl355Error<E>> {
         self.write_reg(Register::POWER_CTL.addr(), 0)
     }
 
+    /// Puts the device in `Standby mode`, where configuration registers can be
+    /// written safely.
+    pub fn stop(&mut self) -> Result<(), Adxl355Error<E>> {
+        self.write_reg(Register::POWER_CTL.addr(), 1)
+    }
+
+    /// Sets the output data rate and low pass filter.
+    ///
+    /// The device should be in `Standby mode` (see [`Adxl355::stop`]).
+    pub fn set_odr(&mut self, odr: ODR_LPF) -> Result<(), Adxl355Error<E>> {
+        self.write_reg(Register::FILTER.addr(), (self.hpf.val() << 4) | odr.val())?;
+        self.odr = odr;
+        Ok(())
+    }
+
+    /// Sets the high pass filter corner.
+    ///
+    /// The device should be in `Standby mode` (see [`Adxl355::stop`]).
+    pub fn set_hpf(&mut self, hpf: HPF_CORNER) -> Result<(), Adxl355Error<E>> {
+        self.write_reg(Register::FILTER.addr(), (hpf.val() << 4) | self.odr.val())?;
+        self.hpf = hpf;
+        Ok(())
+    }
+
+    /// Sets the measurement range.
+    ///
+    /// The device should be in `Standby mode` (see [`Adxl355::stop`]).
+    pub fn set_range(&mut self, range: Range) -> Result<(), Adxl355Error<E>> {
+        self.write_reg(Register::RANGE.addr(), range.val())?;
+        self.range = range;
+        Ok(())
+    }
+
+    /// Time to wait after [`Adxl355::start`] before readings can be trusted,
+    /// computed from the current configuration.
+    ///
+    /// This is the turn-on time (10 ms) or 16 output periods for the low pass
+    /// filter to flush, whichever is longer:
+    ///
+    /// | ODR (Hz) | Settling (ms) |
+    /// |----------|---------------|
+    /// | 4000     | 10            |
+    /// | 2000     | 10            |
+    /// | 1000     | 16            |
+    /// | 500      | 32            |
+    /// | 250      | 64            |
+    /// | 125      | 128           |
+    /// | 62.5     | 256           |
+    /// | 31.25    | 512           |
+    /// | 15.625   | 1024          |
+    /// | 7.813    | 2048          |
+    /// | 3.906    | 4096          |
+    ///
+    /// With the high pass filter enabled, 4.6 time constants of the filter
+    /// (`1 / (2π f_c)`) are added, which is seconds at the lowest corners.
+    pub fn settling_time(&self) -> Duration {
+        let odr: f32 = self.odr.into();
+        let lpf = Duration::from_secs_f32(LPF_SETTLING_PERIODS / odr).max(TURN_ON_TIME);
+        let hpf = self
+            .hpf
+            .corner_frequency(self.odr)
+            .map(|fc| {
+                Duration::from_secs_f32(HPF_SETTLING_TAUS / (2.0 * core::f32::consts::PI * fc))
+            })
+            .unwrap_or_default();
+        lpf + hpf
+    }
+
+    /// Current output data rate
+    pub fn odr(&self) -> ODR_LPF {
+        self.odr
+    }
+
+    /// Current high pass filter corner
+    pub fn hpf(&self) -> HPF_CORNER {
+        self.hpf
+    }
+
+    /// -3 dB bandwidth in Hz at the current output data rate, see
+    /// [`ODR_LPF::bandwidth_hz`]
+    pub fn bandwidth_hz(&self) -> f32 {
+        self.odr.bandwidth_hz()
+    }
+
+    /// Limits every SPI transfer to `len` bytes, command byte included, for
+    /// buses that cap the transfer length. `len` is clamped to
+    /// [`MIN_TRANSFER_LEN`] ..= [`MAX_TRANSFER_LEN`], the default.
+    ///
+    /// Longer reads are split transparently: register reads continue at the
+    /// address where the previous transfer stopped, and FIFO reads, whose
+    /// FIFO_DATA address does not auto-increment, are split on whole sample
+    /// sets. Each transfer is a separate chip select, so a
+    /// [`Adxl355::read_frame`] split in two may pair STATUS with a sample
+    /// that arrived after it was read.
+    ///
+    /// Linux spidev, the bus of `rppal`, accepts transfers up to its
+    /// `bufsiz` module parameter, 4096 bytes by default, and needs no limit.
+    /// Microcontroller HALs bound by DMA descriptor sizes and USB bridges
+    /// with fixed report sizes may.
+    pub fn set_max_transfer(&mut self, len: usize) {
+        self.max_transfer = len.clamp(MIN_TRANSFER_LEN, MAX_TRANSFER_LEN);
+    }
+
+    /// Longest SPI transfer in bytes, see [`Adxl355::set_max_transfer`]
+    pub fn max_transfer(&self) -> usize {
+        self.max_transfer
+    }
+
+    /// Sets the mounting orientation correction applied to the samples of
+    /// [`RawAccelerometer::accel_raw`], and so of [`Accelerometer::accel_norm`]
+    /// and [`Adxl355::accel_mps2`]. Default is [`AxisTransform::IDENTITY`]
+    ///
+    /// FIFO data and [`Adxl355::read_frame`] are left in sensor axes.
+    pub fn set_orientation(&mut self, orientation: AxisTransform) {
+        self.orientation = orientation;
+    }
+
+    /// Current mounting orientation correction
+    pub fn orientation(&self) -> AxisTransform {
+        self.orientation
+    }
+
+    /// Calls `hook` with the register, direction and bytes of every
+    /// transfer after it completed, failed ones included, e.g. to log a
+    /// transcript of the bus during bring-up. `None`, the default, removes
+    /// the hook, leaving one branch per transfer.
+    ///
+    /// The hook runs inside every sample read: at kHz output data rates a
+    /// hook that formats or prints each transfer can take longer than the
+    /// transfer itself and miss samples, so keep it off in production or
+    /// filter cheaply by [`Transfer::register`].
+    pub fn set_trace(&mut self, hook: Option<TraceHook>) {
+        self.trace = hook;
+    }
+
+    /// Current measurement range
+    pub fn range(&self) -> Range {
+        self.range
+    }
+
+    /// Acceleration in g per raw count at the current range, as applied by
+    /// [`Accelerometer::accel_norm`]
+    pub fn scale(&self) -> f32 {
+        1.0 / self.range.sensitivity_lsb_per_g() as f32
+    }
+
+    /// Acceleration in m/s², i.e. [`Accelerometer::accel_norm`] times
+    /// [`STANDARD_GRAVITY`]
+    pub fn accel_mps2(&mut self) -> Result<F32x3, Error<E>>
+    where
+        E: Debug,
+    {
+        let g = self.accel_norm()?;
+        Ok(F32x3::new(
+            g.x * STANDARD_GRAVITY,
+            g.y * STANDARD_GRAVITY,
+            g.z * STANDARD_GRAVITY,
+        ))
+    }
+
+    /// Acceleration in milli-g like [`Accelerometer::accel_norm`], with
+    /// integer math only for MCUs without an FPU, e.g. Cortex-M0.
+    ///
+    /// A milli-g is 256, 128 or 64 counts at the ±2, ±4 and ±8 g ranges, so
+    /// the counts are shifted right with rounding to the nearest milli-g,
+    /// halves up, without a division. The result is within 0.5 mg of
+    /// `accel_norm`, but the 3.9 to 15.6 µg resolution of the counts is
+    /// lost: use [`RawAccelerometer::accel_raw`] with your own fixed point
+    /// scale for finer steps.
+    pub fn accel_milli_g(&mut self) -> Result<I32x3, Error<E>>
+    where
+        E: Debug,
+    {
+        let raw = self.accel_raw()?;
+        // log2 of the counts per milli-g, see `Range::sensitivity_lsb_per_g`
+        let shift = match self.range {
+            Range::_2G => 8,
+            Range::_4G => 7,
+            Range::_8G => 6,
+        };
+        let milli_g = |counts: i32| (counts + (1 << (shift - 1))) >> shift;
+        Ok(I32x3::new(milli_g(raw.x), milli_g(raw.y), milli_g(raw.z)))
+    }
+
+    /// Raw counts like [`RawAccelerometer::accel_raw`] in offset binary,
+    /// as some reference tools show them: the signed 20-bit counts plus
+    /// 2^19, so -2^19 (negative full scale) reads 0, zero reads 524 288 and
+    /// 2^19 - 1 (positive full scale) reads 1 048 575 = 2^20 - 1.
+    ///
+    /// The mounting orientation is applied first. An axis it negates can
+    /// reach +2^19, which saturates at 2^20 - 1.
+    pub fn accel_raw_unsigned(&mut self) -> Result<(u32, u32, u32), Error<E>>
+    where
+        E: Debug,
+    {
+        let raw = self.accel_raw()?;
+        let max = 2 * ACCEL_MAX_I20 + 1;
+        let offset = |counts: i32| {
+            (i64::from(counts) + i64::from(ACCEL_MAX_I20) + 1).clamp(0, max.into()) as u32
+        };
+        Ok((offset(raw.x), offset(raw.y), offset(raw.z)))
+    }
+
+    /// Acceleration in g like [`Accelerometer::accel_norm`], corrected by
+    /// the gain of [`Config::sensitivity_table`] at the die temperature read
+    /// in the same burst
+    pub fn accel_norm_table_compensated(&mut self) -> Result<F32x3, Error<E>>
+    where
+        E: Debug,
+    {
+        let frame = self.read_frame()?;
+        Ok(self.compensate(&frame))
+    }
+
+    /// Reads a calibrated sample, the acceleration and die temperature read
+    /// in one burst, for applications that want clean data without wiring
+    /// the corrections themselves.
+    ///
+    /// The acceleration is in g with the mounting orientation
+    /// ([`Adxl355::set_orientation`]) and the sensitivity gain at the die
+    /// temperature ([`Config::sensitivity_table`]) applied, like
+    /// [`Adxl355::accel_norm_table_compensated`]. Offsets written to the
+    /// OFFSET registers are applied by the device itself. The temperature is
+    /// the nominal conversion of [`Adxl355::read_temp_celsius`]. Readings are
+    /// numbered from 0 in the order they were taken by this driver.
+    pub fn reading(&mut self) -> Result<Reading, Adxl355Error<E>> {
+        let frame = self.read_frame()?;
+        let index = self.readings;
+        self.readings += 1;
+        Ok(Reading {
+            index,
+            accel: self.compensate(&frame),
+            temperature: frame.temperature_celsius(),
+            fresh: frame.data_ready(),
+        })
+    }
+
+    /// Acceleration of `frame` in g, oriented and corrected by the
+    /// sensitivity gain at its temperature
+    fn compensate(&self, frame: &Frame) -> F32x3 {
+        let v = self.orientation.apply(frame.sample);
+        let scale = self.scale() / self.sensitivity.gain(frame.temperature_celsius());
+        F32x3::new(v.x as f32 * scale, v.y as f32 * scale, v.z as f32 * scale)
+    }
+
+    /// Sensitivity table applied by [`Adxl355::accel_norm_table_compensated`]
+    pub fn sensitivity_table(&self) -> SensitivityTable {
+        self.sensitivity
+    }
+
     /// Returns the raw contents of the temperature registers
-    pub fn read_temp_raw(&mut self) -> u16 {
+    pub fn read_temp_raw(&mut self) -> Result<u16, Adxl355Error<E>> {
         let mut bytes = [(Register::TEMP2.addr() << 1) | SPI_READ, 0, 0];
-        self.read(&mut bytes);
+        self.read(&mut bytes)?;
 
         let temp_h = ((bytes[1] & 0x0F) as u16) << 8;
         let temp_l = (bytes[2] as u16) & 0x00FF;
 
-        temp_h | temp_l
+        Ok(temp_h | temp_l)
+    }
+
+    /// Reads the die temperature in degrees Celsius
+    ///
+    /// The sensor is uncalibrated: the nominal intercept is 1885 LSB at 25°C
+    /// with a slope of -9.05 LSB/°C, so absolute values may be off by a few
+    /// degrees but are well suited to tracking drift.
+    pub fn read_temp_celsius(&mut self) -> Result<f32, Adxl355Error<E>> {
+        let mut bytes = [0u8; 2];
+        self.read_regs(Register::TEMP2.addr(), &mut bytes)?;
+        let raw = (((bytes[0] & 0x0F) as u16) << 8) | bytes[1] as u16;
+        Ok(temp_celsius(raw))
+    }
+
+    /// Reads STATUS, FIFO_ENTRIES, the temperature and the latest sample in
+    /// a single burst. This is the cheapest way to get a fully annotated
+    /// sample.
+    pub fn read_frame(&mut self) -> Result<Frame, Adxl355Error<E>> {
+        let mut block = [0u8; FRAME_LEN];
+        self.read_regs(Register::STATUS.addr(), &mut block)?;
+        Ok(Frame::decode(&block))
+    }
+
+    /// Reads the identification registers
+    pub fn read_ids(&mut self) -> Result<DeviceIds, Adxl355Error<E>> {
+        let mut ids = [0u8; 4];
+        self.read_regs(Register::DEVID_AD.addr(), &mut ids)?;
+        Ok(DeviceIds {
+            devid_ad: ids[0],
+            devid_mst: ids[1],
+            partid: ids[2],
+            revid: ids[3],
+        })
+    }
+
+    /// Reads the identification, configuration, power and status
+    /// registers, the FIFO level and the temperature, for bug reports.
+    ///
+    /// The sample registers are not read, so a running acquisition loses no
+    /// data, but reading STATUS clears its FIFO_OVR flag.
+    pub fn diagnostics(&mut self) -> Result<Diagnostics, Adxl355Error<E>> {
+        let ids = self.read_ids()?;
+        let config = self.dump_config()?;
+        let power_ctl = self.read_register(Register::POWER_CTL)?;
+        // STATUS, FIFO_ENTRIES, TEMP2 and TEMP1
+        let mut block = [0u8; 4];
+        self.read_regs(Register::STATUS.addr(), &mut block)?;
+        let temp_raw = (((block[2] & 0x0F) as u16) << 8) | block[3] as u16;
+        Ok(Diagnostics {
+            ids,
+            config,
+            power_ctl,
+            status: block[0],
+            fifo_entries: block[1] & 0x7F,
+            temperature: temp_celsius(temp_raw),
+        })
     }
 
     /// Get the device ID
-    pub fn get_device_id(&mut self) -> Result<u8, E> {
+    pub fn get_device_id(&mut self) -> Result<u8, Adxl355Error<E>> {
         let reg = Register::DEVID.addr();
         let mut output = [1u8];
         self.read_reg(reg, &mut output)?;
         Ok(output[0])
     }
 
-    fn write_reg(&mut self, reg: u8, value: u8) -> Result<(), E> {
+    /// Reads a single register, for settings the typed API does not cover
+    pub fn read_register(&mut self, reg: Register) -> Result<u8, Adxl355Error<E>> {
+        let mut output = [0u8];
+        self.read_reg(reg.addr(), &mut output)?;
+        Ok(output[0])
+    }
+
+    /// Writes a single register, for settings the typed API does not cover.
+    ///
+    /// The driver keeps its own copy of the range, output data rate and high
+    /// pass corner for scaling and timing: change those with
+    /// [`Adxl355::set_range`], [`Adxl355::set_odr`] and [`Adxl355::set_hpf`]
+    /// instead, or call [`Adxl355::refresh_config`] after writing FILTER or
+    /// RANGE. Most settings only take effect in standby, see
+    /// [`Adxl355::stop`].
+    pub fn write_register(&mut self, reg: Register, value: u8) -> Result<(), Adxl355Error<E>> {
+        self.write_reg(reg.addr(), value)
+    }
+
+    /// Waits the configured settling delay before a transfer
+    fn settle(&mut self) {
+        if self.settle_ns > 0 {
+            self.delay.delay_ns(self.settle_ns);
+        }
+    }
+
+    fn write_reg(&mut self, reg: u8, value: u8) -> Result<(), Adxl355Error<E>> {
+        #[cfg(feature = "defmt")]
+        defmt::trace!("adxl355: write reg {=u8:#04x} <- {=u8:#04x}", reg, value);
+        self.settle();
         let bytes = [(reg << 1) | SPI_WRITE, value];
-        self.spi.write(&bytes)?;
+        let res = self.spi.write(&bytes).map_err(Adxl355Error::Spi);
+        if let Some(hook) = self.trace {
+            hook(&Transfer {
+                direction: Direction::Write,
+                register: reg,
+                bytes: &bytes[1..],
+                ok: res.is_ok(),
+            });
+        }
+        res?;
+        #[cfg(feature = "defmt")]
+        defmt::trace!("adxl355: write reg {=u8:#04x} done", reg);
+        Ok(())
+    }
+
+    /// Reads raw samples from the FIFO into `buf`.
+    ///
+    /// Only whole x/y/z sample sets are read, so the number of bytes read is
+    /// `buf.len()` rounded down to a multiple of [`FIFO_SET_LEN`]. Returns the
+    /// number of bytes written to `buf`, or [`Adxl355Error::BufferTooSmall`]
+    /// if `buf` cannot hold a single sample set.
+    pub fn read_fifo(&mut self, buf: &mut [u8]) -> Result<usize, Adxl355Error<E>> {
+        let len = buf.len() - buf.len() % FIFO_SET_LEN;
+        if len == 0 {
+            return Err(Adxl355Error::BufferTooSmall {
+                required: FIFO_SET_LEN,
+                actual: buf.len(),
+            });
+        }
+        // FIFO_DATA does not auto-increment, every burst restarts at the same
+        // address and continues with the next sample set
+        let burst = (self.max_transfer - 1) / FIFO_SET_LEN * FIFO_SET_LEN;
+        for chunk in buf[..len].chunks_mut(burst) {
+            self.burst(Register::FIFO_DATA.addr(), chunk)?;
+        }
+        Ok(len)
+    }
+
+    /// Number of valid axis words in the FIFO (three per sample set)
+    pub fn fifo_entries(&mut self) -> Result<u8, Adxl355Error<E>> {
+        let mut output = [0u8];
+        self.read_reg(Register::FIFO_ENTRIES.addr(), &mut output)?;
+        Ok(output[0] & 0x7F)
+    }
+
+    /// Reads and decodes one sample set from the FIFO
+    pub fn read_fifo_entry(&mut self) -> Result<FifoEntry, Adxl355Error<E>> {
+        let mut set = [0u8; FIFO_SET_LEN];
+        self.read_fifo(&mut set)?;
+        Ok(FifoEntry::decode(&set))
+    }
+
+    /// Reads `buf.len()` consecutive registers starting at `reg`
+    fn read_regs(&mut self, reg: u8, buf: &mut [u8]) -> Result<(), Adxl355Error<E>> {
+        if buf.is_empty() {
+            return Err(Adxl355Error::BufferTooSmall {
+                required: 1,
+                actual: 0,
+            });
+        }
+        // register addresses auto-increment, so each burst starts where the
+        // previous one stopped
+        let burst = self.max_transfer - 1;
+        for (i, chunk) in buf.chunks_mut(burst).enumerate() {
+            self.burst(reg + (i * burst) as u8, chunk)?;
+        }
+        Ok(())
+    }
+
+    /// Reads `out.len()` bytes in a single transfer starting at `reg`
+    fn burst(&mut self, reg: u8, out: &mut [u8]) -> Result<(), Adxl355Error<E>> {
+        debug_assert!(out.len() <= BURST_LEN);
+        let mut scratch = [0u8; BURST_LEN + 1];
+        let bytes = &mut scratch[..out.len() + 1];
+        bytes[0] = (reg << 1) | SPI_READ;
+        self.read(bytes)?;
+        out.copy_from_slice(&bytes[1..]);
         Ok(())
     }
 
-    fn read_reg(&mut self, reg: u8, buffer: &mut [u8]) -> Result<(), E> {
+    fn read_reg(&mut self, reg: u8, buffer: &mut [u8]) -> Result<(), Adxl355Error<E>> {
+        debug_assert!(!buffer.is_empty());
         let mut bytes = [(reg << 1) | SPI_READ, 0];
-        self.spi.transfer_in_place(&mut bytes)?;
+        self.read(&mut bytes)?;
         buffer[0] = bytes[1];
         Ok(())
     }
 
-    fn read(&mut self, bytes: &mut [u8]) {
-        self.spi.transfer_in_place(bytes).ok();
+    /// Transfers `bytes` in place. On error the buffer holds whatever the
+    /// bus left in it, e.g. a partial reply, and must not be decoded
+    fn read(&mut self, bytes: &mut [u8]) -> Result<(), Adxl355Error<E>> {
+        // the command byte is overwritten by the transfer
+        let register = bytes[0] >> 1;
+        self.settle();
+        let res = self.spi.transfer_in_place(bytes).map_err(Adxl355Error::Spi);
+        if let Some(hook) = self.trace {
+            hook(&Transfer {
+                direction: Direction::Read,
+                register,
+                bytes: &bytes[1..],
+                ok: res.is_ok(),
+            });
+        }
+        res
     }
 }
 
-impl<SPI, E> RawAccelerometer<I32x3> for Adxl355<SPI>
+impl<SPI, D, E> RawAccelerometer<I32x3> for Adxl355<SPI, D>
 where
     SPI: SpiBus<u8, Error = E>,
+    D: DelayNs,
     E: Debug,
 {
     type Error = E;
@@ -173,33 +851,16 @@ where
     fn accel_raw(&mut self) -> Result<I32x3, Error<E>> {
         let mut bytes = [0u8; 9 + 1];
         bytes[0] = (Register::XDATA3.addr() << 1) | SPI_READ;
-        self.read(&mut bytes);
-
-        // combine 3 bytes into one i32 value
-        // right-shift with sign-extend to 20-bit
-        #[allow(clippy::double_parens)]
-        {
-            let x = (((bytes[1] as i32) << 24)
-                | ((bytes[2] as i32) << 16)
-                | ((bytes[3] & 0xF0) as i32) << 8)
-                >> 12;
-            let y = (((bytes[4] as i32) << 24)
-                | ((bytes[5] as i32) << 16)
-                | ((bytes[6] & 0xF0) as i32) << 8)
-                >> 12;
-            let z = (((bytes[7] as i32) << 24)
-                | ((bytes[8] as i32) << 16)
-                | ((bytes[9] & 0xF0) as i32) << 8)
-                >> 12;
-
-            Ok(I32x3::new(x, y, z))
-        }
+        self.read(&mut bytes)?;
+
+        Ok(self.orientation.apply(fifo::decode_sample(&bytes[1..])))
     }
 }
 
-impl<SPI, E> Accelerometer for Adxl355<SPI>
+impl<SPI, D, E> Accelerometer for Adxl355<SPI, D>
 where
     SPI: SpiBus<u8, Error = E>,
+    D: DelayNs,
     E: Debug,
 {
     type Error = E;
@@ -210,11 +871,11 @@ where
 
     fn accel_norm(&mut self) -> Result<F32x3, Error<Self::Error>> {
         let raw_data: I32x3 = self.accel_raw()?;
-        let range: f32 = self.range.into(); // range in [g], so 2, 4 or 8
+        let lsb_per_g = self.range.sensitivity_lsb_per_g() as f32;
 
-        let x = (raw_data.x as f32 / ACCEL_MAX_I20 as f32) * range;
-        let y = (raw_data.y as f32 / ACCEL_MAX_I20 as f32) * range;
-        let z = (raw_data.z as f32 / ACCEL_MAX_I20 as f32) * range;
+        let x = raw_data.x as f32 / lsb_per_g;
+        let y = raw_data.y as f32 / lsb_per_g;
+        let z = raw_data.z as f32 / lsb_per_g;
 
         Ok(F32x3::new(x, y, z))
     }
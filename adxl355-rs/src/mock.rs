@@ -0,0 +1,329 @@
+//! Simulated SPI bus for host-side testing
+
+extern crate std;
+
+use std::collections::VecDeque;
+use std::vec::Vec;
+
+use hal::spi::{ErrorKind, ErrorType, SpiBus};
+
+use crate::register::Register;
+use crate::{hal, I32x3, SPI_READ};
+
+/// Number of addressable registers (0x00 ..= RESET)
+const REG_COUNT: usize = Register::RESET as usize + 1;
+/// FIFO depth in axis words (32 sample sets)
+const FIFO_DEPTH: usize = 96;
+/// STATUS bits maintained by the simulated FIFO
+const FIFO_FULL: u8 = 0x02;
+const FIFO_OVR: u8 = 0x04;
+
+/// SPI bus backed by an in-memory register file instead of a device.
+///
+/// The registers start at their datasheet reset values, so
+/// `Adxl355::new(MockSpi::new(samples), &config)` configures the mock like a
+/// real sensor. Every read covering XDATA3 latches the next raw sample of
+/// the canned sequence into the data registers, wrapping around at the end.
+///
+/// The FIFO is empty unless [`MockSpi::fifo_rate`] is set, in which case
+/// every read covering STATUS first appends that many samples of the
+/// sequence to it, as if that many output periods had passed. FIFO_ENTRIES
+/// counts the queued axis words, STATUS reports FIFO_FULL once they reach
+/// the FIFO_SAMPLES watermark and FIFO_OVR once samples did not fit in the
+/// 96 words, until STATUS is read again.
+///
+/// Transfers longer than [`MockSpi::max_transfer`], if set, fail with
+/// [`MockSpiError::TransferTooLong`] without touching the registers. Reads
+/// cut short by [`MockSpi::short_reads`] fail with
+/// [`MockSpiError::ShortTransfer`].
+///
+/// ```
+/// use adxl355::{Accelerometer, Adxl355, Config, MockSpi, RawAccelerometer, I32x3};
+///
+/// let spi = MockSpi::new(vec![I32x3::new(1, -1, 0), I32x3::new(0, 0, 1 << 18)]);
+/// let mut accel = Adxl355::new(spi, Config::default().verify(true)).unwrap();
+/// let v = accel.accel_raw().unwrap();
+/// assert_eq!((v.x, v.y, v.z), (1, -1, 0));
+/// // 2^18 counts are half of full scale, i.e. 1.024g at the default range
+/// assert!((accel.accel_norm().unwrap().z - 1.024).abs() < 1e-5);
+/// // the sequence wraps around
+/// assert_eq!(accel.accel_raw().unwrap().x, 1);
+/// ```
+#[derive(Debug, Clone)]
+pub struct MockSpi {
+    regs: [u8; REG_COUNT],
+    samples: Vec<I32x3>,
+    next: usize,
+    /// Queued axis words, 3 bytes each with the markers in the last byte
+    fifo: VecDeque<[u8; 3]>,
+    fifo_rate: usize,
+    max_transfer: Option<usize>,
+    /// Start register and length in bytes of reads cut short
+    short_reads: Option<(u8, usize)>,
+    /// Reads left that return zeros, see [`MockSpi::powering_up`]
+    powering_up: usize,
+}
+
+/// Error of a [`MockSpi`] transfer
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MockSpiError {
+    /// A transfer was longer than [`MockSpi::max_transfer`]
+    TransferTooLong {
+        /// Length of the transfer in bytes
+        len: usize,
+        /// Longest accepted transfer
+        max: usize,
+    },
+    /// A read set by [`MockSpi::short_reads`] stopped early
+    ShortTransfer {
+        /// Length of the transfer in bytes
+        len: usize,
+        /// Bytes transferred before the fault
+        transferred: usize,
+    },
+}
+
+impl hal::spi::Error for MockSpiError {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Other
+    }
+}
+
+impl MockSpi {
+    /// Creates a bus holding the reset register values (device IDs, ±2.048g
+    /// range, 4 kHz ODR, 25°C) that plays back `samples` as raw 20-bit
+    /// counts
+    pub fn new(samples: impl IntoIterator<Item = I32x3>) -> Self {
+        let mut regs = [0u8; REG_COUNT];
+        regs[Register::DEVID_AD as usize] = 0xAD;
+        regs[Register::DEVID_MST as usize] = 0x1D;
+        regs[Register::DEVID as usize] = crate::EXPECTED_DEVICE_ID;
+        regs[Register::REVID as usize] = 0x01;
+        // 1885 LSB, the nominal reading at 25°C
+        regs[Register::TEMP2 as usize] = 0x07;
+        regs[Register::TEMP1 as usize] = 0x5D;
+        regs[Register::FIFO_SAMPLES as usize] = 0x60;
+        regs[Register::RANGE as usize] = 0x81;
+        regs[Register::POWER_CTL as usize] = 0x01;
+        Self {
+            regs,
+            samples: samples.into_iter().collect(),
+            next: 0,
+            fifo: VecDeque::new(),
+            fifo_rate: 0,
+            max_transfer: None,
+            short_reads: None,
+            powering_up: 0,
+        }
+    }
+
+    /// Current value of the register at `addr` (0 outside the register map)
+    pub fn register(&self, addr: u8) -> u8 {
+        self.regs.get(addr as usize).copied().unwrap_or_default()
+    }
+
+    /// Overrides the register at `addr`, e.g. to simulate another part or
+    /// temperature. Addresses outside the register map are ignored.
+    pub fn set_register(&mut self, addr: u8, value: u8) -> &mut Self {
+        if let Some(reg) = self.regs.get_mut(addr as usize) {
+            *reg = value;
+        }
+        self
+    }
+
+    /// Sets the number of samples appended to the FIFO on every read of
+    /// STATUS. Default is 0, the FIFO stays empty
+    pub fn fifo_rate(&mut self, samples: usize) -> &mut Self {
+        self.fifo_rate = samples;
+        self
+    }
+
+    /// Fails transfers longer than `len` bytes, like buses with a capped
+    /// transfer length. Default is no limit
+    pub fn max_transfer(&mut self, len: usize) -> &mut Self {
+        self.max_transfer = Some(len);
+        self
+    }
+
+    /// Fails every read starting at register `addr` after `len` bytes of the
+    /// transfer, the command byte included, like a bus faulting midway. The
+    /// registers are read as usual, but the bytes after `len` are left as
+    /// they were in the buffer. Default is whole transfers
+    pub fn short_reads(&mut self, addr: u8, len: usize) -> &mut Self {
+        self.short_reads = Some((addr, len));
+        self
+    }
+
+    /// Returns zeros for the next `reads` read transfers, like a part still
+    /// in power-on reset. Default is a powered up part
+    pub fn powering_up(&mut self, reads: usize) -> &mut Self {
+        self.powering_up = reads;
+        self
+    }
+
+    /// Checks a transfer of `len` bytes against the limit
+    fn check_len(&self, len: usize) -> Result<(), MockSpiError> {
+        match self.max_transfer {
+            Some(max) if len > max => Err(MockSpiError::TransferTooLong { len, max }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Number of samples latched or queued in the FIFO so far
+    pub fn samples_read(&self) -> usize {
+        self.next
+    }
+
+    /// Next sample of the sequence as three 20-bit axis words
+    fn next_words(&mut self) -> Option<[[u8; 3]; 3]> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let sample = self.samples[self.next % self.samples.len()];
+        self.next += 1;
+        let mut words = [[0u8; 3]; 3];
+        for (word, axis) in words.iter_mut().zip([sample.x, sample.y, sample.z]) {
+            let v = (axis as u32) & 0xF_FFFF;
+            *word = [(v >> 12) as u8, (v >> 4) as u8, ((v & 0x0F) << 4) as u8];
+        }
+        Some(words)
+    }
+
+    /// Loads the next sample into XDATA3 ..= ZDATA1 and flags it in STATUS
+    fn latch_sample(&mut self) {
+        if let Some(words) = self.next_words() {
+            let base = Register::XDATA3 as usize;
+            self.regs[base..base + 9].copy_from_slice(words.as_flattened());
+            self.regs[Register::STATUS as usize] |= 0x01;
+        }
+    }
+
+    /// Appends `fifo_rate` samples to the FIFO, flagging an overrun for the
+    /// ones that do not fit
+    fn fill_fifo(&mut self) {
+        for _ in 0..self.fifo_rate {
+            let Some(mut words) = self.next_words() else {
+                return;
+            };
+            if self.fifo.len() + words.len() > FIFO_DEPTH {
+                self.regs[Register::STATUS as usize] |= FIFO_OVR;
+                continue;
+            }
+            words[0][2] |= 0x01; // X-axis marker
+            self.fifo.extend(words);
+        }
+        self.update_fifo();
+    }
+
+    /// Reflects the FIFO level in FIFO_ENTRIES and STATUS
+    fn update_fifo(&mut self) {
+        let entries = self.fifo.len() as u8;
+        let watermark = self.regs[Register::FIFO_SAMPLES as usize] & 0x7F;
+        self.regs[Register::FIFO_ENTRIES as usize] = entries;
+        let status = &mut self.regs[Register::STATUS as usize];
+        if entries >= watermark.max(1) {
+            *status |= FIFO_FULL;
+        } else {
+            *status &= !FIFO_FULL;
+        }
+    }
+
+    fn read_from(&mut self, reg: u8, out: &mut [u8]) {
+        let reg = reg as usize;
+        if reg == Register::FIFO_DATA as usize {
+            // FIFO_DATA does not auto-increment, every word pops the FIFO
+            for word in out.chunks_mut(3) {
+                // empty marker
+                let next = self.fifo.pop_front().unwrap_or([0, 0, 0x02]);
+                word.copy_from_slice(&next[..word.len()]);
+            }
+            self.update_fifo();
+            return;
+        }
+        let range = reg..reg + out.len();
+        let status = Register::STATUS as usize;
+        if range.contains(&status) {
+            self.fill_fifo();
+        }
+        if range.contains(&(Register::XDATA3 as usize)) {
+            self.latch_sample();
+        }
+        for (i, byte) in out.iter_mut().enumerate() {
+            *byte = self.regs.get(reg + i).copied().unwrap_or_default();
+        }
+        if range.contains(&status) {
+            self.regs[status] &= !FIFO_OVR;
+        }
+    }
+
+    fn write_from(&mut self, reg: u8, data: &[u8]) {
+        for (i, byte) in data.iter().enumerate() {
+            self.set_register(reg.saturating_add(i as u8), *byte);
+        }
+    }
+}
+
+impl ErrorType for MockSpi {
+    type Error = MockSpiError;
+}
+
+impl SpiBus<u8> for MockSpi {
+    fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        self.check_len(words.len())?;
+        words.fill(0);
+        Ok(())
+    }
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        self.check_len(words.len())?;
+        if let Some((cmd, data)) = words.split_first() {
+            if cmd & SPI_READ == 0 {
+                self.write_from(cmd >> 1, data);
+            }
+        }
+        Ok(())
+    }
+
+    fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Self::Error> {
+        let mut bytes = Vec::from(write);
+        bytes.resize(read.len().max(write.len()), 0);
+        self.transfer_in_place(&mut bytes)?;
+        read.copy_from_slice(&bytes[..read.len()]);
+        Ok(())
+    }
+
+    fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        self.check_len(words.len())?;
+        let Some((&cmd, data)) = words.split_first() else {
+            return Ok(());
+        };
+        if cmd & SPI_READ == 0 {
+            self.write_from(cmd >> 1, data);
+            words[0] = 0;
+            return Ok(());
+        }
+        if self.powering_up > 0 {
+            self.powering_up -= 1;
+            words.fill(0);
+            return Ok(());
+        }
+        let mut read = Vec::from(data);
+        self.read_from(cmd >> 1, &mut read);
+        read.insert(0, 0);
+        let len = words.len();
+        match self.short_reads {
+            Some((addr, transferred)) if addr == cmd >> 1 && transferred < len => {
+                words[..transferred].copy_from_slice(&read[..transferred]);
+                Err(MockSpiError::ShortTransfer { len, transferred })
+            }
+            _ => {
+                words.copy_from_slice(&read);
+                Ok(())
+            }
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
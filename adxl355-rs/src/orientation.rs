@@ -0,0 +1,153 @@
+//! Mounting orientation correction
+
+use core::fmt;
+use core::str::FromStr;
+
+use crate::{I32x3, ParseConfigError};
+
+/// Sensor axis
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Axis {
+    /// X-axis
+    X,
+    /// Y-axis
+    Y,
+    /// Z-axis
+    Z,
+}
+
+impl Axis {
+    fn index(self) -> usize {
+        match self {
+            Axis::X => 0,
+            Axis::Y => 1,
+            Axis::Z => 2,
+        }
+    }
+
+    fn name(self) -> char {
+        match self {
+            Axis::X => 'x',
+            Axis::Y => 'y',
+            Axis::Z => 'z',
+        }
+    }
+}
+
+/// Maps the sensor axes to the axes of a canonical frame for sensors mounted
+/// in different orientations: each output axis is a sensor axis, optionally
+/// negated.
+///
+/// Written as the signed sensor axes of the output x, y and z, e.g. `-y,x,z`
+/// for a sensor turned 90° about z (its y axis pointing along -x), or
+/// `x,-y,-z` for one mounted upside down.
+///
+/// ```
+/// use adxl355::{Axis, AxisTransform, I32x3};
+///
+/// let v = I32x3::new(1, 2, 3);
+/// let apply = |spec: &str| {
+///     let v = spec.parse::<AxisTransform>().unwrap().apply(v);
+///     (v.x, v.y, v.z)
+/// };
+/// assert_eq!(apply("x,y,z"), (1, 2, 3));
+/// assert_eq!(apply("-y,x,z"), (-2, 1, 3));
+/// assert_eq!(apply("x,-y,-z"), (1, -2, -3));
+/// assert_eq!(apply("z, x, y"), (3, 1, 2));
+///
+/// let upside_down = AxisTransform::new([Axis::X, Axis::Y, Axis::Z], [false, true, true]);
+/// assert_eq!(upside_down.unwrap().to_string(), "x,-y,-z");
+/// // every sensor axis must be used exactly once
+/// assert!(AxisTransform::new([Axis::X, Axis::X, Axis::Z], [false; 3]).is_none());
+/// assert!("x,x,z".parse::<AxisTransform>().is_err());
+/// assert!("x,y".parse::<AxisTransform>().is_err());
+/// assert!("x,y,w".parse::<AxisTransform>().is_err());
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct AxisTransform {
+    axes: [Axis; 3],
+    negate: [bool; 3],
+}
+
+impl AxisTransform {
+    /// Sensor axes used as they are
+    pub const IDENTITY: AxisTransform = AxisTransform {
+        axes: [Axis::X, Axis::Y, Axis::Z],
+        negate: [false; 3],
+    };
+
+    /// Output x, y and z are `axes`, negated where `negate` is set. Returns
+    /// `None` unless every sensor axis is used exactly once
+    pub fn new(axes: [Axis; 3], negate: [bool; 3]) -> Option<AxisTransform> {
+        let mut used = [false; 3];
+        for axis in axes {
+            if core::mem::replace(&mut used[axis.index()], true) {
+                return None;
+            }
+        }
+        Some(AxisTransform { axes, negate })
+    }
+
+    /// Transform a vector in sensor axes
+    pub fn apply(&self, v: I32x3) -> I32x3 {
+        let v = [v.x, v.y, v.z];
+        let out = |i: usize| {
+            let value = v[self.axes[i].index()];
+            if self.negate[i] {
+                -value
+            } else {
+                value
+            }
+        };
+        I32x3::new(out(0), out(1), out(2))
+    }
+}
+
+impl Default for AxisTransform {
+    fn default() -> Self {
+        AxisTransform::IDENTITY
+    }
+}
+
+impl fmt::Display for AxisTransform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (axis, negate)) in self.axes.iter().zip(self.negate).enumerate() {
+            let sep = if i > 0 { "," } else { "" };
+            let sign = if negate { "-" } else { "" };
+            write!(f, "{sep}{sign}{}", axis.name())?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses three comma separated sensor axes, each optionally prefixed with
+/// `-` or `+`, such as `-y,x,z`
+impl FromStr for AxisTransform {
+    type Err = ParseConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut axes = [Axis::X; 3];
+        let mut negate = [false; 3];
+        let mut parts = s.split(',');
+        for i in 0..3 {
+            let part = parts.next().ok_or(ParseConfigError)?.trim();
+            let (sign, name) = match part.strip_prefix('-') {
+                Some(name) => (true, name),
+                None => (false, part.strip_prefix('+').unwrap_or(part)),
+            };
+            axes[i] = match name {
+                "x" | "X" => Axis::X,
+                "y" | "Y" => Axis::Y,
+                "z" | "Z" => Axis::Z,
+                _ => return Err(ParseConfigError),
+            };
+            negate[i] = sign;
+        }
+        if parts.next().is_some() {
+            return Err(ParseConfigError);
+        }
+        AxisTransform::new(axes, negate).ok_or(ParseConfigError)
+    }
+}
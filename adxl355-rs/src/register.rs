@@ -4,53 +4,106 @@
 
 // See datasheet https://www.analog.com/media/en/technical-documentation/data-sheets/adxl354_355.pdf
 
-#[allow(dead_code)]
+/// Register map of the ADXL355, for use with [`crate::Adxl355::read_register`]
+/// and [`crate::Adxl355::write_register`]. Multi-byte values are stored most
+/// significant byte first at the lower address.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[repr(u8)]
 pub enum Register {
+    /// Analog Devices ID, 0xAD (read only)
     DEVID_AD = 0x00,
+    /// Analog Devices MEMS ID, 0x1D (read only)
     DEVID_MST = 0x01,
     /// Device ID (Read Only)
     ///
     /// "The DEVID register holds a fixed device ID code of 0xED (355 octal)."
+    /// Called PARTID in the datasheet, see [`Register::PARTID`].
     DEVID = 0x02,
+    /// Product revision (read only)
     REVID = 0x03,
+    /// Data ready, FIFO full, FIFO overrun, activity and NVM busy flags
+    /// (read only)
     STATUS = 0x04,
+    /// Number of valid axis words in the FIFO, bits 6:0 (read only)
     FIFO_ENTRIES = 0x05,
+    /// Temperature bits 11:8 in bits 3:0 (read only)
     TEMP2 = 0x06,
+    /// Temperature bits 7:0 (read only)
     TEMP1 = 0x07,
+    /// X axis data bits 19:12 (read only)
     XDATA3 = 0x08,
+    /// X axis data bits 11:4 (read only)
     XDATA2 = 0x09,
+    /// X axis data bits 3:0 in bits 7:4 (read only)
     XDATA1 = 0x0A,
+    /// Y axis data bits 19:12 (read only)
     YDATA3 = 0x0B,
+    /// Y axis data bits 11:4 (read only)
     YDATA2 = 0x0C,
+    /// Y axis data bits 3:0 in bits 7:4 (read only)
     YDATA1 = 0x0D,
+    /// Z axis data bits 19:12 (read only)
     ZDATA3 = 0x0E,
+    /// Z axis data bits 11:4 (read only)
     ZDATA2 = 0x0F,
+    /// Z axis data bits 3:0 in bits 7:4 (read only)
     ZDATA1 = 0x10,
+    /// FIFO read port, does not auto-increment (read only)
     FIFO_DATA = 0x11,
+    /// X axis offset bits 15:8, the offset has the significance of data bits
+    /// 19:4
     OFFSET_X_H = 0x1E,
+    /// X axis offset bits 7:0
     OFFSET_X_L = 0x1F,
+    /// Y axis offset bits 15:8
     OFFSET_Y_H = 0x20,
+    /// Y axis offset bits 7:0
     OFFSET_Y_L = 0x21,
+    /// Z axis offset bits 15:8
     OFFSET_Z_H = 0x22,
+    /// Z axis offset bits 7:0
     OFFSET_Z_L = 0x23,
+    /// Axes included in activity detection, bits 2:0 for z, y, x
     ACT_EN = 0x24,
+    /// Activity threshold bits 15:8, compared to bits 18:3 of the data
     ACT_THRESH_H = 0x25,
+    /// Activity threshold bits 7:0
     ACT_THRESH_L = 0x26,
+    /// Consecutive samples above threshold needed to flag activity
     ACT_COUNT = 0x27,
+    /// High pass corner in bits 6:4, output data rate and low pass corner in
+    /// bits 3:0
     FILTER = 0x28,
+    /// FIFO watermark in samples, bits 6:0 (reset value 0x60)
     FIFO_SAMPLES = 0x29,
+    /// Interrupt pin mapping of the status flags
     INT_MAP = 0x2A,
+    /// External clock and synchronization settings
     SYNC = 0x2B,
+    /// I2C speed, interrupt polarity and measurement range in bits 1:0
+    /// (reset value 0x81)
     RANGE = 0x2C,
+    /// DRDY_OFF, TEMP_OFF and STANDBY bits (reset value 0x01, standby)
     POWER_CTL = 0x2D,
+    /// Self test force (bit 1) and enable (bit 0)
     SELF_TEST = 0x2E,
+    /// Writing 0x52 resets the device like a power cycle (write only)
     RESET = 0x2F,
 }
 
 impl Register {
+    /// Datasheet name of [`Register::DEVID`]
+    pub const PARTID: Register = Register::DEVID;
+
     /// Get register address
-    pub fn addr(self) -> u8 {
+    pub const fn addr(self) -> u8 {
         self as u8
     }
+
+    /// Whether the register only reports device state. Writes to it are
+    /// ignored by the device
+    pub const fn is_read_only(self) -> bool {
+        self.addr() <= Register::FIFO_DATA.addr()
+    }
 }
@@ -0,0 +1,94 @@
+//! Shared access to a single ADXL355
+
+extern crate std;
+
+use std::sync::{Arc, Mutex, MutexGuard, TryLockError};
+
+use crate::{
+    Accelerometer, Adxl355, Debug, DelayNs, Error, F32x3, I32x3, NoDelay, RawAccelerometer, SpiBus,
+};
+
+/// Cloneable handle to an [`Adxl355`] shared between threads or tasks.
+///
+/// Register access is serialized through an internal mutex, so a fast reader
+/// and a configuration task can both use the same device. Single-owner users
+/// should use [`Adxl355`] directly, which needs no locking.
+pub struct SharedAdxl355<SPI, D = NoDelay> {
+    inner: Arc<Mutex<Adxl355<SPI, D>>>,
+}
+
+impl<SPI, D> Clone for SharedAdxl355<SPI, D> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<SPI, D> SharedAdxl355<SPI, D> {
+    /// Wraps a driver for shared access
+    pub fn new(device: Adxl355<SPI, D>) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(device)),
+        }
+    }
+
+    /// Runs `f` with exclusive access to the device
+    pub fn with<R>(&self, f: impl FnOnce(&mut Adxl355<SPI, D>) -> R) -> R {
+        f(&mut self.lock())
+    }
+
+    /// Runs `f` with exclusive access to the device, or returns `None`
+    /// without waiting if another handle holds it
+    pub fn try_with<R>(&self, f: impl FnOnce(&mut Adxl355<SPI, D>) -> R) -> Option<R> {
+        let mut device = match self.inner.try_lock() {
+            Ok(device) => device,
+            Err(TryLockError::Poisoned(e)) => e.into_inner(),
+            Err(TryLockError::WouldBlock) => return None,
+        };
+        Some(f(&mut device))
+    }
+
+    /// Returns the driver if this is the last handle
+    pub fn try_unwrap(self) -> Result<Adxl355<SPI, D>, Self> {
+        Arc::try_unwrap(self.inner)
+            .map(|inner| inner.into_inner().unwrap_or_else(|e| e.into_inner()))
+            .map_err(|inner| Self { inner })
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Adxl355<SPI, D>> {
+        // A panic while holding the lock leaves the driver usable, registers
+        // are written in single transfers
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<SPI, D, E> RawAccelerometer<I32x3> for SharedAdxl355<SPI, D>
+where
+    SPI: SpiBus<u8, Error = E>,
+    D: DelayNs,
+    E: Debug,
+{
+    type Error = E;
+
+    fn accel_raw(&mut self) -> Result<I32x3, Error<E>> {
+        self.lock().accel_raw()
+    }
+}
+
+impl<SPI, D, E> Accelerometer for SharedAdxl355<SPI, D>
+where
+    SPI: SpiBus<u8, Error = E>,
+    D: DelayNs,
+    E: Debug,
+{
+    type Error = E;
+
+    fn sample_rate(&mut self) -> Result<f32, Error<Self::Error>> {
+        self.lock().sample_rate()
+    }
+
+    fn accel_norm(&mut self) -> Result<F32x3, Error<Self::Error>> {
+        self.lock().accel_norm()
+    }
+}
@@ -0,0 +1,56 @@
+//! Register traffic seen by the trace hook of [`crate::Adxl355::set_trace`]
+
+/// Direction of a traced transfer
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Direction {
+    /// Register read
+    Read,
+    /// Register write
+    Write,
+}
+
+/// One SPI transfer of the driver, passed to the trace hook once it
+/// completed
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Transfer<'a> {
+    /// Read or write
+    pub direction: Direction,
+    /// First register address of the transfer
+    pub register: u8,
+    /// Bytes written, or read, without the command byte. After a failed
+    /// read they hold whatever the bus left in them
+    pub bytes: &'a [u8],
+    /// The bus reported no error
+    pub ok: bool,
+}
+
+/// Trace hook, called with every transfer of the driver
+pub type TraceHook = fn(&Transfer<'_>);
+
+/// Optional [`TraceHook`] of a [`crate::Config`]
+#[derive(Copy, Clone, Debug, Default)]
+pub(crate) struct Tracer(pub(crate) Option<TraceHook>);
+
+/// Compares whether a hook is set: function addresses are not unique
+impl PartialEq for Tracer {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.is_some() == other.0.is_some()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Tracer {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "{}",
+            if self.0.is_some() {
+                "traced"
+            } else {
+                "untraced"
+            }
+        )
+    }
+}
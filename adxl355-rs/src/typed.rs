@@ -0,0 +1,249 @@
+//! Power state of an ADXL355 checked at compile time
+
+use core::marker::PhantomData;
+
+use crate::{
+    Accelerometer, Adxl355, Adxl355Error, AxisTransform, Debug, DelayNs, Error, F32x3, Frame,
+    I32x3, NoDelay, Range, RawAccelerometer, Reading, Register, SpiBus, HPF_CORNER, ODR_LPF,
+};
+
+/// [`TypedAdxl355`] state where configuration registers can be written
+#[derive(Debug, Copy, Clone)]
+pub struct Standby;
+
+/// [`TypedAdxl355`] state where samples can be read
+#[derive(Debug, Copy, Clone)]
+pub struct Measuring;
+
+/// [`Adxl355`] whose power state `S`, [`Standby`] or [`Measuring`], is part
+/// of its type.
+///
+/// The rate, filter and range can only be set in standby and samples only
+/// read while measuring; [`TypedAdxl355::start`] and [`TypedAdxl355::stop`]
+/// change the type. Drivers whose state is only known at run time go
+/// through [`PowerState`], and [`TypedAdxl355::into_dynamic`] returns the
+/// unchecked driver.
+///
+/// ```compile_fail
+/// use adxl355::{Measuring, Range, TypedAdxl355};
+/// use embedded_hal::spi::SpiBus;
+///
+/// fn reconfigure<SPI: SpiBus>(device: &mut TypedAdxl355<SPI, Measuring>) {
+///     // only in standby
+///     let _ = device.set_range(Range::_4G);
+/// }
+/// ```
+pub struct TypedAdxl355<SPI, S, D = NoDelay> {
+    device: Adxl355<SPI, D>,
+    state: PhantomData<S>,
+}
+
+/// Failed state change of a [`TypedAdxl355`], returning the driver in the
+/// state it was in
+pub struct TransitionError<T, E> {
+    /// Driver in its previous state
+    pub device: T,
+    /// Error of the register access
+    pub error: Adxl355Error<E>,
+}
+
+/// Shows the error only: drivers are not `Debug`
+impl<T, E: Debug> Debug for TransitionError<T, E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("TransitionError")
+            .field("error", &self.error)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T, E: Debug> core::fmt::Display for TransitionError<T, E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "power state change failed: {}", self.error)
+    }
+}
+
+/// Result of a state change of a [`TypedAdxl355`]
+pub type Transition<T, U, E> = Result<T, TransitionError<U, E>>;
+
+impl<SPI, S, D> TypedAdxl355<SPI, S, D> {
+    fn wrap(device: Adxl355<SPI, D>) -> Self {
+        Self {
+            device,
+            state: PhantomData,
+        }
+    }
+
+    /// Returns the unchecked driver, in the same power state
+    pub fn into_dynamic(self) -> Adxl355<SPI, D> {
+        self.device
+    }
+
+    /// Read-only access to the unchecked driver, e.g. for its configuration
+    pub fn device(&self) -> &Adxl355<SPI, D> {
+        &self.device
+    }
+}
+
+impl<SPI, S, D, E> TypedAdxl355<SPI, S, D>
+where
+    SPI: SpiBus<u8, Error = E>,
+    D: DelayNs,
+{
+    /// Mounting orientation applied to every sample, see
+    /// [`Adxl355::set_orientation`]
+    pub fn set_orientation(&mut self, orientation: AxisTransform) {
+        self.device.set_orientation(orientation);
+    }
+}
+
+impl<SPI, D, E> TypedAdxl355<SPI, Standby, D>
+where
+    SPI: SpiBus<u8, Error = E>,
+    D: DelayNs,
+{
+    /// Puts `device` in standby
+    pub fn new(mut device: Adxl355<SPI, D>) -> Transition<Self, Adxl355<SPI, D>, E> {
+        match device.stop() {
+            Ok(()) => Ok(Self::wrap(device)),
+            Err(error) => Err(TransitionError { device, error }),
+        }
+    }
+
+    /// Sets the output data rate and low pass filter
+    pub fn set_odr(&mut self, odr: ODR_LPF) -> Result<(), Adxl355Error<E>> {
+        self.device.set_odr(odr)
+    }
+
+    /// Sets the high pass filter corner
+    pub fn set_hpf(&mut self, hpf: HPF_CORNER) -> Result<(), Adxl355Error<E>> {
+        self.device.set_hpf(hpf)
+    }
+
+    /// Sets the measurement range
+    pub fn set_range(&mut self, range: Range) -> Result<(), Adxl355Error<E>> {
+        self.device.set_range(range)
+    }
+
+    /// Puts the device in measurement mode
+    pub fn start(mut self) -> Transition<TypedAdxl355<SPI, Measuring, D>, Self, E> {
+        match self.device.start() {
+            Ok(()) => Ok(TypedAdxl355::wrap(self.device)),
+            Err(error) => Err(TransitionError {
+                device: self,
+                error,
+            }),
+        }
+    }
+}
+
+impl<SPI, D, E> TypedAdxl355<SPI, Measuring, D>
+where
+    SPI: SpiBus<u8, Error = E>,
+    D: DelayNs,
+{
+    /// Puts the device in standby
+    pub fn stop(mut self) -> Transition<TypedAdxl355<SPI, Standby, D>, Self, E> {
+        match self.device.stop() {
+            Ok(()) => Ok(TypedAdxl355::wrap(self.device)),
+            Err(error) => Err(TransitionError {
+                device: self,
+                error,
+            }),
+        }
+    }
+
+    /// See [`Adxl355::accel_milli_g`]
+    pub fn accel_milli_g(&mut self) -> Result<I32x3, Error<E>>
+    where
+        E: Debug,
+    {
+        self.device.accel_milli_g()
+    }
+
+    /// See [`Adxl355::accel_raw_unsigned`]
+    pub fn accel_raw_unsigned(&mut self) -> Result<(u32, u32, u32), Error<E>>
+    where
+        E: Debug,
+    {
+        self.device.accel_raw_unsigned()
+    }
+
+    /// See [`Adxl355::reading`]
+    pub fn reading(&mut self) -> Result<Reading, Adxl355Error<E>> {
+        self.device.reading()
+    }
+
+    /// See [`Adxl355::read_frame`]
+    pub fn read_frame(&mut self) -> Result<Frame, Adxl355Error<E>> {
+        self.device.read_frame()
+    }
+
+    /// See [`Adxl355::read_temp_celsius`]
+    pub fn read_temp_celsius(&mut self) -> Result<f32, Adxl355Error<E>> {
+        self.device.read_temp_celsius()
+    }
+}
+
+impl<SPI, D, E> RawAccelerometer<I32x3> for TypedAdxl355<SPI, Measuring, D>
+where
+    SPI: SpiBus<u8, Error = E>,
+    D: DelayNs,
+    E: Debug,
+{
+    type Error = E;
+
+    fn accel_raw(&mut self) -> Result<I32x3, Error<E>> {
+        self.device.accel_raw()
+    }
+}
+
+impl<SPI, D, E> Accelerometer for TypedAdxl355<SPI, Measuring, D>
+where
+    SPI: SpiBus<u8, Error = E>,
+    D: DelayNs,
+    E: Debug,
+{
+    type Error = E;
+
+    fn sample_rate(&mut self) -> Result<f32, Error<Self::Error>> {
+        self.device.sample_rate()
+    }
+
+    fn accel_norm(&mut self) -> Result<F32x3, Error<Self::Error>> {
+        self.device.accel_norm()
+    }
+}
+
+/// [`TypedAdxl355`] in the power state read from the device, for drivers
+/// whose state is only known at run time
+pub enum PowerState<SPI, D = NoDelay> {
+    /// In standby
+    Standby(TypedAdxl355<SPI, Standby, D>),
+    /// Measuring
+    Measuring(TypedAdxl355<SPI, Measuring, D>),
+}
+
+impl<SPI, D, E> PowerState<SPI, D>
+where
+    SPI: SpiBus<u8, Error = E>,
+    D: DelayNs,
+{
+    /// Reads the power state of `device` from POWER_CTL
+    pub fn read(mut device: Adxl355<SPI, D>) -> Transition<Self, Adxl355<SPI, D>, E> {
+        match device.read_register(Register::POWER_CTL) {
+            Ok(power_ctl) if power_ctl & 0x01 != 0 => {
+                Ok(PowerState::Standby(TypedAdxl355::wrap(device)))
+            }
+            Ok(_) => Ok(PowerState::Measuring(TypedAdxl355::wrap(device))),
+            Err(error) => Err(TransitionError { device, error }),
+        }
+    }
+
+    /// Returns the unchecked driver
+    pub fn into_dynamic(self) -> Adxl355<SPI, D> {
+        match self {
+            PowerState::Standby(device) => device.into_dynamic(),
+            PowerState::Measuring(device) => device.into_dynamic(),
+        }
+    }
+}
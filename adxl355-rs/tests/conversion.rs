@@ -0,0 +1,89 @@
+//! Sample conversion of the driver over a [`MockSpi`]
+use adxl355::{
+    Accelerometer, Adxl355, AxisTransform, Config, I32x3, MockSpi, Range, RawAccelerometer,
+    SensitivityTable,
+};
+
+#[test]
+fn sequence_is_played_back_and_wraps_around() {
+    let spi = MockSpi::new(vec![I32x3::new(1, -1, 0), I32x3::new(0, 0, 1 << 18)]);
+    let mut accel = Adxl355::new(spi, Config::default().verify(true)).unwrap();
+    let v = accel.accel_raw().unwrap();
+    assert_eq!((v.x, v.y, v.z), (1, -1, 0));
+    // 2^18 counts are half of full scale, i.e. 1.024g at the default range
+    assert!((accel.accel_norm().unwrap().z - 1.024).abs() < 1e-5);
+    assert_eq!(accel.accel_raw().unwrap().x, 1);
+    assert!((accel.read_temp_celsius().unwrap() - 25.0).abs() < 1e-3);
+}
+
+#[test]
+fn orientation_is_applied_to_every_reading() {
+    let spi = MockSpi::new(vec![I32x3::new(1, -1, 0), I32x3::new(0, 0, 1 << 18)]);
+    let mut accel = Adxl355::new(spi, Config::default().verify(true)).unwrap();
+    accel.accel_raw().unwrap();
+    // a sensor mounted upside down
+    accel.set_orientation("x,-y,-z".parse().unwrap());
+    assert!((accel.accel_norm().unwrap().z + 1.024).abs() < 1e-5);
+    assert_eq!(accel.accel_raw().unwrap().y, 1);
+    accel.set_orientation(AxisTransform::IDENTITY);
+    assert_eq!(accel.accel_raw().unwrap().y, 0);
+}
+
+#[test]
+fn counts_are_scaled_by_the_range() {
+    // 64 000 counts are 0.25g, 0.5g and 1g at the three ranges
+    for (range, g) in [(Range::_2G, 0.25), (Range::_4G, 0.5), (Range::_8G, 1.0)] {
+        let spi = MockSpi::new(vec![I32x3::new(64_000, -64_000, 0)]);
+        let mut accel = Adxl355::new(spi, Config::default().range(range).verify(true)).unwrap();
+        let v = accel.accel_norm().unwrap();
+        assert_eq!((v.x, v.y, v.z), (g, -g, 0.0));
+        assert_eq!(accel.scale() * 64_000.0, g);
+        let mg = accel.accel_milli_g().unwrap();
+        let expected = (g * 1000.0) as i32;
+        assert_eq!((mg.x, mg.y, mg.z), (expected, -expected, 0));
+    }
+}
+
+#[test]
+fn milli_g_round_to_the_nearest() {
+    // halves up
+    let spi = MockSpi::new(vec![I32x3::new(383, 384, -384)]);
+    let mut accel = Adxl355::new(spi, Config::default().verify(true)).unwrap();
+    let mg = accel.accel_milli_g().unwrap();
+    assert_eq!((mg.x, mg.y, mg.z), (1, 2, -1));
+}
+
+#[test]
+fn unsigned_counts_are_offset_binary() {
+    // the signed counts plus 2^19
+    let spi = MockSpi::new(vec![
+        I32x3::new(-(1 << 19), 0, (1 << 19) - 1),
+        I32x3::new(-1, 1, 1000),
+    ]);
+    let mut accel = Adxl355::new(spi, Config::default().verify(true)).unwrap();
+    assert_eq!(accel.accel_raw_unsigned().unwrap(), (0, 524_288, 1_048_575));
+    assert_eq!(
+        accel.accel_raw_unsigned().unwrap(),
+        (524_287, 524_289, 525_288)
+    );
+    // negated negative full scale saturates
+    accel.set_orientation("-x,y,z".parse().unwrap());
+    assert_eq!(accel.accel_raw_unsigned().unwrap().0, 1_048_575);
+}
+
+#[test]
+fn sensitivity_is_corrected_for_the_temperature() {
+    // 25°C in the mock
+    let table = SensitivityTable::new(&[(0.0, 1.0), (50.0, 1.002)]).unwrap();
+    let spi = MockSpi::new(vec![I32x3::new(0, 0, 1 << 18)]);
+    let mut accel = Adxl355::new(spi, Config::default().sensitivity_table(table)).unwrap();
+    let z = accel.accel_norm_table_compensated().unwrap().z;
+    assert!((z - 1.024 / 1.001).abs() < 1e-5);
+    // the same corrections, with the temperature and a running index
+    accel.set_orientation("x,-y,-z".parse().unwrap());
+    let first = accel.reading().unwrap();
+    assert!((first.accel.z + 1.024 / 1.001).abs() < 1e-5);
+    assert!((first.temperature - 25.0).abs() < 1e-3);
+    assert!(first.fresh);
+    assert_eq!((first.index, accel.reading().unwrap().index), (0, 1));
+}
@@ -0,0 +1,99 @@
+//! FIFO reads and bus limits of the driver over a [`MockSpi`]
+use adxl355::{
+    Adxl355, Adxl355Error, Config, FifoEntry, I32x3, MockSpi, MockSpiError, RawAccelerometer,
+    Register, FIFO_SET_LEN, MIN_TRANSFER_LEN,
+};
+
+#[test]
+fn fifo_fills_up_to_32_samples() {
+    // 2 samples per STATUS read, watermark at 6 axis words
+    let mut spi = MockSpi::new(vec![I32x3::new(1, 2, 3)]);
+    spi.fifo_rate(2).set_register(0x29, 6);
+    let mut accel = Adxl355::default(spi).unwrap();
+    assert_eq!(accel.read_register(Register::STATUS).unwrap() & 0x02, 0x02);
+    assert_eq!(accel.fifo_entries().unwrap(), 6);
+    let sample = FifoEntry::Sample(I32x3::new(1, 2, 3));
+    assert_eq!(accel.read_fifo_entry().unwrap(), sample);
+    assert_eq!(accel.read_fifo_entry().unwrap(), sample);
+    assert_eq!(accel.read_fifo_entry().unwrap(), FifoEntry::Empty);
+    // the next ones are lost
+    for _ in 0..16 {
+        assert!(!accel.read_frame().unwrap().fifo_overrun());
+    }
+    assert_eq!(accel.fifo_entries().unwrap(), 96);
+    assert!(accel.read_frame().unwrap().fifo_overrun());
+    accel.read_fifo(&mut [0; 32 * FIFO_SET_LEN]).unwrap();
+    assert!(!accel.read_frame().unwrap().fifo_overrun());
+}
+
+#[test]
+fn transfers_are_split_at_the_bus_limit() {
+    let mut spi = MockSpi::new((0..32).map(|i| I32x3::new(i, -i, 1)));
+    spi.max_transfer(12).fifo_rate(32);
+    let mut accel = Adxl355::default(spi).unwrap();
+    assert!(matches!(
+        accel.read_frame(),
+        Err(Adxl355Error::Spi(MockSpiError::TransferTooLong {
+            len: 14,
+            max: 12
+        }))
+    ));
+    accel.set_max_transfer(12);
+    // STATUS to ZDATA1 in two transfers, the first of which fills the FIFO
+    assert_eq!(accel.read_frame().unwrap().fifo_entries, 96);
+    // 32 sample sets, one per transfer, in order
+    let mut buf = [0u8; 32 * FIFO_SET_LEN];
+    assert_eq!(accel.read_fifo(&mut buf).unwrap(), buf.len());
+    for (i, set) in buf.chunks(FIFO_SET_LEN).enumerate() {
+        let mut words = [0u8; FIFO_SET_LEN];
+        words.copy_from_slice(set);
+        let i = i as i32;
+        assert_eq!(
+            FifoEntry::decode(&words),
+            FifoEntry::Sample(I32x3::new(i, -i, 1))
+        );
+    }
+    assert_eq!(accel.fifo_entries().unwrap(), 0);
+    // limits below one sample set are raised to one
+    accel.set_max_transfer(2);
+    assert_eq!(accel.max_transfer(), MIN_TRANSFER_LEN);
+}
+
+#[test]
+fn fifo_buffers_hold_whole_sample_sets() {
+    let mut spi = MockSpi::new((0..32).map(|i| I32x3::new(i, -i, 1)));
+    spi.fifo_rate(32);
+    let mut accel = Adxl355::default(spi).unwrap();
+    accel.fifo_entries().unwrap();
+    assert_eq!(
+        accel.read_fifo(&mut [0; FIFO_SET_LEN + 2]).unwrap(),
+        FIFO_SET_LEN
+    );
+    for len in [FIFO_SET_LEN - 1, 0] {
+        assert!(matches!(
+            accel.read_fifo(&mut vec![0; len]),
+            Err(Adxl355Error::BufferTooSmall { required: FIFO_SET_LEN, actual }) if actual == len
+        ));
+    }
+}
+
+#[test]
+fn short_reads_are_errors_not_stale_samples() {
+    let mut spi = MockSpi::new(vec![I32x3::new(7, 8, 9)]);
+    spi.short_reads(Register::TEMP2.addr(), 2);
+    let mut accel = Adxl355::new(spi, &Config::default()).unwrap();
+    assert!(matches!(
+        accel.read_temp_raw(),
+        Err(Adxl355Error::Spi(MockSpiError::ShortTransfer {
+            len: 3,
+            transferred: 2
+        }))
+    ));
+    assert!(accel.read_temp_celsius().is_err());
+    // other reads are whole
+    assert_eq!(accel.accel_raw().unwrap().z, 9);
+    let mut spi = MockSpi::new(vec![I32x3::new(7, 8, 9)]);
+    spi.short_reads(Register::XDATA3.addr(), 5);
+    let mut accel = Adxl355::new(spi, &Config::default()).unwrap();
+    assert!(accel.accel_raw().is_err());
+}
@@ -0,0 +1,92 @@
+//! Register access and configuration of the driver over a [`MockSpi`]
+use adxl355::{
+    Accelerometer, Adxl355, Adxl355Error, Config, I32x3, MockSpi, Range, RawAccelerometer,
+    Register, HPF_CORNER, ODR_LPF,
+};
+use std::cell::Cell;
+
+#[test]
+fn registers_without_a_typed_accessor() {
+    let mut accel = Adxl355::default(MockSpi::new(vec![I32x3::new(0, 0, 1 << 18)])).unwrap();
+    accel.write_register(Register::OFFSET_X_H, 0x12).unwrap();
+    assert_eq!(accel.read_register(Register::OFFSET_X_H).unwrap(), 0x12);
+    assert_eq!(accel.read_register(Register::PARTID).unwrap(), 0xED);
+    // a range written behind the driver's back is picked up on refresh
+    accel.write_register(Register::RANGE, 0x83).unwrap();
+    assert_eq!(accel.range(), Range::_2G);
+    accel.refresh_config().unwrap();
+    assert_eq!(accel.range(), Range::_8G);
+    assert!((accel.accel_norm().unwrap().z - 4.096).abs() < 1e-5);
+}
+
+#[test]
+fn live_configuration_round_trips() {
+    let mut config = Config::default();
+    config
+        .range(Range::_4G)
+        .odr(ODR_LPF::ODR_500_Hz)
+        .hpf(HPF_CORNER::_3_862_ODR);
+    let mut accel = Adxl355::new(MockSpi::new(Vec::new()), &config).unwrap();
+    assert_eq!(Config::from_registers(&mut accel).unwrap(), config);
+    let accel = Adxl355::new(accel.release(), &Config::default()).unwrap();
+    assert_eq!(accel.range(), Range::_2G);
+    let mut accel = Adxl355::new(accel.release(), &config).unwrap();
+    let resumed = Config::from_registers(&mut accel).unwrap();
+    let accel = Adxl355::new(accel.release(), &resumed).unwrap();
+    assert_eq!(
+        (accel.range(), accel.odr()),
+        (Range::_4G, ODR_LPF::ODR_500_Hz)
+    );
+}
+
+#[test]
+fn diagnostics_snapshot_the_device() {
+    let mut config = Config::default();
+    config.range(Range::_4G);
+    let mut accel = Adxl355::new(MockSpi::new(Vec::new()), &config).unwrap();
+    accel.start().unwrap();
+    let diag = accel.diagnostics().unwrap();
+    assert!(diag.ids.is_adxl355() && diag.measuring());
+    assert_eq!((diag.config.range, diag.fifo_entries), (Range::_4G, 0));
+    assert!((diag.temperature - 25.0).abs() < 1e-3);
+}
+
+#[test]
+fn settling_delay_runs_before_every_transfer() {
+    struct Counter<'a>(&'a Cell<u32>);
+    impl embedded_hal::delay::DelayNs for Counter<'_> {
+        fn delay_ns(&mut self, ns: u32) {
+            assert_eq!(ns, 500);
+            self.0.set(self.0.get() + 1);
+        }
+    }
+    let delays = Cell::new(0);
+    let spi = MockSpi::new(vec![I32x3::new(1, 2, 3)]);
+    let mut accel =
+        Adxl355::new_with_delay(spi, &Config::default(), Counter(&delays), 500).unwrap();
+    // identification read and the FILTER and RANGE writes
+    assert_eq!(delays.get(), 3);
+    assert_eq!(accel.accel_raw().unwrap().z, 3);
+    assert_eq!(delays.get(), 4);
+}
+
+#[test]
+fn other_parts_are_rejected() {
+    let mut spi = MockSpi::new(Vec::new());
+    spi.set_register(0x02, 0x00);
+    assert!(matches!(
+        Adxl355::default(spi),
+        Err(Adxl355Error::UnexpectedIds(_))
+    ));
+}
+
+#[test]
+fn parts_powering_up_are_retried_if_configured() {
+    // with the delay of `new_with_delay` between attempts
+    let mut spi = MockSpi::new(Vec::new());
+    spi.powering_up(2);
+    assert!(Adxl355::default(spi.clone()).is_err());
+    let accel = Adxl355::new(spi.clone(), Config::default().id_attempts(3, 1000)).unwrap();
+    assert_eq!(accel.id_attempts(), 3);
+    assert!(Adxl355::new(spi, Config::default().id_attempts(2, 1000)).is_err());
+}
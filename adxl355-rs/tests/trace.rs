@@ -0,0 +1,31 @@
+//! Trace hook of the driver over a [`MockSpi`]
+use adxl355::{Adxl355, Config, Direction, I32x3, MockSpi, RawAccelerometer, Register, Transfer};
+use std::sync::Mutex;
+
+/// Direction, register, bytes and outcome of a transfer
+type Record = (Direction, u8, Vec<u8>, bool);
+
+#[test]
+fn transcript_covers_construction_and_failed_reads() {
+    static TRANSCRIPT: Mutex<Vec<Record>> = Mutex::new(Vec::new());
+    fn record(t: &Transfer<'_>) {
+        TRANSCRIPT
+            .lock()
+            .unwrap()
+            .push((t.direction, t.register, t.bytes.to_vec(), t.ok));
+    }
+    let mut spi = MockSpi::new(vec![I32x3::new(1, 2, 3)]);
+    spi.short_reads(Register::TEMP2.addr(), 2);
+    let mut accel = Adxl355::new(spi, Config::default().trace(record)).unwrap();
+    assert!(accel.read_temp_raw().is_err());
+    accel.set_trace(None);
+    accel.accel_raw().unwrap();
+    let transcript = TRANSCRIPT.lock().unwrap();
+    // identification, FILTER and RANGE, then the failed read
+    assert_eq!(transcript.len(), 4);
+    assert_eq!((transcript[0].0, transcript[0].1), (Direction::Read, 0x00));
+    assert_eq!(transcript[0].2[..3], [0xAD, 0x1D, 0xED]);
+    assert_eq!(transcript[1], (Direction::Write, 0x28, vec![0x0A], true));
+    assert_eq!(transcript[2], (Direction::Write, 0x2C, vec![0x01], true));
+    assert_eq!((transcript[3].1, transcript[3].3), (0x06, false));
+}
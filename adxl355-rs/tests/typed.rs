@@ -0,0 +1,28 @@
+//! Power state typestate of the driver over a [`MockSpi`]
+use adxl355::{Accelerometer, Adxl355, I32x3, MockSpi, PowerState, Range, TypedAdxl355, ODR_LPF};
+
+#[test]
+fn configured_in_standby_read_while_measuring() {
+    let spi = MockSpi::new(vec![I32x3::new(64_000, 0, 0)]);
+    let mut standby = TypedAdxl355::new(Adxl355::default(spi).unwrap()).unwrap();
+    standby.set_range(Range::_8G).unwrap();
+    let mut measuring = standby.start().unwrap();
+    assert_eq!(measuring.accel_norm().unwrap().x, 1.0);
+    let mut standby = measuring.stop().unwrap();
+    standby.set_odr(ODR_LPF::ODR_4000_Hz).unwrap();
+    let accel = standby.into_dynamic();
+    assert!(matches!(
+        PowerState::read(accel).unwrap(),
+        PowerState::Standby(_)
+    ));
+}
+
+#[test]
+fn unknown_power_state_is_checked_at_run_time() {
+    let mut accel = Adxl355::default(MockSpi::new(Vec::new())).unwrap();
+    accel.start().unwrap();
+    let PowerState::Measuring(measuring) = PowerState::read(accel).unwrap() else {
+        panic!("started");
+    };
+    assert_eq!(measuring.device().range(), Range::_2G);
+}
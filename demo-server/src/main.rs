@@ -3,11 +3,18 @@ use std::{
         Arc,
         atomic::{AtomicBool, Ordering},
     },
-    time::Instant,
+    time::Duration,
 };
 
-use accel_data::{AccelData, tcp_server};
+use accel_data::{
+    AxisMask, SampleSource, ServerConfig, SyntheticSource, deadband_sink, tcp_server,
+};
+use adxl355::Range;
 use clap::Parser;
+
+mod signal;
+use signal::{Excursion, Generator, Signal, SignalConfig};
+
 /// Program to forward serial port over TCP
 #[derive(Parser, Debug)]
 #[command(version, about, long_about)]
@@ -20,6 +27,44 @@ struct Args {
     )]
     /// Network port to listen on to send commands
     port: u16,
+    #[arg(long)]
+    /// Deadband threshold in g, as `--deadband` of accel-daemon
+    deadband: Option<f32>,
+    #[arg(long, default_value = "1000")]
+    /// Deadband keepalive in milliseconds, as `--keepalive-ms` of accel-daemon
+    keepalive_ms: u64,
+    #[arg(long, default_value = "xyz")]
+    /// Axes forwarded to clients, e.g. `xz`. Disabled axes are omitted from
+    /// TCP data frames
+    axes: AxisMask,
+    #[arg(long, default_value = "sine")]
+    /// Generated signal: `sine`, `sweep` (linear chirp), `noise` (Gaussian),
+    /// `step` (square wave) or `impulse`
+    signal: Signal,
+    #[arg(long, default_value = "1.0")]
+    /// Peak value of the generated signal in g
+    amplitude: f32,
+    #[arg(long, default_value = "0.2")]
+    /// Frequency of the sine signal and start frequency of the sweep in Hz
+    frequency: f32,
+    #[arg(long, default_value = "100.0")]
+    /// End frequency of the sweep in Hz. The samples are 1 ms apart, keep
+    /// this below 500 Hz
+    sweep_to: f32,
+    #[arg(long, default_value = "10.0")]
+    /// Period of the sweep, step and impulse signals and of the over-range
+    /// excursions in seconds
+    period: f32,
+    #[arg(long, default_value = "2g")]
+    /// Simulated measurement range: `2g`, `4g` or `8g`. Samples are clipped
+    /// to its full scale, as by the sensor
+    range: Range,
+    #[arg(long)]
+    /// Inject an over-range excursion half way through every period, to test
+    /// clipping detection: `spike` (one sample on all axes), `plateau` (x for
+    /// a tenth of the period) or `alternate` (like `plateau`, rotating
+    /// through +x, -y, +z, -x, +y, -z). Excursions read as full scale
+    overrange: Option<Excursion>,
 }
 
 #[tokio::main(flavor = "multi_thread", worker_threads = 4)]
@@ -41,19 +86,66 @@ async fn main() {
     });
     // Create a broadcast channel for sending accelerometer data
     let (sink, _) = tokio::sync::broadcast::channel(100);
+    if !(args.frequency > 0.0 && args.period > 0.0) {
+        log::error!("--frequency and --period must be positive");
+        return;
+    }
     // Initialize dummy data source
-    let gen_task1 = tokio::spawn(generate_dummy_data(
-        1289, // Dummy index
-        running.clone(),
-        sink.clone(),
-    ));
-    let gen_task2 = tokio::spawn(generate_dummy_data(
-        1044, // Another dummy index
-        running.clone(),
-        sink.clone(),
-    ));
+    let full_scale = f32::from(args.range);
+    let signal = SignalConfig {
+        signal: args.signal,
+        amplitude: args.amplitude,
+        frequency: args.frequency,
+        sweep_to: args.sweep_to,
+        period: args.period,
+        full_scale,
+        excursion: args.overrange,
+    };
+    log::info!("Generating {} signal, range {}", args.signal, args.range);
+    if args.amplitude > full_scale {
+        log::warn!(
+            "Amplitude {} g exceeds the {} range, samples will clip at ±{full_scale} g",
+            args.amplitude,
+            args.range
+        );
+    }
+    if let Some(excursion) = args.overrange {
+        log::info!(
+            "Injecting {excursion} over-range excursions every {} s",
+            args.period
+        );
+    }
+    let gen_task1 = tokio::spawn(
+        synthetic_source(1289, signal) // Dummy index
+            .run(sink.clone(), running.clone()),
+    );
+    let gen_task2 = tokio::spawn(
+        synthetic_source(1044, signal) // Another dummy index
+            .run(sink.clone(), running.clone()),
+    );
+    // Apply the deadband filter to the data sent to clients
+    let sink = match args.deadband {
+        Some(threshold) => deadband_sink(
+            sink,
+            threshold,
+            Duration::from_millis(args.keepalive_ms),
+            100,
+            running.clone(),
+        ),
+        None => sink,
+    };
     // Start the TCP server
-    let srv_task = tokio::spawn(tcp_server(args.port, running.clone(), sink));
+    let mut config = ServerConfig::default();
+    config.axes(args.axes);
+    let srv_task = tokio::spawn({
+        let running = running.clone();
+        async move {
+            if let Err(e) = tcp_server(args.port, running.clone(), sink, config).await {
+                log::error!("TCP server failed: {e}");
+                running.store(false, Ordering::Relaxed);
+            }
+        }
+    });
     log::info!("TCP server started on port {}", args.port);
     // Wait for the server task to finish
     while running.load(Ordering::Relaxed) {
@@ -75,38 +167,9 @@ async fn main() {
     }
 }
 
-async fn generate_dummy_data(
-    idx: u32,
-    running: Arc<AtomicBool>,
-    sink: tokio::sync::broadcast::Sender<AccelData>,
-) {
-    log::info!("Starting dummy data generation for index {idx}");
-    let root = Instant::now();
-    let mut start = None;
-    while running.load(Ordering::Relaxed) {
-        let (gap, now) = match start.take() {
-            None => {
-                let now = Instant::now();
-                start = Some(now);
-                (0, now.duration_since(root).as_secs_f32())
-            }
-            Some(past) => {
-                let now = Instant::now();
-                let elapsed = now.duration_since(past).as_micros() as u32;
-                start = Some(now);
-                (elapsed, now.duration_since(root).as_secs_f32())
-            }
-        };
-        let data = AccelData {
-            idx,
-            gap,
-            x: f32::sin(now / 5.0),
-            y: f32::cos(now / 5.0 + 1.0),
-            z: f32::tan(now / 5.0 + 2.0).clamp(-2.0, 2.0),
-        };
-        if sink.receiver_count() > 0 && sink.send(data).is_err() {
-            log::error!("Failed to send dummy data");
-        }
-        tokio::time::sleep(tokio::time::Duration::from_millis(1)).await;
-    }
+/// Source of sensor `idx` sampling `signal` every millisecond, seeded by
+/// the index
+fn synthetic_source(idx: u32, signal: SignalConfig) -> impl SampleSource {
+    let mut generator = Generator::new(signal, u64::from(idx));
+    SyntheticSource::new(idx, Duration::from_millis(1), move |t| generator.sample(t))
 }
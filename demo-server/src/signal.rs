@@ -0,0 +1,329 @@
+//! Synthetic test signals for the demo data source
+
+use std::{f32::consts::TAU, fmt, str::FromStr};
+
+/// Waveform of the generated samples
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Signal {
+    /// Sine waves at the base frequency, a third of a period apart per axis
+    #[default]
+    Sine,
+    /// Linear chirp from the base frequency to the sweep frequency, restarting
+    /// every period
+    Sweep,
+    /// Gaussian white noise with a standard deviation of a third of the
+    /// amplitude, independent per axis
+    Noise,
+    /// Square wave switching between ± amplitude every half period
+    Step,
+    /// One sample at full amplitude at the start of every period, zero
+    /// otherwise
+    Impulse,
+}
+
+impl FromStr for Signal {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "sine" => Ok(Signal::Sine),
+            "sweep" => Ok(Signal::Sweep),
+            "noise" => Ok(Signal::Noise),
+            "step" => Ok(Signal::Step),
+            "impulse" => Ok(Signal::Impulse),
+            _ => Err(format!(
+                "Invalid signal '{s}', expected sine, sweep, noise, step or impulse"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Signal::Sine => "sine",
+            Signal::Sweep => "sweep",
+            Signal::Noise => "noise",
+            Signal::Step => "step",
+            Signal::Impulse => "impulse",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Over-range excursion injected into the generated signal, to exercise
+/// clipping detection. Each starts half way through every period and drives
+/// the signal to 1.5 times the full scale, so it is reported at full scale
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Excursion {
+    /// One sample on all axes at + full scale
+    Spike,
+    /// The x-axis held at + full scale for a tenth of the period
+    Plateau,
+    /// Like `plateau`, but on x, y and z in turn with alternating sign:
+    /// +x, -y, +z, -x, +y, -z, then again
+    Alternate,
+}
+
+impl FromStr for Excursion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "spike" => Ok(Excursion::Spike),
+            "plateau" => Ok(Excursion::Plateau),
+            "alternate" => Ok(Excursion::Alternate),
+            _ => Err(format!(
+                "Invalid excursion '{s}', expected spike, plateau or alternate"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Excursion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Excursion::Spike => "spike",
+            Excursion::Plateau => "plateau",
+            Excursion::Alternate => "alternate",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Waveform parameters shared by all generated sensors
+#[derive(Debug, Clone, Copy)]
+pub struct SignalConfig {
+    pub signal: Signal,
+    /// Peak value in g
+    pub amplitude: f32,
+    /// Sine frequency and sweep start frequency in Hz
+    pub frequency: f32,
+    /// Sweep end frequency in Hz
+    pub sweep_to: f32,
+    /// Repetition period of the sweep, step and impulse signals and of the
+    /// excursions in seconds
+    pub period: f32,
+    /// Full scale of the simulated range in g, samples are clipped to it
+    pub full_scale: f32,
+    /// Injected over-range excursion, if any
+    pub excursion: Option<Excursion>,
+}
+
+/// Generator of one sensor's samples
+#[derive(Debug, Clone)]
+pub struct Generator {
+    config: SignalConfig,
+    /// xorshift64 state of the noise signal
+    rng: u64,
+    /// Time of the previous sample, to place impulses
+    last: Option<f32>,
+}
+
+impl Generator {
+    /// Create a generator, `seed` decorrelates the noise of different sensors
+    pub fn new(config: SignalConfig, seed: u64) -> Self {
+        Self {
+            config,
+            rng: seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1,
+            last: None,
+        }
+    }
+
+    /// Sample of the x, y and z axes at `t` seconds since the start
+    pub fn sample(&mut self, t: f32) -> [f32; 3] {
+        let config = self.config;
+        // time shift between the axes
+        let shift = match config.signal {
+            Signal::Sine => 1.0 / (3.0 * config.frequency),
+            _ => config.period / 3.0,
+        };
+        let last = self.last.replace(t);
+        let mut out = [0.0; 3];
+        for (axis, value) in out.iter_mut().enumerate() {
+            let t = t - axis as f32 * shift;
+            *value = match config.signal {
+                Signal::Sine => config.amplitude * (TAU * config.frequency * t).sin(),
+                Signal::Sweep => {
+                    let tau = t.rem_euclid(config.period);
+                    let rate = (config.sweep_to - config.frequency) / config.period;
+                    let phase = TAU * (config.frequency * tau + rate * tau * tau / 2.0);
+                    config.amplitude * phase.sin()
+                }
+                Signal::Noise => config.amplitude / 3.0 * self.gaussian(),
+                Signal::Step => {
+                    if t.rem_euclid(config.period) < config.period / 2.0 {
+                        config.amplitude
+                    } else {
+                        -config.amplitude
+                    }
+                }
+                Signal::Impulse => {
+                    let period = |t: f32| (t / config.period).floor();
+                    match last {
+                        Some(last) if period(last - axis as f32 * shift) != period(t) => {
+                            config.amplitude
+                        }
+                        _ => 0.0,
+                    }
+                }
+            };
+        }
+        if let Some(excursion) = config.excursion {
+            let over = 1.5 * config.full_scale;
+            // periods since the start, shifted by half a period
+            let period = |t: f32| (t / config.period - 0.5).floor();
+            let tau = (t / config.period - 0.5).rem_euclid(1.0);
+            match excursion {
+                Excursion::Spike => {
+                    if last.is_some_and(|last| period(last) != period(t)) {
+                        out = [over; 3];
+                    }
+                }
+                Excursion::Plateau => {
+                    if tau < 0.1 {
+                        out[0] = over;
+                    }
+                }
+                Excursion::Alternate => {
+                    if tau < 0.1 {
+                        let n = period(t) as i64;
+                        let sign = if n.rem_euclid(2) == 0 { 1.0 } else { -1.0 };
+                        out[n.rem_euclid(3) as usize] = sign * over;
+                    }
+                }
+            }
+        }
+        // the sensor saturates at full scale
+        out.map(|value| value.clamp(-config.full_scale, config.full_scale))
+    }
+
+    /// Uniform sample in (0, 1]
+    fn uniform(&mut self) -> f32 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        ((self.rng >> 40) as f32 + 1.0) / (1u64 << 24) as f32
+    }
+
+    /// Standard normal sample (Box-Muller)
+    fn gaussian(&mut self) -> f32 {
+        let (u, v) = (self.uniform(), self.uniform());
+        (-2.0 * u.ln()).sqrt() * (TAU * v).cos()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Full scale of the 2g range
+    const FULL_SCALE: f32 = 2.048;
+
+    fn config(signal: Signal, amplitude: f32, excursion: Option<Excursion>) -> SignalConfig {
+        SignalConfig {
+            signal,
+            amplitude,
+            frequency: 0.2,
+            sweep_to: 100.0,
+            period: 1.0,
+            full_scale: FULL_SCALE,
+            excursion,
+        }
+    }
+
+    /// `n` samples 1 ms apart, as sent by the demo server
+    fn samples(config: SignalConfig, seed: u64, n: usize) -> Vec<[f32; 3]> {
+        let mut generator = Generator::new(config, seed);
+        (0..n).map(|i| generator.sample(i as f32 * 1e-3)).collect()
+    }
+
+    #[test]
+    fn default_sine_stays_in_range_and_is_continuous() {
+        let out = samples(config(Signal::Sine, 1.0, None), 0, 10_000);
+        assert!(out.iter().flatten().all(|v| v.abs() <= 1.0 + 1e-6));
+        // at most the slope of the sine, 2π f A, over 1 ms
+        let max_step = TAU * 0.2 * 1.0 * 1e-3 * 1.01;
+        for (a, b) in out.iter().zip(&out[1..]) {
+            for axis in 0..3 {
+                assert!((b[axis] - a[axis]).abs() <= max_step, "{a:?} -> {b:?}");
+            }
+        }
+        // the axes peak a third of a period apart
+        assert!(out[1250][0] > 0.99 && out[1250 + 1667][1] > 0.99);
+    }
+
+    #[test]
+    fn every_signal_is_clipped_to_full_scale() {
+        for signal in [
+            Signal::Sine,
+            Signal::Sweep,
+            Signal::Noise,
+            Signal::Step,
+            Signal::Impulse,
+        ] {
+            let out = samples(config(signal, 3.0, None), 1, 3000);
+            assert!(
+                out.iter().flatten().all(|v| v.abs() <= FULL_SCALE),
+                "{signal}"
+            );
+        }
+    }
+
+    #[test]
+    fn noise_is_reproducible_from_its_seed() {
+        let noise = config(Signal::Noise, 1.0, None);
+        assert_eq!(samples(noise, 7, 1000), samples(noise, 7, 1000));
+        assert_ne!(samples(noise, 7, 1000), samples(noise, 8, 1000));
+        // a standard deviation of a third of the amplitude
+        let out = samples(noise, 7, 30_000);
+        let var = out.iter().flatten().map(|v| v * v).sum::<f32>() / 90_000.0;
+        assert!((var.sqrt() - 1.0 / 3.0).abs() < 0.02, "{}", var.sqrt());
+    }
+
+    #[test]
+    fn spike_is_one_sample_on_all_axes_per_period() {
+        let out = samples(config(Signal::Sine, 0.0, Some(Excursion::Spike)), 0, 3000);
+        let spikes: Vec<_> = (0..out.len()).filter(|&i| out[i][0] != 0.0).collect();
+        assert_eq!(spikes.len(), 3, "{spikes:?}");
+        for (n, &i) in spikes.iter().enumerate() {
+            // half way through every period
+            assert!(i.abs_diff(500 + 1000 * n) <= 1, "{spikes:?}");
+            assert_eq!(out[i], [FULL_SCALE; 3]);
+        }
+    }
+
+    #[test]
+    fn plateau_holds_x_for_a_tenth_of_the_period() {
+        let out = samples(config(Signal::Sine, 0.0, Some(Excursion::Plateau)), 0, 3000);
+        for n in 0..3 {
+            let period = &out[1000 * n..1000 * (n + 1)];
+            let high: Vec<_> = (0..1000).filter(|&i| period[i][0] == FULL_SCALE).collect();
+            assert!(high.len().abs_diff(100) <= 1, "{}", high.len());
+            // one run, from half way through the period
+            assert!(high[0].abs_diff(500) <= 1);
+            assert_eq!(high[high.len() - 1] - high[0], high.len() - 1);
+            assert!(period.iter().all(|v| v[1] == 0.0 && v[2] == 0.0));
+        }
+    }
+
+    #[test]
+    fn alternate_rotates_through_the_axes_and_signs() {
+        let signal = config(Signal::Sine, 0.0, Some(Excursion::Alternate));
+        let out = samples(signal, 0, 7000);
+        let expected = [
+            [FULL_SCALE, 0.0, 0.0],
+            [0.0, -FULL_SCALE, 0.0],
+            [0.0, 0.0, FULL_SCALE],
+            [-FULL_SCALE, 0.0, 0.0],
+            [0.0, FULL_SCALE, 0.0],
+            [0.0, 0.0, -FULL_SCALE],
+        ];
+        for n in 0..7 {
+            // in the middle of the excursion of period n
+            assert_eq!(out[550 + 1000 * n], expected[n % 6], "period {n}");
+            // and back to the signal after it
+            assert_eq!(out[700 + 1000 * n], [0.0; 3], "period {n}");
+        }
+    }
+}
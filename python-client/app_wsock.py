@@ -101,6 +101,9 @@ def run(addr: str, port: int):
                 except json.JSONDecodeError:
                     print(f"Received non-JSON data: {data}")
                     continue
+                if isinstance(datas, dict):  # Handshake or status record
+                    print(f"Info: {datas}")
+                    continue
                 try:
                     for data in datas:
                         try:
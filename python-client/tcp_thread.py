@@ -73,10 +73,11 @@ class DataRate:
 
 
 class TcpThread(Thread):
-    def __init__(self, host: str, port: int, queue: Queue, datasize: int = 2000):
+    def __init__(self, host: str, port: int, queue: Queue, datasize: int = 2000, token: str | None = None):
         super().__init__()
         self.host = host
         self.port = port
+        self.token = token  # shared secret of a daemon run with --auth-token
         self.queue = queue
         self.running = True
         self.datasize = datasize
@@ -97,35 +98,41 @@ class TcpThread(Thread):
             break
             
         print(f"Connected to {self.host}:{self.port}")
+        try:
+            if self.token is not None:
+                client.sendall(self.token.encode() + b'\n')
+            check_header(client)
+        except (ConnectionError, ValueError) as e:
+            print(f"Error: {e}")
+            client.close()
+            return
         last = perf_counter_ns()
         while True:
-            now = perf_counter_ns()
             try:
-                bytes = client.recv(20)
-                datarate.update(len(bytes))
-                (id, gap, x, y, z) = struct.unpack('<IIfff', bytes)
-                gap *= 1e-6 # Convert gap to seconds
-                if id not in datasets:
-                    print(f"Creating new DataBuffer: {id}, {gap}, {x}, {y}, {z}")
-                    ids.append(id)
-                    datasets[id] = DataBuffer(maxlen=self.datasize)
-                    datasets[id].append((gap, x, y, z, np.nan, np.nan, np.nan))
-                    packets[id] = 1
-                else:
-                    tstamp, x0, y0, z0, _, _, _ = datasets[id][-1]
-                    tstamp += gap
-                    dx = (x - x0) / gap
-                    dy = (y - y0) / gap
-                    dz = (z - z0) / gap
-                    datasets[id].append((tstamp, x, y, z, dx, dy, dz))
-                    packets[id] += 1
-                    if now - last > 100e6:  # If more than 100 ms since last update
-                        last = now
-                        dataframes = [(id, datasets[id].to_dataframe()) for id in ids if len(datasets[id]) > 0]
-                        if len(dataframes) > 0:
-                            self.queue.put_nowait(dataframes)
+                for (id, gap, x, y, z) in recv_samples(client, datarate):
+                    now = perf_counter_ns()
+                    gap *= 1e-6 # Convert gap to seconds
+                    if id not in datasets:
+                        print(f"Creating new DataBuffer: {id}, {gap}, {x}, {y}, {z}")
+                        ids.append(id)
+                        datasets[id] = DataBuffer(maxlen=self.datasize)
+                        datasets[id].append((gap, x, y, z, np.nan, np.nan, np.nan))
+                        packets[id] = 1
+                    else:
+                        tstamp, x0, y0, z0, _, _, _ = datasets[id][-1]
+                        tstamp += gap
+                        dx = (x - x0) / gap
+                        dy = (y - y0) / gap
+                        dz = (z - z0) / gap
+                        datasets[id].append((tstamp, x, y, z, dx, dy, dz))
+                        packets[id] += 1
+                        if now - last > 100e6:  # If more than 100 ms since last update
+                            last = now
+                            dataframes = [(id, datasets[id].to_dataframe()) for id in ids if len(datasets[id]) > 0]
+                            if len(dataframes) > 0:
+                                self.queue.put_nowait(dataframes)
             except struct.error as e:
-                print(f"Error unpacking data: {e}, received data: {bytes}") # type: ignore
+                print(f"Error unpacking data: {e}")
                 continue
             except Exception as e:
                 print(f"Error: {e}")
@@ -136,3 +143,55 @@ class TcpThread(Thread):
                 client.close()
                 break
 
+
+FRAME_DATA = 0
+STREAM_MAGIC = b'ACS'
+STREAM_VERSION = 2
+MIN_STREAM_VERSION = 1
+
+
+def check_header(client: socket.socket):
+    """Read the stream header, raising ValueError if this client does not
+    understand the stream."""
+    header = recv_exact(client, len(STREAM_MAGIC) + 1)
+    if header[:-1] != STREAM_MAGIC:
+        raise ValueError('Not an accelerometer stream')
+    if not MIN_STREAM_VERSION <= header[-1] <= STREAM_VERSION:
+        raise ValueError(f'Unsupported stream version {header[-1]}, '
+                         f'expected {MIN_STREAM_VERSION} to {STREAM_VERSION}')
+
+
+def recv_exact(client: socket.socket, size: int) -> bytes:
+    buf = b''
+    while len(buf) < size:
+        chunk = client.recv(size - len(buf))
+        if not chunk:
+            raise ConnectionError('Connection closed')
+        buf += chunk
+    return buf
+
+
+def recv_samples(client: socket.socket, datarate: DataRate):
+    """Receive one frame and return its samples as (idx, gap, x, y, z) tuples.
+
+    Frames are `kind: u8, len: u16` followed by `len` bytes of payload. Data
+    frames start with an axis mask byte (bit 0: x, 1: y, 2: z), followed by
+    records of `idx: u32, seq: u32, gap: u32, time: u64` and one f32 per
+    enabled axis. Disabled axes are returned as 0.0. Bit 3 appends a
+    temperature f32 and bit 4 a freshness u32, which are dropped. Other frame
+    kinds are skipped.
+    """
+    kind, length = struct.unpack('<BH', recv_exact(client, 3))
+    payload = recv_exact(client, length)
+    datarate.update(3 + length)
+    if kind != FRAME_DATA or length == 0:
+        return []
+    mask = payload[0]
+    axes = [bool(mask & (1 << i)) for i in range(3)]
+    fmt = '<IIIQ' + 'f' * sum(axes) + ('f' if mask & 0b1000 else '') + ('I' if mask & 0b10000 else '')
+    samples = []
+    for (id, _seq, gap, _time, *values) in struct.iter_unpack(fmt, payload[1:]):
+        values = iter(values)
+        x, y, z = [next(values) if enabled else 0.0 for enabled in axes]
+        samples.append((id, gap, x, y, z))
+    return samples